@@ -0,0 +1,16 @@
+//! Loadable DuckDB extension wrapping [`plexos2duckdb::register_table_functions`].
+//!
+//! ```sql
+//! LOAD 'plexos2duckdb.duckdb_extension';
+//! SELECT * FROM plexos_tables('Model Base Solution.zip');
+//! ```
+
+use std::error::Error;
+
+use duckdb::{Connection, duckdb_entrypoint_c_api};
+
+#[duckdb_entrypoint_c_api(ext_name = "plexos2duckdb", min_duckdb_version = "v1.2.0")]
+pub fn extension_entrypoint(con: Connection) -> Result<(), Box<dyn Error>> {
+    plexos2duckdb_lib::register_table_functions(&con).map_err(|err| format!("{err:#}"))?;
+    Ok(())
+}
@@ -1,7 +1,9 @@
-"""Python interface for the ``plexos2duckdb`` command-line tool.
+"""Python interface for ``plexos2duckdb``.
 
-This package currently provides a small subprocess-backed wrapper class for
-the installed CLI. Native Rust bindings are not exposed at this time.
+:class:`PLEXOS2DuckDB` converts solutions in-process through the native
+extension module ``plexos2duckdb._plexos2duckdb`` and uses the bundled CLI for
+inspection. The extension's ``SolutionDataset`` is also exported for direct
+access to loaded solutions and Arrow streams of their data tables.
 """
 
 from importlib.metadata import PackageNotFoundError, version
@@ -10,6 +12,11 @@ import re
 
 from .api import PLEXOS2DuckDB, PLEXOS2DuckDBError, ReportAccessor
 
+try:
+    from ._plexos2duckdb import SolutionDataset
+except ImportError:  # source checkout without `maturin develop`
+    pass
+
 
 def _version_from_pyproject() -> str:
     pyproject_path = Path(__file__).resolve().parents[1] / "pyproject.toml"
@@ -40,4 +47,10 @@ except PackageNotFoundError:
     __version__ = _version_from_pyproject()
 
 
-__all__ = ["PLEXOS2DuckDB", "PLEXOS2DuckDBError", "ReportAccessor", "__version__"]
+__all__ = [
+    "PLEXOS2DuckDB",
+    "PLEXOS2DuckDBError",
+    "ReportAccessor",
+    "SolutionDataset",
+    "__version__",
+]
@@ -0,0 +1,13 @@
+"""Entry point of the ``plexos2duckdb`` console script."""
+
+import sys
+
+from ._plexos2duckdb import main as _main
+
+
+def main() -> None:
+    sys.exit(_main(sys.argv))
+
+
+if __name__ == "__main__":
+    main()
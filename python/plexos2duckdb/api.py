@@ -9,12 +9,17 @@ from typing import Any, Callable
 import duckdb
 from rich.table import Table
 
+try:
+    from . import _plexos2duckdb as _native
+except ImportError:  # source checkout without `maturin develop`
+    _native = None
+
 
 type ReportTree = dict[str, ReportTree | str]
 
 
 class PLEXOS2DuckDBError(RuntimeError):
-    """Raised when the plexos2duckdb CLI returns an error."""
+    """Raised when a conversion or the plexos2duckdb CLI fails."""
 
 
 def _build_report_tree(view_names: list[str]) -> ReportTree:
@@ -303,44 +308,66 @@ class PLEXOS2DuckDB:
         table_name_pattern: str | None = None,
         on_event: Callable[[dict[str, Any]], None] | None = None,
     ) -> pathlib.Path:
-        input_path = self._resolve_input_path(input_path)
+        """Convert a PLEXOS solution ZIP or XML file in-process.
+
+        ``on_event`` receives the same event dicts as the CLI's
+        ``--format-diagnostics json`` stream: ``status`` messages, data table
+        progress events and a final ``completed`` event.
+        """
+        if _native is None:
+            raise PLEXOS2DuckDBError(
+                "The native plexos2duckdb extension is not built. Run `maturin develop` in `python/`."
+            )
         requested_output_path = (
             pathlib.Path(output_path) if output_path is not None else self._output_path
         )
-        args = [
-            "convert",
-            "--input",
-            str(input_path),
-            "--format-diagnostics",
-            "json",
-            "--no-progress-bar",
-        ]
-        if requested_output_path is not None:
-            args.extend(["--output", str(requested_output_path)])
-        if force:
-            args.append("--force")
-        if n_threads is not None:
-            args.extend(["--n-threads", str(n_threads)])
-        if table_name_pattern is not None:
-            args.extend(["--table-name-pattern", table_name_pattern])
-
-        if on_event is None:
-            completed = self._run_command(args)
-            events = [
-                json.loads(line)
-                for line in completed.stdout.splitlines()
-                if line.strip()
-            ]
-        else:
-            events = self._run_convert_stream(args, on_event)
+        try:
+            input_path = pathlib.Path(
+                _native.resolve_solution_file(self._resolve_input_path(input_path))
+            )
+            path = pathlib.Path(
+                _native.prepare_output_path(input_path, requested_output_path, force)
+            )
+            model_name = _native.model_name(input_path)
+        except _native.PlexosError as err:
+            raise PLEXOS2DuckDBError(str(err)) from err
+
+        def emit(event: dict[str, Any]) -> None:
+            if on_event is not None:
+                on_event(event)
+
+        def on_progress(message: str) -> None:
+            emit({"event": "status", "message": message})
+
+        log_path = input_path.parent / f"Model ( {model_name} ) Log.txt"
+        run_stats_path = input_path.parent / "runstats.json"
+        try:
+            load = (
+                _native.SolutionDataset.from_zip
+                if input_path.suffix.lower() == ".zip"
+                else _native.SolutionDataset.from_xml
+            )
+            dataset = load(
+                input_path,
+                model_name=model_name,
+                simulation_log=log_path.read_text() if log_path.exists() else None,
+                run_stats=run_stats_path.read_text() if run_stats_path.exists() else None,
+                on_progress=on_progress,
+            )
+            dataset.to_duckdb(
+                path,
+                n_threads=n_threads,
+                table_name_pattern=table_name_pattern,
+                on_progress=on_progress,
+                on_event=emit,
+            )
+        except _native.PlexosError as err:
+            raise PLEXOS2DuckDBError(str(err)) from err
 
-        for event in reversed(events):
-            if event.get("event") == "completed":
-                path = pathlib.Path(event["output"])
-                self._input_path = input_path
-                self._output_path = path
-                return path
-        raise PLEXOS2DuckDBError("Conversion did not emit a completion event")
+        emit({"event": "completed", "output": str(path)})
+        self._input_path = input_path
+        self._output_path = path
+        return path
 
     def connect(
         self, database: str | pathlib.Path | None = None, *, read_only: bool = True
@@ -387,9 +414,6 @@ class PLEXOS2DuckDB:
             "Could not find the bundled `plexos2duckdb` executable or a PATH fallback. Install the package with `pip install plexos2duckdb` or pass `executable=` explicitly."
         )
 
-    def _quote_ident(self, identifier: str) -> str:
-        return '"' + identifier.replace('"', '""') + '"'
-
     def _load_metadata(
         self, database: str | pathlib.Path | None = None
     ) -> dict[str, Any]:
@@ -417,34 +441,24 @@ class PLEXOS2DuckDB:
         try:
             rows = connection.execute(
                 """
-                SELECT table_schema, table_name, table_type
-                FROM information_schema.tables
-                WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
-                ORDER BY table_schema, table_name
+                SELECT schema_name, table_name, 'table', estimated_size
+                FROM duckdb_tables()
+                WHERE database_name = current_database()
+                UNION ALL
+                SELECT schema_name, view_name, 'view', NULL
+                FROM duckdb_views()
+                WHERE database_name = current_database() AND NOT internal
+                ORDER BY 1, 2
                 """
             ).fetchall()
             inventory = []
-            for schema, table_name, table_type in rows:
-                kind = "table" if table_type == "BASE TABLE" else "view"
-                row_count = "-"
-                if table_type == "BASE TABLE":
-                    quoted_schema = self._quote_ident(schema)
-                    quoted_table = self._quote_ident(table_name)
-                    count_row = connection.execute(
-                        f"SELECT COUNT(*) FROM {quoted_schema}.{quoted_table}"
-                    ).fetchone()
-                    if count_row is None:
-                        raise PLEXOS2DuckDBError(
-                            f"Could not count rows for {quoted_schema}.{quoted_table}"
-                        )
-                    count = count_row[0]
-                    row_count = str(count)
+            for schema, table_name, kind, estimated_size in rows:
                 inventory.append(
                     {
                         "schema": schema,
                         "table": table_name,
                         "kind": kind,
-                        "row_count": row_count,
+                        "row_count": "-" if estimated_size is None else str(estimated_size),
                     }
                 )
             return inventory
@@ -522,43 +536,6 @@ class PLEXOS2DuckDB:
             "An output DuckDB path is required for this operation. Run `convert()` first, pass `output_path=` in the constructor, or provide `database=` explicitly."
         )
 
-    def _run_convert_stream(
-        self,
-        args: list[str],
-        on_event: Callable[[dict[str, Any]], None],
-    ) -> list[dict[str, Any]]:
-        process = subprocess.Popen(
-            [str(self._executable), *args],
-            text=True,
-            stdout=subprocess.PIPE,
-            stderr=subprocess.PIPE,
-        )
-        events: list[dict[str, Any]] = []
-        stdout = process.stdout
-        if stdout is None:
-            process.kill()
-            raise PLEXOS2DuckDBError("Failed to capture conversion output")
-
-        try:
-            for line in stdout:
-                line = line.strip()
-                if not line:
-                    continue
-                event = json.loads(line)
-                events.append(event)
-                on_event(event)
-        finally:
-            stdout.close()
-
-        stderr = process.stderr.read() if process.stderr is not None else ""
-        if process.stderr is not None:
-            process.stderr.close()
-        return_code = process.wait()
-        if return_code != 0:
-            message = stderr.strip() or "Unknown error"
-            raise PLEXOS2DuckDBError(message)
-        return events
-
     def _run_command(self, args: list[str]) -> subprocess.CompletedProcess[str]:
         completed = subprocess.run(
             [str(self._executable), *args],
@@ -0,0 +1,70 @@
+"""Tests for the native ``plexos2duckdb._plexos2duckdb`` extension module.
+
+Skipped unless the extension has been built, e.g. with ``maturin develop``.
+"""
+
+from __future__ import annotations
+
+import pathlib
+
+import pytest
+
+native = pytest.importorskip("plexos2duckdb._plexos2duckdb")
+
+
+FIXTURE_ZIP = (
+    pathlib.Path(__file__).resolve().parents[2]
+    / "tests"
+    / "data"
+    / "plexos-solution-files"
+    / "Model_Base_LT_Solution.zip"
+)
+
+
+def test_missing_file_raises_plexos_error(tmp_path: pathlib.Path) -> None:
+    with pytest.raises(native.PlexosError):
+        native.SolutionDataset.from_zip(tmp_path / "missing.zip")
+
+
+def test_invalid_output_format_raises_value_error() -> None:
+    if not FIXTURE_ZIP.exists():
+        pytest.skip(f"missing fixture: {FIXTURE_ZIP}")
+    dataset = native.SolutionDataset.from_zip(FIXTURE_ZIP)
+    with pytest.raises(ValueError):
+        dataset.to_duckdb("out.duckdb", output_format="csv")
+
+
+def test_convert_reports_progress_and_streams_arrow(tmp_path: pathlib.Path) -> None:
+    if not FIXTURE_ZIP.exists():
+        pytest.skip(f"missing fixture: {FIXTURE_ZIP}")
+    pyarrow = pytest.importorskip("pyarrow")
+
+    messages: list[str] = []
+    events: list[dict] = []
+    dataset = native.SolutionDataset.from_zip(FIXTURE_ZIP, on_progress=messages.append)
+    output = tmp_path / "out.duckdb"
+    dataset.to_duckdb(output, on_progress=messages.append, on_event=events.append)
+
+    assert output.exists()
+    assert messages
+    assert {event["event"] for event in events} >= {"data_table_start", "data_table_end"}
+
+    table_name = dataset.data_table_names()[0]
+    reader = pyarrow.RecordBatchReader.from_stream(
+        dataset.data_table_reader(table_name, dimensions=True)
+    )
+    assert "timestamp" in reader.schema.names
+    assert reader.read_all().num_rows > 0
+
+
+def test_force_keeps_directories_not_written_by_a_conversion(
+    tmp_path: pathlib.Path,
+) -> None:
+    output = tmp_path / "out.duckdb"
+    output.mkdir()
+    (output / "notes.txt").write_text("keep")
+
+    with pytest.raises(native.PlexosError, match="not written by plexos2duckdb"):
+        native.prepare_output_path(tmp_path / "Model X Solution.zip", output, force=True)
+    assert (output / "notes.txt").exists()
+    assert native.model_name(tmp_path / "Model X Solution.zip") == "X"
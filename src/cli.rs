@@ -1,20 +1,24 @@
-use std::time::{Duration, Instant};
+use std::{
+    io::Write as _,
+    time::{Duration, Instant},
+};
 
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{Generator, Shell, generate};
 use color_eyre::{
     Result,
-    eyre::{ContextCompat, eyre},
+    eyre::{ContextCompat, WrapErr, eyre},
 };
 use console::Term;
-use ctrlc;
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use owo_colors::OwoColorize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tabled::{Table, Tabled, settings::Style};
 
+use crate::sink::quote_ident;
+
 #[derive(Parser)]
-#[command(author, version = plexos2duckdb::utils::version(), about, long_about = None)]
+#[command(author, version = crate::utils::version(), about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
@@ -23,9 +27,19 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Command {
     /// Convert a PLEXOS solution file to DuckDB
-    Convert(ConvertArgs),
-    /// Show operational metadata from a generated DuckDB database
+    Convert(Box<ConvertArgs>),
+    /// Show metadata from a generated DuckDB database or a solution file
     Inspect(InspectArgs),
+    /// Export report views to CSV, Parquet, JSON lines or XLSX
+    Export(ExportArgs),
+    /// Run SQL against a generated DuckDB database
+    Query(QueryArgs),
+    /// Compare two generated DuckDB databases
+    Diff(DiffArgs),
+    /// Merge generated DuckDB databases into one multi-scenario database
+    Merge(MergeArgs),
+    /// Upgrade a DuckDB database written by an older plexos2duckdb in place
+    Migrate(MigrateArgs),
     /// Generate shell completion scripts
     #[command(name = "generate-shell-completions")]
     Completions(CompletionsArgs),
@@ -37,15 +51,121 @@ pub enum OutputFormat {
     Json,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum DiffMatchBy {
+    /// Class and object name
+    Name,
+    /// PLEXOS object GUID, so renamed objects still match
+    Guid,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParquetLayout {
+    /// One directory per data table
+    Table,
+    /// Hive-style phase=/period=/collection=/property= partitions
+    Hive,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConvertFormat {
+    /// A single DuckDB database file
+    Duckdb,
+    /// A folder of Parquet files with a manifest and view definitions
+    Parquet,
+    /// A single SQLite database file
+    Sqlite,
+    /// A folder of Arrow IPC (Feather) files with a manifest
+    Arrow,
+}
+
+impl ConvertFormat {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Duckdb => "duckdb",
+            Self::Parquet => "parquet",
+            Self::Sqlite => "sqlite",
+            Self::Arrow => "arrow",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArrowCompression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl From<ArrowCompression> for crate::ArrowIpcCompression {
+    fn from(compression: ArrowCompression) -> Self {
+        match compression {
+            ArrowCompression::None => Self::None,
+            ArrowCompression::Lz4 => Self::Lz4,
+            ArrowCompression::Zstd => Self::Zstd,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DownsamplePeriod {
+    Hour,
+    Day,
+    Month,
+}
+
+impl From<DownsamplePeriod> for crate::DownsamplePeriod {
+    fn from(period: DownsamplePeriod) -> Self {
+        match period {
+            DownsamplePeriod::Hour => Self::Hour,
+            DownsamplePeriod::Day => Self::Day,
+            DownsamplePeriod::Month => Self::Month,
+        }
+    }
+}
+
+impl From<ConvertFormat> for crate::OutputFormat {
+    fn from(format: ConvertFormat) -> Self {
+        match format {
+            ConvertFormat::Duckdb => Self::DuckDb,
+            ConvertFormat::Parquet => Self::Parquet,
+            ConvertFormat::Sqlite => Self::Sqlite,
+            ConvertFormat::Arrow => Self::ArrowIpc,
+        }
+    }
+}
+
+impl From<ParquetLayout> for crate::ExternalDataParquetLayout {
+    fn from(layout: ParquetLayout) -> Self {
+        match layout {
+            ParquetLayout::Table => Self::Table,
+            ParquetLayout::Hive => Self::Hive,
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct ConvertArgs {
     /// Path to the PLEXOS solution file or folder (either XML or ZIP containing XML, or solution folder)
-    #[arg(short, long)]
-    pub input: std::path::PathBuf,
-    /// Path to the output DuckDB file (leave empty to use the same name as input)
+    #[arg(short, long, required_unless_present = "config")]
+    pub input: Option<std::path::PathBuf>,
+    /// TOML or YAML profile with convert options; flags given on the command line take precedence
+    #[arg(long)]
+    pub config: Option<std::path::PathBuf>,
+    /// Named profile from the `profiles` table of `--config`, applied over its top-level options
+    #[arg(long, requires = "config")]
+    pub profile: Option<String>,
+    /// Path to the output DuckDB or SQLite file, or Parquet or Arrow directory (leave empty to use the same name as input)
     #[arg(short, long)]
     pub output: Option<std::path::PathBuf>,
-    /// Overwrite the output DuckDB file if it already exists
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ConvertFormat::Duckdb)]
+    pub format: ConvertFormat,
+    /// Overwrite the output if it already exists
     #[arg(long, default_value_t = false)]
     pub force: bool,
     /// Print a summary of the dataset
@@ -60,9 +180,52 @@ pub struct ConvertArgs {
     /// Regex matched against generated data table names; defaults to all tables
     #[arg(long)]
     pub table_name_pattern: Option<String>,
+    /// Only write time series for objects of this class (glob, or `re:<regex>`); repeatable
+    #[arg(long = "class")]
+    pub classes: Vec<String>,
+    /// Only write time series for memberships in this collection (glob, or `re:<regex>`); repeatable
+    #[arg(long = "collection")]
+    pub collections: Vec<String>,
+    /// Only write time series for objects in this category (glob, or `re:<regex>`); repeatable
+    #[arg(long = "category")]
+    pub categories: Vec<String>,
+    /// Only write time series for objects with this name (glob, or `re:<regex>`); repeatable
+    #[arg(long = "object")]
+    pub objects: Vec<String>,
+    /// Only write time series for this property (glob, or `re:<regex>`); repeatable
+    #[arg(long = "property")]
+    pub properties: Vec<String>,
+    /// Only write sample ids in this list (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub samples: Vec<i64>,
+    /// Only write band ids in this list (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub bands: Vec<i64>,
+    /// Remove keys, memberships and objects outside the filters from the `raw` tables
+    #[arg(long, default_value_t = false)]
+    pub trim_dimensions: bool,
+    /// Only write values with a timestamp at or after this time (`YYYY-MM-DD[ HH:MM[:SS]]`)
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Only write values with a timestamp before this time (`YYYY-MM-DD[ HH:MM[:SS]]`)
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Aggregate interval data tables to this period instead of storing every interval
+    #[arg(long, value_enum)]
+    pub downsample: Option<DownsamplePeriod>,
+    /// Aggregation for a property when downsampling, as `PROPERTY=mean|sum|min|max`; repeatable.
+    /// Other properties are summed for energy and cost units (MWh, $) and averaged otherwise
+    #[arg(long = "downsample-aggregation", requires = "downsample")]
+    pub downsample_aggregations: Vec<String>,
     /// Write time series data as external Parquet files and create DuckDB views over them
     #[arg(long)]
     pub external_data_parquet_dir: Option<std::path::PathBuf>,
+    /// Directory layout for external Parquet files or `--format parquet` output
+    #[arg(long, value_enum, default_value_t = ParquetLayout::Table)]
+    pub external_data_parquet_layout: ParquetLayout,
+    /// Buffer compression for `--format arrow`; uncompressed files can be memory-mapped
+    #[arg(long, value_enum, default_value_t = ArrowCompression::None)]
+    pub arrow_compression: ArrowCompression,
     /// Output format for diagnostics and results
     #[arg(long = "format-diagnostics", value_enum, default_value_t = OutputFormat::Text)]
     pub format_diagnostics: OutputFormat,
@@ -70,14 +233,203 @@ pub struct ConvertArgs {
 
 #[derive(Parser, Debug)]
 pub struct InspectArgs {
-    /// Path to a generated DuckDB database
+    /// Path to a generated DuckDB database, or a PLEXOS solution ZIP or XML
+    /// file to inspect without converting it
     #[arg(short, long)]
     pub input: std::path::PathBuf,
+    /// Sections to show, comma separated; all sections by default
+    #[arg(long = "section", value_enum, value_delimiter = ',')]
+    pub sections: Vec<InspectSection>,
     /// Output format for inspected metadata and inventory
     #[arg(long = "format-diagnostics", value_enum, default_value_t = OutputFormat::Text)]
     pub format_diagnostics: OutputFormat,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum InspectSection {
+    /// Converter, source file and provenance metadata
+    Metadata,
+    /// First and last timestamp of each phase and period
+    Horizon,
+    /// Object counts per class
+    Classes,
+    /// Data table counts and rows per phase and period
+    DataTables,
+    /// Units and the number of properties using them
+    Units,
+    /// Samples and their weights
+    Samples,
+    /// Bands
+    Bands,
+    /// Every table and view with its row count
+    Inventory,
+    /// Key count and estimated rows of each data table (solution files only)
+    Tables,
+    /// BIN files with their compression and size (solution files only)
+    BinFiles,
+}
+
+impl InspectArgs {
+    fn shows(&self, section: InspectSection) -> bool {
+        self.sections.is_empty() || self.sections.contains(&section)
+    }
+
+    fn reject_sections(&self, sections: &[InspectSection], input: &str) -> Result<()> {
+        if let Some(section) = self
+            .sections
+            .iter()
+            .find(|section| sections.contains(section))
+        {
+            let name = section
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            return Err(eyre!("--section {name} is not available for {input}"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+    Jsonl,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+            Self::Jsonl => "jsonl",
+            Self::Xlsx => "xlsx",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportArgs {
+    /// Path to a generated DuckDB database
+    #[arg(short, long)]
+    pub input: std::path::PathBuf,
+    /// View to export as `schema.name`; glob patterns (`*`, `?`) are allowed and the schema
+    /// defaults to `report`
+    #[arg(long, required = true)]
+    pub view: Vec<String>,
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub format: ExportFormat,
+    /// Output file, or output directory when more than one view is selected
+    #[arg(short, long)]
+    pub out: std::path::PathBuf,
+    /// Only export rows for these object names
+    #[arg(long)]
+    pub object: Vec<String>,
+    /// Only export rows for these object categories
+    #[arg(long)]
+    pub category: Vec<String>,
+    /// Only export rows with a timestamp at or after this time
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Only export rows with a timestamp before this time
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Overwrite output files if they already exist
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum QueryFormat {
+    Table,
+    Jsonl,
+    Csv,
+    Parquet,
+}
+
+#[derive(Parser, Debug)]
+pub struct QueryArgs {
+    /// Path to a generated DuckDB database; without it, queries run in memory
+    /// against solution files via `read_plexos()` and `plexos_tables()`
+    #[arg(short, long)]
+    pub input: Option<std::path::PathBuf>,
+    /// SQL to run
+    #[arg(required_unless_present = "file", conflicts_with = "file")]
+    pub sql: Option<String>,
+    /// Read the SQL to run from a file
+    #[arg(long)]
+    pub file: Option<std::path::PathBuf>,
+    /// Output format for query results
+    #[arg(long, value_enum, default_value_t = QueryFormat::Table)]
+    pub format: QueryFormat,
+    /// Write results to a file instead of stdout (required for Parquet)
+    #[arg(short, long)]
+    pub out: Option<std::path::PathBuf>,
+    /// Maximum number of rows to return
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Open the database for writing instead of read-only
+    #[arg(long, default_value_t = false)]
+    pub read_write: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct DiffArgs {
+    /// Path to the base DuckDB database
+    #[arg(long)]
+    pub base: std::path::PathBuf,
+    /// Path to the DuckDB database compared against the base
+    #[arg(long)]
+    pub other: std::path::PathBuf,
+    /// Number of objects with the largest value differences to report per data table
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+    /// How objects are matched between the databases; defaults to `guid` when both
+    /// have schema version 3 or later and `name` otherwise
+    #[arg(long, value_enum)]
+    pub match_by: Option<DiffMatchBy>,
+    /// Write the materialized `diff.*` tables to this DuckDB file
+    #[arg(long)]
+    pub out: Option<std::path::PathBuf>,
+    /// Overwrite the `--out` database if it already exists
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+    /// Output format for the diff report
+    #[arg(long = "format-diagnostics", value_enum, default_value_t = OutputFormat::Text)]
+    pub format_diagnostics: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct MergeArgs {
+    /// Paths to generated DuckDB databases, one per scenario
+    #[arg(short, long, required = true, num_args = 1..)]
+    pub input: Vec<std::path::PathBuf>,
+    /// Scenario name for each input, in order (defaults to the input file stem)
+    #[arg(long)]
+    pub scenario: Vec<String>,
+    /// Path to the merged DuckDB file
+    #[arg(short, long)]
+    pub output: std::path::PathBuf,
+    /// Overwrite the output DuckDB file if it already exists
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+    /// Merge databases written by different plexos2duckdb releases
+    #[arg(long, default_value_t = false)]
+    pub allow_version_mismatch: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct MigrateArgs {
+    /// Path to a generated DuckDB database; it is modified in place
+    #[arg(short, long)]
+    pub input: std::path::PathBuf,
+    /// Output format for the migration summary
+    #[arg(long = "format-diagnostics", value_enum, default_value_t = OutputFormat::Text)]
+    pub format_diagnostics: OutputFormat,
+}
+
 #[derive(Parser, Debug)]
 pub struct CompletionsArgs {
     /// Shell to generate completion script for
@@ -89,8 +441,35 @@ pub struct CompletionsArgs {
 struct DatabaseMetadata {
     database: String,
     converter_version: String,
+    /// 1 for databases written before `schema_version` was recorded
+    schema_version: u32,
     source_file: String,
     model_name: String,
+    /// How the database was produced; empty for databases written before
+    /// provenance was recorded
+    #[serde(skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    provenance: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+/// `main.plexos2duckdb` keys written by the "Recording provenance" convert step,
+/// with their label in `inspect`. `convert_options` and `step_timings` hold JSON.
+const PROVENANCE_KEYS: &[(&str, &str)] = &[
+    ("plexos_file_sha256", "source sha256"),
+    ("plexos_file_size_bytes", "source size (bytes)"),
+    ("plexos_file_modified_at", "source modified at"),
+    ("host", "host"),
+    ("elapsed_seconds", "elapsed (s)"),
+    ("tables_written", "tables written"),
+    ("rows_written", "rows written"),
+    ("convert_options", "convert options"),
+    ("convert_config", "convert config"),
+    ("step_timings", "step timings"),
+];
+
+#[derive(Debug, Clone, PartialEq, Tabled)]
+struct StepTimingRow {
+    step: String,
+    seconds: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Tabled)]
@@ -107,6 +486,77 @@ struct TableInventoryRow {
     row_count: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct HorizonRow {
+    phase: String,
+    period: String,
+    first: String,
+    last: String,
+    blocks: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct ClassCountRow {
+    class: String,
+    objects: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct DataTableGroupRow {
+    phase: String,
+    period: String,
+    tables: usize,
+    /// Sum of the known row counts, suffixed with `+` when some are unknown
+    rows: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct UnitRow {
+    unit: String,
+    properties: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct SampleRow {
+    sample_id: i64,
+    sample: String,
+    weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct BandRow {
+    band_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct SolutionMetadata {
+    solution: String,
+    model_name: String,
+    data_tables: usize,
+    estimated_rows: u128,
+    /// Uncompressed size of the estimated rows in the `data` schema
+    estimated_data_bytes: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct DataTableEstimateRow {
+    phase: String,
+    period: String,
+    collection: String,
+    property: String,
+    unit: String,
+    keys: usize,
+    estimated_rows: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Tabled, Serialize)]
+struct BinFileRow {
+    file: String,
+    storage: String,
+    size_bytes: u64,
+    stored_size_bytes: u64,
+}
+
 #[derive(Debug, Serialize)]
 struct ConvertJsonEnvelope {
     timestamp: String,
@@ -155,12 +605,110 @@ enum ConvertJsonEvent {
     Completed {
         output: String,
     },
+    Error {
+        message: String,
+        // structured `crate::Error` found in the error chain, if any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<serde_json::Value>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffTableRow {
+    status: String,
+    schema: String,
+    table: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffObjectRow {
+    status: String,
+    class: String,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffMembershipRow {
+    status: String,
+    collection: String,
+    parent: String,
+    child: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffUnitRow {
+    collection: String,
+    property: String,
+    base_unit: String,
+    other_unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffDataRow {
+    table: String,
+    matched_rows: i64,
+    only_base_rows: i64,
+    only_other_rows: i64,
+    max_abs_delta: f64,
+    total_delta: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Tabled, Serialize)]
+struct DiffTopObjectRow {
+    table: String,
+    object: String,
+    total_abs_delta: f64,
+    total_delta: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct DiffJsonOutput {
+    base: String,
+    other: String,
+    tables: Vec<DiffTableRow>,
+    objects: Vec<DiffObjectRow>,
+    memberships: Vec<DiffMembershipRow>,
+    units: Vec<DiffUnitRow>,
+    data: Vec<DiffDataRow>,
+    top_objects: Vec<DiffTopObjectRow>,
 }
 
 #[derive(Debug, Serialize)]
 struct InspectJsonOutput {
-    metadata: DatabaseMetadata,
-    inventory: Vec<TableInventoryRow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<DatabaseMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    horizon: Option<Vec<HorizonRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    classes: Option<Vec<ClassCountRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_tables: Option<Vec<DataTableGroupRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    units: Option<Vec<UnitRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    samples: Option<Vec<SampleRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bands: Option<Vec<BandRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inventory: Option<Vec<TableInventoryRow>>,
+}
+
+#[derive(Debug, Serialize)]
+struct InspectSolutionJsonOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<SolutionMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    horizon: Option<Vec<HorizonRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    classes: Option<Vec<ClassCountRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_tables: Option<Vec<DataTableGroupRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tables: Option<Vec<DataTableEstimateRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    units: Option<Vec<UnitRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bin_files: Option<Vec<BinFileRow>>,
 }
 
 fn print_json<T: Serialize>(value: &T) -> Result<()> {
@@ -175,7 +723,17 @@ fn print_convert_json_event(event: ConvertJsonEvent) -> Result<()> {
     })
 }
 
-fn resolve_input_path(input: &std::path::Path) -> Result<std::path::PathBuf> {
+fn convert_json_error(err: &color_eyre::Report) -> ConvertJsonEvent {
+    ConvertJsonEvent::Error {
+        message: err.to_string(),
+        error: err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<crate::Error>())
+            .and_then(|error| serde_json::to_value(error).ok()),
+    }
+}
+
+pub(crate) fn resolve_input_path(input: &std::path::Path) -> Result<std::path::PathBuf> {
     let path = if input.is_file() {
         let ext = input
             .extension()
@@ -192,7 +750,7 @@ fn resolve_input_path(input: &std::path::Path) -> Result<std::path::PathBuf> {
             .map(|e| e.path())
             .filter(|p| {
                 p.extension()
-                    .map_or(false, |ext| ext.eq_ignore_ascii_case("zip"))
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
             })
             .collect::<Vec<_>>();
 
@@ -221,158 +779,2452 @@ fn resolve_input_path(input: &std::path::Path) -> Result<std::path::PathBuf> {
 fn resolve_output_path(
     input: &std::path::Path,
     output: Option<std::path::PathBuf>,
+    format: ConvertFormat,
     force: bool,
 ) -> Result<std::path::PathBuf> {
+    let output_path = default_output_path(input, output, format);
+    remove_existing_output(&output_path, force, "Re-run with `--force`")?;
+    Ok(output_path)
+}
+
+/// Model name from a `Model <name> Solution.zip` or `.xml` file name.
+pub(crate) fn model_name(input_path: &std::path::Path) -> Result<String> {
+    let file_name = input_path
+        .file_name()
+        .context("File name must exist")?
+        .to_str()
+        .context("File name must be valid UTF-8")?;
+    Ok(file_name
+        .trim_start_matches("Model ")
+        .trim_end_matches(" Solution")
+        .trim_end_matches(" Solution.zip")
+        .trim_end_matches(" Solution.xml")
+        .to_string())
+}
+
+/// `output`, or `input` with the extension of `format`. DuckDB and SQLite
+/// paths without an extension get one.
+pub(crate) fn default_output_path(
+    input: &std::path::Path,
+    output: Option<std::path::PathBuf>,
+    format: ConvertFormat,
+) -> std::path::PathBuf {
+    let extension = format.name();
     let output_path = if let Some(output_path) = output {
         output_path
     } else {
-        input.with_extension("duckdb")
+        input.with_extension(extension)
     };
-    let output_path = if output_path.extension().is_none() {
-        output_path.with_extension("duckdb")
+    if matches!(format, ConvertFormat::Duckdb | ConvertFormat::Sqlite)
+        && output_path.extension().is_none()
+    {
+        output_path.with_extension(extension)
     } else {
         output_path
-    };
-    if output_path.exists() {
-        if !force {
+    }
+}
+
+/// Remove an existing output at `path` when `force` is set. Directories are
+/// only removed if a conversion wrote them; `force_hint` names the option.
+pub(crate) fn remove_existing_output(
+    output_path: &std::path::Path,
+    force: bool,
+    force_hint: &str,
+) -> Result<()> {
+    if !output_path.exists() {
+        return Ok(());
+    }
+    if !force {
+        return Err(eyre!(
+            "Output file already exists: \"{}\". {force_hint} to overwrite it",
+            output_path.display()
+        ));
+    }
+    if output_path.is_dir() {
+        if !is_converted_dataset_dir(output_path) {
             return Err(eyre!(
-                "Output file already exists: \"{}\". Re-run with `--force` to overwrite it",
-                output_path.display().to_string().bold()
+                "Output path is a directory that was not written by plexos2duckdb: \"{}\". Only Parquet or Arrow IPC output folders are replaced",
+                output_path.display()
             ));
         }
-        std::fs::remove_file(&output_path)?;
+        std::fs::remove_dir_all(output_path)?;
+    } else {
+        std::fs::remove_file(output_path)?;
     }
-    Ok(output_path)
+    Ok(())
 }
 
-fn quote_ident(identifier: &str) -> String {
-    format!("\"{}\"", identifier.replace('"', "\"\""))
+/// Whether `dir` holds a `manifest.json` written by a Parquet or Arrow IPC
+/// conversion, so `--force` may remove it.
+fn is_converted_dataset_dir(dir: &std::path::Path) -> bool {
+    std::fs::read_to_string(dir.join("manifest.json"))
+        .ok()
+        .and_then(|manifest| serde_json::from_str::<serde_json::Value>(&manifest).ok())
+        .is_some_and(|manifest| manifest.get("plexos2duckdb_version").is_some())
 }
 
-fn metadata_value(
-    metadata: &std::collections::HashMap<String, String>,
-    key: &str,
-) -> Result<String> {
-    metadata
-        .get(key)
-        .cloned()
-        .ok_or_else(|| eyre!("Missing metadata key in main.plexos2duckdb: {key}"))
+fn data_key_filter(args: &ConvertArgs) -> Result<Option<crate::DataKeyFilter>> {
+    let compile = |patterns: &[String]| {
+        patterns
+            .iter()
+            .map(|pattern| crate::DataKeyFilter::pattern(pattern))
+            .collect::<Result<Vec<_>>>()
+    };
+    let filter = crate::DataKeyFilter {
+        classes: compile(&args.classes)?,
+        collections: compile(&args.collections)?,
+        categories: compile(&args.categories)?,
+        objects: compile(&args.objects)?,
+        properties: compile(&args.properties)?,
+        samples: args.samples.clone(),
+        bands: args.bands.clone(),
+        trim_dimensions: args.trim_dimensions,
+    };
+    if filter.is_empty() {
+        if args.trim_dimensions {
+            return Err(eyre!(
+                "`--trim-dimensions` requires at least one of `--class`, `--collection`, `--category`, `--object`, `--property`, `--samples` or `--bands`"
+            ));
+        }
+        return Ok(None);
+    }
+    Ok(Some(filter))
 }
 
-fn load_database_metadata(con: &duckdb::Connection) -> Result<DatabaseMetadata> {
-    let mut stmt = con.prepare("SELECT key, value FROM main.plexos2duckdb")?;
-    let rows = stmt.query_map([], |row| {
-        Ok((
-            row.get::<_, String>(0)?,
-            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
-        ))
-    })?;
-
-    let mut metadata = std::collections::HashMap::new();
-    for row in rows {
-        let (key, value) = row?;
-        metadata.insert(key, value);
+fn parse_convert_datetime(value: &str, flag: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime.and_utc());
+        }
     }
-
-    Ok(DatabaseMetadata {
-        database: String::new(),
-        converter_version: metadata_value(&metadata, "plexos2duckdb_version")?,
-        source_file: metadata_value(&metadata, "plexos_file")?,
-        model_name: metadata_value(&metadata, "model_name")?,
-    })
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|_| eyre!("Invalid `{flag}` value {value:?}; expected `YYYY-MM-DD[ HH:MM[:SS]]`"))
 }
 
-fn load_table_inventory(con: &duckdb::Connection) -> Result<Vec<TableInventoryRow>> {
-    let mut stmt = con.prepare(
-        "
-        SELECT table_schema, table_name, table_type
-        FROM information_schema.tables
-        WHERE table_schema NOT IN ('information_schema', 'pg_catalog')
-        ORDER BY table_schema, table_name
-        ",
-    )?;
-    let tables = stmt.query_map([], |row| {
-        Ok((
-            row.get::<_, String>(0)?,
-            row.get::<_, String>(1)?,
-            row.get::<_, String>(2)?,
-        ))
-    })?;
+fn data_time_window(args: &ConvertArgs) -> Result<crate::DataTimeWindow> {
+    let window = crate::DataTimeWindow {
+        from: args
+            .from
+            .as_deref()
+            .map(|value| parse_convert_datetime(value, "--from"))
+            .transpose()?,
+        to: args
+            .to
+            .as_deref()
+            .map(|value| parse_convert_datetime(value, "--to"))
+            .transpose()?,
+    };
+    if let (Some(from), Some(to)) = (window.from, window.to)
+        && from >= to
+    {
+        return Err(eyre!("`--from` must be earlier than `--to`"));
+    }
+    Ok(window)
+}
 
-    let mut inventory = Vec::new();
-    for table in tables {
-        let (schema, table_name, table_type) = table?;
-        let kind = if table_type == "BASE TABLE" {
-            "table"
-        } else {
-            "view"
-        }
-        .to_string();
-        let row_count = if table_type == "BASE TABLE" {
-            let sql = format!(
-                "SELECT COUNT(*) FROM {}.{}",
-                quote_ident(&schema),
-                quote_ident(&table_name)
-            );
-            let count: i64 = con.query_row(&sql, [], |row| row.get(0))?;
-            count.to_string()
-        } else {
-            "-".to_string()
+fn downsample(args: &ConvertArgs) -> Result<Option<crate::Downsample>> {
+    let Some(period) = args.downsample else {
+        return Ok(None);
+    };
+    let mut downsample = crate::Downsample::new(period.into());
+    for entry in &args.downsample_aggregations {
+        let (property, aggregation) = entry.split_once('=').ok_or_else(|| {
+            eyre!(
+                "Invalid `--downsample-aggregation` {entry:?}; expected `PROPERTY=mean|sum|min|max`"
+            )
+        })?;
+        let aggregation = match aggregation.trim().to_ascii_lowercase().as_str() {
+            "mean" | "avg" => crate::DownsampleAggregation::Mean,
+            "sum" => crate::DownsampleAggregation::Sum,
+            "min" => crate::DownsampleAggregation::Min,
+            "max" => crate::DownsampleAggregation::Max,
+            other => {
+                return Err(eyre!(
+                    "Unknown aggregation {other:?} for property {property:?}; expected mean, sum, min or max"
+                ));
+            },
         };
-        inventory.push(TableInventoryRow {
-            schema,
-            table: table_name,
-            kind,
-            row_count,
-        });
+        downsample
+            .aggregations
+            .insert(property.trim().replace([' ', '-'], "_"), aggregation);
     }
+    Ok(Some(downsample))
+}
 
-    Ok(inventory)
+/// Convert options read from a `--config` file. Keys are the long flag names
+/// of `convert`; unset keys leave the flag at its command-line value or default.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ConvertProfile {
+    input: Option<std::path::PathBuf>,
+    output: Option<std::path::PathBuf>,
+    format: Option<ConvertFormat>,
+    force: Option<bool>,
+    print_summary: Option<bool>,
+    no_progress_bar: Option<bool>,
+    n_threads: Option<std::num::NonZeroUsize>,
+    table_name_pattern: Option<String>,
+    class: Option<Vec<String>>,
+    collection: Option<Vec<String>>,
+    category: Option<Vec<String>>,
+    object: Option<Vec<String>>,
+    property: Option<Vec<String>>,
+    samples: Option<Vec<i64>>,
+    bands: Option<Vec<i64>>,
+    trim_dimensions: Option<bool>,
+    from: Option<String>,
+    to: Option<String>,
+    downsample: Option<DownsamplePeriod>,
+    downsample_aggregation: Option<Vec<String>>,
+    external_data_parquet_dir: Option<std::path::PathBuf>,
+    external_data_parquet_layout: Option<ParquetLayout>,
+    arrow_compression: Option<ArrowCompression>,
+    /// Named profiles, each applied over the top-level options with `--profile`
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    profiles: std::collections::BTreeMap<String, ConvertProfile>,
+}
+
+impl ConvertProfile {
+    fn load(path: &std::path::Path, profile: Option<&str>) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| eyre!("Could not read config file {}: {err}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let mut config: Self = match extension.as_deref() {
+            Some("toml") => toml::from_str(&text)
+                .map_err(|err| eyre!("Invalid config file {}: {err}", path.display()))?,
+            Some("yaml" | "yml") => serde_yaml::from_str(&text)
+                .map_err(|err| eyre!("Invalid config file {}: {err}", path.display()))?,
+            _ => {
+                return Err(eyre!(
+                    "Config file must have .toml, .yaml or .yml extension: {}",
+                    path.display()
+                ));
+            },
+        };
+
+        let profiles = std::mem::take(&mut config.profiles);
+        if let Some(name) = profile {
+            let selected = profiles.get(name).cloned().ok_or_else(|| {
+                eyre!(
+                    "Profile {name:?} not found in {}; available profiles: {}",
+                    path.display(),
+                    profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                )
+            })?;
+            if !selected.profiles.is_empty() {
+                return Err(eyre!(
+                    "Profile {name:?} in {} cannot nest profiles",
+                    path.display()
+                ));
+            }
+            config = config.overlay(selected);
+        }
+
+        // Paths in a shared profile are relative to the profile, not to the
+        // directory convert runs from.
+        let base = path.parent().unwrap_or_else(|| std::path::Path::new(""));
+        for config_path in [
+            &mut config.input,
+            &mut config.output,
+            &mut config.external_data_parquet_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if config_path.is_relative() {
+                *config_path = base.join(&*config_path);
+            }
+        }
+        Ok(config)
+    }
+
+    /// `self` with every option set in `other` replaced by its value there.
+    fn overlay(self, other: Self) -> Self {
+        Self {
+            input: other.input.or(self.input),
+            output: other.output.or(self.output),
+            format: other.format.or(self.format),
+            force: other.force.or(self.force),
+            print_summary: other.print_summary.or(self.print_summary),
+            no_progress_bar: other.no_progress_bar.or(self.no_progress_bar),
+            n_threads: other.n_threads.or(self.n_threads),
+            table_name_pattern: other.table_name_pattern.or(self.table_name_pattern),
+            class: other.class.or(self.class),
+            collection: other.collection.or(self.collection),
+            category: other.category.or(self.category),
+            object: other.object.or(self.object),
+            property: other.property.or(self.property),
+            samples: other.samples.or(self.samples),
+            bands: other.bands.or(self.bands),
+            trim_dimensions: other.trim_dimensions.or(self.trim_dimensions),
+            from: other.from.or(self.from),
+            to: other.to.or(self.to),
+            downsample: other.downsample.or(self.downsample),
+            downsample_aggregation: other.downsample_aggregation.or(self.downsample_aggregation),
+            external_data_parquet_dir: other
+                .external_data_parquet_dir
+                .or(self.external_data_parquet_dir),
+            external_data_parquet_layout: other
+                .external_data_parquet_layout
+                .or(self.external_data_parquet_layout),
+            arrow_compression: other.arrow_compression.or(self.arrow_compression),
+            profiles: std::collections::BTreeMap::new(),
+        }
+    }
+
+    /// The options `convert` runs with, for recording in the output.
+    fn resolved(args: &ConvertArgs) -> Self {
+        Self {
+            input: args.input.clone(),
+            output: args.output.clone(),
+            format: Some(args.format),
+            force: Some(args.force),
+            print_summary: Some(args.print_summary),
+            no_progress_bar: Some(args.no_progress_bar),
+            n_threads: args.n_threads,
+            table_name_pattern: args.table_name_pattern.clone(),
+            class: Some(args.classes.clone()),
+            collection: Some(args.collections.clone()),
+            category: Some(args.categories.clone()),
+            object: Some(args.objects.clone()),
+            property: Some(args.properties.clone()),
+            samples: Some(args.samples.clone()),
+            bands: Some(args.bands.clone()),
+            trim_dimensions: Some(args.trim_dimensions),
+            from: args.from.clone(),
+            to: args.to.clone(),
+            downsample: args.downsample,
+            downsample_aggregation: Some(args.downsample_aggregations.clone()),
+            external_data_parquet_dir: args.external_data_parquet_dir.clone(),
+            external_data_parquet_layout: Some(args.external_data_parquet_layout),
+            arrow_compression: Some(args.arrow_compression),
+            profiles: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+/// Fill the convert options not given on the command line from `--config`.
+fn apply_convert_profile(mut args: ConvertArgs, matches: &clap::ArgMatches) -> Result<ConvertArgs> {
+    let Some(path) = args.config.as_deref() else {
+        return Ok(args);
+    };
+    let profile = ConvertProfile::load(path, args.profile.as_deref())?;
+    let unset = |id: &str| matches.value_source(id) != Some(clap::parser::ValueSource::CommandLine);
+
+    if unset("input") && profile.input.is_some() {
+        args.input = profile.input;
+    }
+    if unset("output") && profile.output.is_some() {
+        args.output = profile.output;
+    }
+    if let Some(format) = profile.format
+        && unset("format")
+    {
+        args.format = format;
+    }
+    if let Some(force) = profile.force
+        && unset("force")
+    {
+        args.force = force;
+    }
+    if let Some(print_summary) = profile.print_summary
+        && unset("print_summary")
+    {
+        args.print_summary = print_summary;
+    }
+    if let Some(no_progress_bar) = profile.no_progress_bar
+        && unset("no_progress_bar")
+    {
+        args.no_progress_bar = no_progress_bar;
+    }
+    if unset("n_threads") && profile.n_threads.is_some() {
+        args.n_threads = profile.n_threads;
+    }
+    if unset("table_name_pattern") && profile.table_name_pattern.is_some() {
+        args.table_name_pattern = profile.table_name_pattern;
+    }
+    for (id, value, arg) in [
+        ("classes", profile.class, &mut args.classes),
+        ("collections", profile.collection, &mut args.collections),
+        ("categories", profile.category, &mut args.categories),
+        ("objects", profile.object, &mut args.objects),
+        ("properties", profile.property, &mut args.properties),
+        (
+            "downsample_aggregations",
+            profile.downsample_aggregation,
+            &mut args.downsample_aggregations,
+        ),
+    ] {
+        if let Some(value) = value
+            && unset(id)
+        {
+            *arg = value;
+        }
+    }
+    if let Some(samples) = profile.samples
+        && unset("samples")
+    {
+        args.samples = samples;
+    }
+    if let Some(bands) = profile.bands
+        && unset("bands")
+    {
+        args.bands = bands;
+    }
+    if let Some(trim_dimensions) = profile.trim_dimensions
+        && unset("trim_dimensions")
+    {
+        args.trim_dimensions = trim_dimensions;
+    }
+    if unset("from") && profile.from.is_some() {
+        args.from = profile.from;
+    }
+    if unset("to") && profile.to.is_some() {
+        args.to = profile.to;
+    }
+    if unset("downsample") && profile.downsample.is_some() {
+        args.downsample = profile.downsample;
+    }
+    if unset("external_data_parquet_dir") && profile.external_data_parquet_dir.is_some() {
+        args.external_data_parquet_dir = profile.external_data_parquet_dir;
+    }
+    if let Some(layout) = profile.external_data_parquet_layout
+        && unset("external_data_parquet_layout")
+    {
+        args.external_data_parquet_layout = layout;
+    }
+    if let Some(compression) = profile.arrow_compression
+        && unset("arrow_compression")
+    {
+        args.arrow_compression = compression;
+    }
+    if args.downsample.is_none() && !args.downsample_aggregations.is_empty() {
+        return Err(eyre!("`downsample-aggregation` requires `downsample`"));
+    }
+    Ok(args)
+}
+
+/// JSON recorded as `convert_config` in `main.plexos2duckdb`: the resolved
+/// options plus the config file and profile they came from.
+fn convert_config_json(args: &ConvertArgs) -> Result<String> {
+    let mut config = serde_json::to_value(ConvertProfile::resolved(args))?;
+    if let Some(object) = config.as_object_mut() {
+        object.insert(
+            "config".to_string(),
+            serde_json::to_value(args.config.as_deref())?,
+        );
+        object.insert("profile".to_string(), serde_json::to_value(&args.profile)?);
+    }
+    Ok(config.to_string())
+}
+
+fn metadata_value(
+    metadata: &std::collections::HashMap<String, String>,
+    key: &str,
+) -> Result<String> {
+    metadata
+        .get(key)
+        .cloned()
+        .ok_or_else(|| eyre!("Missing metadata key in main.plexos2duckdb: {key}"))
+}
+
+fn load_database_metadata(con: &duckdb::Connection) -> Result<DatabaseMetadata> {
+    load_database_metadata_from(con, "main")
+}
+
+fn load_database_metadata_from(
+    con: &duckdb::Connection,
+    catalog: &str,
+) -> Result<DatabaseMetadata> {
+    let mut stmt = con.prepare(&format!(
+        "SELECT key, value FROM {}.main.plexos2duckdb",
+        quote_ident(catalog)
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+        ))
+    })?;
+
+    let mut metadata = std::collections::HashMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        metadata.insert(key, value);
+    }
+
+    let schema_version = match metadata.get("schema_version") {
+        Some(value) => value.trim().parse().map_err(|err| {
+            eyre!("Invalid schema_version {value:?} in main.plexos2duckdb: {err}")
+        })?,
+        None => 1,
+    };
+    crate::check_schema_version(schema_version)?;
+
+    let mut provenance = std::collections::BTreeMap::new();
+    for (key, _) in PROVENANCE_KEYS {
+        if let Some(value) = metadata.get(*key) {
+            let value = match *key {
+                "convert_options" | "convert_config" | "step_timings" => {
+                    serde_json::from_str(value)?
+                },
+                _ => serde_json::Value::from(value.as_str()),
+            };
+            provenance.insert(key.to_string(), value);
+        }
+    }
+
+    Ok(DatabaseMetadata {
+        database: String::new(),
+        converter_version: metadata_value(&metadata, "plexos2duckdb_version")?,
+        schema_version,
+        source_file: metadata_value(&metadata, "plexos_file")?,
+        model_name: metadata_value(&metadata, "model_name")?,
+        provenance,
+    })
+}
+
+/// Every table and view of the database. Table row counts come from DuckDB's
+/// storage metadata and row counts of views over external Parquet files from
+/// the file footers, so nothing is scanned; other views show "-".
+fn load_table_inventory(con: &duckdb::Connection) -> Result<Vec<TableInventoryRow>> {
+    let tables = query_rows(
+        con,
+        "
+        SELECT schema_name, table_name, 'table', estimated_size, NULL
+        FROM duckdb_tables()
+        WHERE database_name = current_database()
+        UNION ALL
+        SELECT schema_name, view_name, 'view', NULL, sql
+        FROM duckdb_views()
+        WHERE database_name = current_database() AND NOT internal
+        ORDER BY 1, 2
+        ",
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        },
+    )?;
+
+    let mut inventory = Vec::new();
+    for (schema, table, kind, estimated_size, view_sql) in tables {
+        let row_count = estimated_size.or_else(|| {
+            view_sql
+                .as_deref()
+                .and_then(read_parquet_argument)
+                .and_then(|paths| parquet_footer_row_count(con, paths))
+        });
+        inventory.push(TableInventoryRow {
+            schema,
+            table,
+            kind,
+            row_count: row_count.map_or_else(|| "-".to_string(), |count| count.to_string()),
+        });
+    }
+
+    Ok(inventory)
+}
+
+/// The argument of the `read_parquet(...)` call in a view definition, when it
+/// is the only argument.
+fn read_parquet_argument(sql: &str) -> Option<&str> {
+    let start = sql.find("read_parquet(")? + "read_parquet(".len();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    for (idx, ch) in sql[start..].char_indices() {
+        match ch {
+            '\'' => in_string = !in_string,
+            _ if in_string => {},
+            '(' | '[' => depth += 1,
+            ')' if depth == 0 => return Some(sql[start..start + idx].trim()),
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => return None,
+            _ => {},
+        }
+    }
+    None
+}
+
+fn parquet_footer_row_count(con: &duckdb::Connection, paths: &str) -> Option<i64> {
+    con.query_row(
+        &format!(
+            "SELECT COALESCE(SUM(row_group_num_rows), 0)::BIGINT FROM (
+               SELECT DISTINCT file_name, row_group_id, row_group_num_rows
+               FROM parquet_metadata({paths})
+             );"
+        ),
+        [],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+fn has_relation(con: &duckdb::Connection, schema: &str, name: &str) -> Result<bool> {
+    let count: i64 = con.query_row(
+        "SELECT COUNT(*) FROM information_schema.tables
+         WHERE table_catalog = current_database() AND table_schema = ? AND table_name = ?",
+        [schema, name],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// First and last block start of each `processed.timestamp_block_*` view.
+fn load_horizon(con: &duckdb::Connection) -> Result<Vec<HorizonRow>> {
+    let blocks = query_rows(
+        con,
+        "SELECT table_name FROM information_schema.tables
+         WHERE table_catalog = current_database() AND table_schema = 'processed'
+           AND table_name LIKE 'timestamp\\_block\\_%' ESCAPE '\\'
+         ORDER BY table_name",
+        |row| row.get::<_, String>(0),
+    )?;
+    let mut horizon = Vec::new();
+    for view in blocks {
+        let block = view.trim_start_matches("timestamp_block_");
+        let (phase, period) = block.split_once("__").unwrap_or((block, ""));
+        let (first, last, blocks) = con.query_row(
+            &format!(
+                "SELECT CAST(MIN(datetime) AS VARCHAR), CAST(MAX(datetime) AS VARCHAR), COUNT(*)
+                 FROM processed.{};",
+                quote_ident(&view)
+            ),
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+        horizon.push(HorizonRow {
+            phase: phase.to_string(),
+            period: period.to_string(),
+            first: first.unwrap_or_else(|| "-".to_string()),
+            last: last.unwrap_or_else(|| "-".to_string()),
+            blocks,
+        });
+    }
+    Ok(horizon)
+}
+
+/// Group `data` tables by the phase and period of their name.
+fn data_table_groups(inventory: &[TableInventoryRow]) -> Vec<DataTableGroupRow> {
+    let mut groups = std::collections::BTreeMap::<(String, String), (usize, usize, i64)>::new();
+    for row in inventory.iter().filter(|row| row.schema == "data") {
+        let mut parts = row.table.split("__");
+        let phase = parts.next().unwrap_or_default().to_string();
+        let period = parts.next().unwrap_or_default().to_string();
+        let (tables, counted, rows) = groups.entry((phase, period)).or_default();
+        *tables += 1;
+        if let Ok(count) = row.row_count.parse::<i64>() {
+            *counted += 1;
+            *rows += count;
+        }
+    }
+    groups
+        .into_iter()
+        .map(
+            |((phase, period), (tables, counted, rows))| DataTableGroupRow {
+                phase,
+                period,
+                tables,
+                rows: match counted {
+                    0 => "-".to_string(),
+                    _ if counted < tables => format!("{rows}+"),
+                    _ => rows.to_string(),
+                },
+            },
+        )
+        .collect()
+}
+
+fn print_inspect_section<T: Tabled>(title: &str, rows: Option<Vec<T>>, first: &mut bool) {
+    let Some(rows) = rows else {
+        return;
+    };
+    if !*first {
+        println!();
+    }
+    *first = false;
+    println!("{title}");
+    if rows.is_empty() {
+        println!("(none)");
+    } else {
+        println!("{}", Table::new(rows).with(Style::rounded()));
+    }
+}
+
+/// Bytes per row of a `data` table: five BIGINT ids and a DOUBLE value.
+const DATA_ROW_BYTES: u128 = 48;
+
+fn is_solution_file(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip") || ext.eq_ignore_ascii_case("xml"))
+}
+
+fn load_solution_horizon(dataset: &crate::SolutionDataset) -> Vec<HorizonRow> {
+    dataset
+        .timestamp_block_names()
+        .into_iter()
+        .map(|block| {
+            let datetimes = dataset.timestamp_block_datetimes(block).unwrap_or_default();
+            let format = |datetime: Option<chrono::DateTime<chrono::Utc>>| {
+                datetime.map_or_else(
+                    || "-".to_string(),
+                    |datetime| datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+                )
+            };
+            let (phase, period) = block.split_once("__").unwrap_or((block, ""));
+            HorizonRow {
+                phase: phase.to_string(),
+                period: period.to_string(),
+                first: format(datetimes.iter().map(|(_, datetime)| *datetime).min()),
+                last: format(datetimes.iter().map(|(_, datetime)| *datetime).max()),
+                blocks: i64::try_from(datetimes.len()).unwrap_or(i64::MAX),
+            }
+        })
+        .collect()
+}
+
+/// Inspect a solution ZIP or XML from its XML alone. No period data is read,
+/// so row counts are the estimates `convert` plans with.
+fn inspect_solution(args: InspectArgs) -> Result<()> {
+    args.reject_sections(
+        &[
+            InspectSection::Samples,
+            InspectSection::Bands,
+            InspectSection::Inventory,
+        ],
+        "solution files",
+    )?;
+    let input = resolve_input_path(&args.input)?;
+    let dataset = if input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    {
+        crate::SolutionDataset::default().with_zip_file(&input)?
+    } else {
+        crate::SolutionDataset::default().with_xml_file(&input)?
+    };
+
+    let estimates = dataset.data_table_estimates()?;
+    let estimated_rows = estimates
+        .iter()
+        .map(|table| table.estimated_rows)
+        .sum::<u128>();
+    let metadata = args
+        .shows(InspectSection::Metadata)
+        .then(|| SolutionMetadata {
+            solution: input.display().to_string(),
+            model_name: dataset.model_name().to_string(),
+            data_tables: estimates.len(),
+            estimated_rows,
+            estimated_data_bytes: estimated_rows.saturating_mul(DATA_ROW_BYTES),
+        });
+    let horizon = args
+        .shows(InspectSection::Horizon)
+        .then(|| load_solution_horizon(&dataset));
+    let classes = args.shows(InspectSection::Classes).then(|| {
+        let mut classes = dataset
+            .classes()
+            .map(|class| ClassCountRow {
+                class: class.name.clone(),
+                objects: i64::try_from(dataset.objects_by_class(class.class_id).count())
+                    .unwrap_or(i64::MAX),
+            })
+            .filter(|row| row.objects > 0)
+            .collect::<Vec<_>>();
+        classes.sort_by(|a, b| a.class.cmp(&b.class));
+        classes
+    });
+    let data_tables = args.shows(InspectSection::DataTables).then(|| {
+        data_table_groups(
+            &estimates
+                .iter()
+                .map(|table| TableInventoryRow {
+                    schema: "data".to_string(),
+                    table: table.table_name.clone(),
+                    kind: "table".to_string(),
+                    row_count: table.estimated_rows.to_string(),
+                })
+                .collect::<Vec<_>>(),
+        )
+    });
+    let tables = args.shows(InspectSection::Tables).then(|| {
+        estimates
+            .iter()
+            .map(|table| {
+                let mut parts = table.table_name.splitn(4, "__");
+                let mut part = || parts.next().unwrap_or_default().to_string();
+                DataTableEstimateRow {
+                    phase: part(),
+                    period: part(),
+                    collection: part(),
+                    property: part(),
+                    unit: dataset
+                        .data_table_unit(&table.table_name)
+                        .unwrap_or_default()
+                        .to_string(),
+                    keys: table.keys,
+                    estimated_rows: table.estimated_rows,
+                }
+            })
+            .collect::<Vec<_>>()
+    });
+    let units = args.shows(InspectSection::Units).then(|| {
+        dataset
+            .units()
+            .map(|unit| UnitRow {
+                unit: unit.name.clone(),
+                properties: i64::try_from(
+                    dataset
+                        .properties()
+                        .filter(|property| property.unit_id == unit.id)
+                        .count(),
+                )
+                .unwrap_or(i64::MAX),
+            })
+            .collect::<Vec<_>>()
+    });
+    let bin_files = if args.shows(InspectSection::BinFiles) {
+        Some(
+            dataset
+                .period_data_files()?
+                .into_iter()
+                .map(|file| BinFileRow {
+                    file: format!("t_data_{}.BIN", file.period_type_id),
+                    storage: file.storage.to_string(),
+                    size_bytes: file.size_bytes,
+                    stored_size_bytes: file.stored_size_bytes,
+                })
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        None
+    };
+
+    if args.format_diagnostics == OutputFormat::Json {
+        return print_json(&InspectSolutionJsonOutput {
+            metadata,
+            horizon,
+            classes,
+            data_tables,
+            tables,
+            units,
+            bin_files,
+        });
+    }
+
+    let mut first = true;
+    let metadata_rows = metadata.map(|metadata| {
+        vec![
+            MetadataRow {
+                field: "solution".to_string(),
+                value: metadata.solution,
+            },
+            MetadataRow {
+                field: "model name".to_string(),
+                value: metadata.model_name,
+            },
+            MetadataRow {
+                field: "data tables".to_string(),
+                value: metadata.data_tables.to_string(),
+            },
+            MetadataRow {
+                field: "estimated rows".to_string(),
+                value: metadata.estimated_rows.to_string(),
+            },
+            MetadataRow {
+                field: "estimated data size (uncompressed)".to_string(),
+                value: indicatif::HumanBytes(
+                    u64::try_from(metadata.estimated_data_bytes).unwrap_or(u64::MAX),
+                )
+                .to_string(),
+            },
+        ]
+    });
+    print_inspect_section("Metadata", metadata_rows, &mut first);
+    print_inspect_section("Horizon", horizon, &mut first);
+    print_inspect_section("Classes", classes, &mut first);
+    print_inspect_section("Data tables", data_tables, &mut first);
+    print_inspect_section("Tables", tables, &mut first);
+    print_inspect_section("Units", units, &mut first);
+    print_inspect_section("BIN files", bin_files, &mut first);
+    Ok(())
+}
+
+fn inspect_database(args: InspectArgs) -> Result<()> {
+    if is_solution_file(&args.input) || args.input.is_dir() {
+        return inspect_solution(args);
+    }
+    if !args.input.exists() {
+        return Err(eyre!(
+            "DuckDB file does not exist: {}",
+            args.input.display()
+        ));
+    }
+    args.reject_sections(
+        &[InspectSection::Tables, InspectSection::BinFiles],
+        "DuckDB databases",
+    )?;
+    let con = duckdb::Connection::open(&args.input)?;
+    let mut metadata = load_database_metadata(&con)?;
+    metadata.database = args.input.display().to_string();
+
+    let has_raw = |table: &str| has_relation(&con, "raw", table);
+    let horizon = if args.shows(InspectSection::Horizon) {
+        Some(load_horizon(&con)?)
+    } else {
+        None
+    };
+    let classes =
+        if args.shows(InspectSection::Classes) && has_raw("objects")? && has_raw("classes")? {
+            Some(query_rows(
+                &con,
+                "SELECT c.name, COUNT(DISTINCT o.name)
+             FROM raw.objects o JOIN raw.classes c ON o.class_id = c.class_id
+             GROUP BY c.name ORDER BY c.name",
+                |row| {
+                    Ok(ClassCountRow {
+                        class: row.get(0)?,
+                        objects: row.get(1)?,
+                    })
+                },
+            )?)
+        } else {
+            None
+        };
+    let units = if args.shows(InspectSection::Units) && has_raw("units")? && has_raw("properties")?
+    {
+        Some(query_rows(
+            &con,
+            "SELECT u.unit_name, COUNT(DISTINCT p.property_id)
+             FROM raw.units u LEFT JOIN raw.properties p ON p.unit_id = u.unit_id
+             GROUP BY u.unit_id, u.unit_name ORDER BY u.unit_id",
+            |row| {
+                Ok(UnitRow {
+                    unit: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                    properties: row.get(1)?,
+                })
+            },
+        )?)
+    } else {
+        None
+    };
+    let samples = if args.shows(InspectSection::Samples) && has_raw("samples")? {
+        Some(query_rows(
+            &con,
+            "SELECT DISTINCT sample_id, sample_name, sample_weight FROM raw.samples ORDER BY sample_id",
+            |row| {
+                Ok(SampleRow {
+                    sample_id: row.get(0)?,
+                    sample: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    weight: row.get::<_, Option<f64>>(2)?.unwrap_or_default(),
+                })
+            },
+        )?)
+    } else {
+        None
+    };
+    let bands = if args.shows(InspectSection::Bands) && has_raw("bands")? {
+        Some(query_rows(
+            &con,
+            "SELECT DISTINCT band_id FROM raw.bands ORDER BY band_id",
+            |row| {
+                Ok(BandRow {
+                    band_id: row.get(0)?,
+                })
+            },
+        )?)
+    } else {
+        None
+    };
+    let inventory =
+        if args.shows(InspectSection::DataTables) || args.shows(InspectSection::Inventory) {
+            Some(load_table_inventory(&con)?)
+        } else {
+            None
+        };
+    let data_tables = inventory
+        .as_deref()
+        .filter(|_| args.shows(InspectSection::DataTables))
+        .map(data_table_groups);
+    let inventory = inventory.filter(|_| args.shows(InspectSection::Inventory));
+    let metadata = args.shows(InspectSection::Metadata).then_some(metadata);
+
+    if args.format_diagnostics == OutputFormat::Json {
+        return print_json(&InspectJsonOutput {
+            metadata,
+            horizon,
+            classes,
+            data_tables,
+            units,
+            samples,
+            bands,
+            inventory,
+        });
+    }
+
+    let mut first = true;
+    if let Some(metadata) = metadata {
+        print_inspect_metadata(metadata);
+        first = false;
+    }
+    print_inspect_section("Horizon", horizon, &mut first);
+    print_inspect_section("Classes", classes, &mut first);
+    print_inspect_section("Data tables", data_tables, &mut first);
+    print_inspect_section("Units", units, &mut first);
+    print_inspect_section("Samples", samples, &mut first);
+    print_inspect_section("Bands", bands, &mut first);
+    print_inspect_section("Inventory", inventory, &mut first);
+    Ok(())
+}
+
+fn print_inspect_metadata(metadata: DatabaseMetadata) {
+    let mut metadata_rows = vec![
+        MetadataRow {
+            field: "database".to_string(),
+            value: metadata.database,
+        },
+        MetadataRow {
+            field: "converter version".to_string(),
+            value: metadata.converter_version,
+        },
+        MetadataRow {
+            field: "schema version".to_string(),
+            value: if metadata.schema_version < crate::SCHEMA_VERSION {
+                format!(
+                    "{} (current is {}; run `plexos2duckdb migrate` to upgrade)",
+                    metadata.schema_version,
+                    crate::SCHEMA_VERSION
+                )
+            } else {
+                metadata.schema_version.to_string()
+            },
+        },
+        MetadataRow {
+            field: "source file".to_string(),
+            value: metadata.source_file,
+        },
+        MetadataRow {
+            field: "model name".to_string(),
+            value: metadata.model_name,
+        },
+    ];
+    for (key, label) in PROVENANCE_KEYS {
+        if *key == "step_timings" {
+            continue;
+        }
+        if let Some(value) = metadata.provenance.get(*key) {
+            metadata_rows.push(MetadataRow {
+                field: label.to_string(),
+                value: value
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| value.to_string()),
+            });
+        }
+    }
+    let step_timings = metadata
+        .provenance
+        .get("step_timings")
+        .and_then(serde_json::Value::as_array)
+        .map(|steps| {
+            steps
+                .iter()
+                .map(|step| StepTimingRow {
+                    step: step["step"].as_str().unwrap_or_default().to_string(),
+                    seconds: step["seconds"]
+                        .as_f64()
+                        .map(|seconds| format!("{seconds:.3}"))
+                        .unwrap_or_default(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    println!("Metadata");
+    println!("{}", Table::new(metadata_rows).with(Style::rounded()));
+    if !step_timings.is_empty() {
+        println!();
+        println!("Steps");
+        println!("{}", Table::new(step_timings).with(Style::rounded()));
+    }
+}
+
+fn open_read_only_connection(path: &std::path::Path) -> Result<duckdb::Connection> {
+    if !path.exists() {
+        return Err(eyre!("DuckDB file does not exist: {}", path.display()));
+    }
+    let config = duckdb::Config::default().access_mode(duckdb::AccessMode::ReadOnly)?;
+    Ok(duckdb::Connection::open_with_flags(path, config)?)
+}
+
+fn sql_string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn select_export_views(
+    con: &duckdb::Connection,
+    patterns: &[String],
+) -> Result<Vec<(String, String)>> {
+    let mut matchers = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let qualified = if pattern.contains('.') {
+            pattern.clone()
+        } else {
+            format!("report.{pattern}")
+        };
+        matchers.push(crate::glob_to_regex(&qualified)?);
+    }
+
+    let mut stmt = con.prepare(
+        "
+        SELECT table_schema, table_name
+        FROM information_schema.tables
+        WHERE table_type = 'VIEW'
+          AND table_schema NOT IN ('information_schema', 'pg_catalog')
+        ORDER BY table_schema, table_name
+        ",
+    )?;
+    let tables = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let views = tables
+        .into_iter()
+        .filter(|(schema, table)| {
+            let qualified = format!("{schema}.{table}");
+            matchers.iter().any(|matcher| matcher.is_match(&qualified))
+        })
+        .collect::<Vec<_>>();
+    if views.is_empty() {
+        return Err(eyre!("No views match {}", patterns.join(", ")));
+    }
+    Ok(views)
+}
+
+fn export_view_query(
+    con: &duckdb::Connection,
+    schema: &str,
+    view: &str,
+    args: &ExportArgs,
+) -> Result<String> {
+    let columns = con
+        .prepare(
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = ? AND table_name = ?",
+        )?
+        .query_map([schema, view], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<std::collections::HashSet<_>, _>>()?;
+    let require_column = |column: &str, flag: &str| {
+        if columns.contains(column) {
+            Ok(())
+        } else {
+            Err(eyre!(
+                "`{flag}` requires a `{column}` column, which {schema}.{view} does not have"
+            ))
+        }
+    };
+
+    let mut conditions = Vec::new();
+    if !args.object.is_empty() {
+        require_column("name", "--object")?;
+        let names = args.object.iter().map(|name| sql_string_literal(name));
+        conditions.push(format!(
+            "name IN ({})",
+            names.collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if !args.category.is_empty() {
+        require_column("category", "--category")?;
+        let categories = args.category.iter().map(|name| sql_string_literal(name));
+        conditions.push(format!(
+            "category IN ({})",
+            categories.collect::<Vec<_>>().join(", ")
+        ));
+    }
+    if let Some(from) = args.from.as_deref() {
+        require_column("timestamp", "--from")?;
+        conditions.push(format!(
+            "timestamp >= CAST({} AS TIMESTAMP)",
+            sql_string_literal(from)
+        ));
+    }
+    if let Some(to) = args.to.as_deref() {
+        require_column("timestamp", "--to")?;
+        conditions.push(format!(
+            "timestamp < CAST({} AS TIMESTAMP)",
+            sql_string_literal(to)
+        ));
+    }
+
+    let mut query = format!(
+        "SELECT * FROM {}.{}",
+        quote_ident(schema),
+        quote_ident(view)
+    );
+    if !conditions.is_empty() {
+        query.push_str(" WHERE ");
+        query.push_str(&conditions.join(" AND "));
+    }
+    Ok(query)
+}
+
+/// JSON for a DuckDB value: numbers stay numbers (DECIMAL included), dates,
+/// times and timestamps become ISO strings, ENUMs their label, and LISTs,
+/// STRUCTs and MAPs arrays and objects.
+fn json_value(value: duckdb::types::Value) -> serde_json::Value {
+    use duckdb::types::Value;
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Boolean(value) => value.into(),
+        Value::TinyInt(value) => value.into(),
+        Value::SmallInt(value) => value.into(),
+        Value::Int(value) => value.into(),
+        Value::BigInt(value) => value.into(),
+        Value::HugeInt(value) => i64::try_from(value)
+            .map(Into::into)
+            .unwrap_or_else(|_| value.to_string().into()),
+        Value::UHugeInt(value) => u64::try_from(value)
+            .map(Into::into)
+            .unwrap_or_else(|_| value.to_string().into()),
+        Value::UTinyInt(value) => value.into(),
+        Value::USmallInt(value) => value.into(),
+        Value::UInt(value) => value.into(),
+        Value::UBigInt(value) => value.into(),
+        Value::Float(value) => serde_json::Number::from_f64(f64::from(value)).into(),
+        Value::Double(value) => serde_json::Number::from_f64(value).into(),
+        Value::Decimal(value) => {
+            let text = value.to_string();
+            text.parse::<serde_json::Number>()
+                .map(Into::into)
+                .unwrap_or_else(|_| text.into())
+        },
+        Value::List(values) | Value::Array(values) => values
+            .into_iter()
+            .map(json_value)
+            .collect::<Vec<_>>()
+            .into(),
+        Value::Struct(fields) => fields
+            .iter()
+            .map(|(name, value)| (name.clone(), json_value(value.clone())))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Value::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| (display_value(key.clone()), json_value(value.clone())))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Value::Union(value) => json_value(*value),
+        other => display_value(other).into(),
+    }
+}
+
+fn timestamp_string(unit: duckdb::types::TimeUnit, value: i64) -> String {
+    chrono::DateTime::from_timestamp_micros(unit.to_micros(value))
+        .map(|datetime| datetime.naive_utc().to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+fn date_string(days: i32) -> String {
+    chrono::NaiveDate::from_ymd_opt(1970, 1, 1)
+        .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(days.into())))
+        .map(|date| date.to_string())
+        .unwrap_or_else(|| days.to_string())
+}
+
+fn time_string(unit: duckdb::types::TimeUnit, value: i64) -> String {
+    let micros = unit.to_micros(value);
+    u32::try_from(micros.div_euclid(1_000_000))
+        .ok()
+        .zip(u32::try_from(micros.rem_euclid(1_000_000) * 1_000).ok())
+        .and_then(|(seconds, nanos)| {
+            chrono::NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanos)
+        })
+        .map(|time| time.to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Text for a DuckDB value, as the table and CSV outputs print it. NULL is
+/// empty at the top level and `NULL` inside lists, structs and maps.
+fn display_value(value: duckdb::types::Value) -> String {
+    use duckdb::types::Value;
+    let nested = |value: Value| match value {
+        Value::Null => "NULL".to_string(),
+        value => display_value(value),
+    };
+    match value {
+        Value::Null => String::new(),
+        Value::Boolean(value) => value.to_string(),
+        Value::TinyInt(value) => value.to_string(),
+        Value::SmallInt(value) => value.to_string(),
+        Value::Int(value) => value.to_string(),
+        Value::BigInt(value) => value.to_string(),
+        Value::HugeInt(value) => value.to_string(),
+        Value::UHugeInt(value) => value.to_string(),
+        Value::UTinyInt(value) => value.to_string(),
+        Value::USmallInt(value) => value.to_string(),
+        Value::UInt(value) => value.to_string(),
+        Value::UBigInt(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Double(value) => value.to_string(),
+        Value::Decimal(value) => value.to_string(),
+        Value::Text(value) | Value::Enum(value) => value,
+        Value::Timestamp(unit, value) => timestamp_string(unit, value),
+        Value::Date32(days) => date_string(days),
+        Value::Time64(unit, value) => time_string(unit, value),
+        Value::Interval {
+            months,
+            days,
+            nanos,
+        } => format!("P{months}M{days}DT{}S", nanos as f64 / 1e9),
+        Value::Blob(bytes) | Value::Geometry(bytes) => bytes
+            .iter()
+            .map(|byte| match byte {
+                b' '..=b'~' if *byte != b'\\' => char::from(*byte).to_string(),
+                _ => format!("\\x{byte:02X}"),
+            })
+            .collect(),
+        Value::List(values) | Value::Array(values) => format!(
+            "[{}]",
+            values
+                .into_iter()
+                .map(nested)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Struct(fields) => format!(
+            "{{{}}}",
+            fields
+                .iter()
+                .map(|(name, value)| format!("'{name}': {}", nested(value.clone())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Map(entries) => format!(
+            "{{{}}}",
+            entries
+                .iter()
+                .map(|(key, value)| format!("{}={}", nested(key.clone()), nested(value.clone())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Union(value) => nested(*value),
+        // `Value` is non-exhaustive; types added to duckdb later
+        other => format!("{other:?}"),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Stream up to `limit` query rows as JSON lines.
+fn write_query_jsonl(
+    con: &duckdb::Connection,
+    query: &str,
+    limit: Option<usize>,
+    writer: &mut dyn std::io::Write,
+) -> Result<()> {
+    let mut stmt = con.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let column_names = rows
+        .as_ref()
+        .context("Query result must have a statement")?
+        .column_names();
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    while remaining > 0
+        && let Some(row) = rows.next()?
+    {
+        remaining -= 1;
+        let mut object = serde_json::Map::with_capacity(column_names.len());
+        for (idx, name) in column_names.iter().enumerate() {
+            object.insert(name.clone(), json_value(row.get(idx)?));
+        }
+        serde_json::to_writer(&mut *writer, &object)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Stream up to `limit` query rows as CSV with a header line.
+fn write_query_csv(
+    con: &duckdb::Connection,
+    query: &str,
+    limit: Option<usize>,
+    writer: &mut dyn std::io::Write,
+) -> Result<()> {
+    let mut stmt = con.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let column_names = rows
+        .as_ref()
+        .context("Query result must have a statement")?
+        .column_names();
+    let header = column_names.iter().map(|name| csv_field(name));
+    writeln!(writer, "{}", header.collect::<Vec<_>>().join(","))?;
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    while remaining > 0
+        && let Some(row) = rows.next()?
+    {
+        remaining -= 1;
+        let mut fields = Vec::with_capacity(column_names.len());
+        for idx in 0..column_names.len() {
+            fields.push(csv_field(&display_value(row.get(idx)?)));
+        }
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write up to `limit` query rows to a Parquet file. The rows come from the
+/// prepared statement, so the SQL runs as given, comments and all.
+fn write_query_parquet(
+    con: &duckdb::Connection,
+    query: &str,
+    limit: Option<usize>,
+    path: &std::path::Path,
+) -> Result<()> {
+    let mut stmt = con.prepare(query)?;
+    let batches = stmt.query_arrow([])?;
+    let mut writer = parquet::arrow::ArrowWriter::try_new(
+        std::fs::File::create(path)?,
+        batches.get_schema(),
+        None,
+    )?;
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    for batch in batches {
+        if remaining == 0 {
+            break;
+        }
+        let batch = batch.slice(0, batch.num_rows().min(remaining));
+        remaining -= batch.num_rows();
+        writer.write(&batch)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn export_view_jsonl(con: &duckdb::Connection, query: &str, path: &std::path::Path) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_query_jsonl(con, query, None, &mut writer)
+}
+
+/// Rows in an Excel worksheet, including the header row.
+const XLSX_MAX_ROWS: i64 = 1_048_576;
+
+/// Fail before writing anything when a view has more rows than one worksheet holds.
+fn check_xlsx_row_count(
+    con: &duckdb::Connection,
+    query: &str,
+    schema: &str,
+    view: &str,
+) -> Result<()> {
+    let rows: i64 = con.query_row(&format!("SELECT COUNT(*) FROM ({query})"), [], |row| {
+        row.get(0)
+    })?;
+    if rows >= XLSX_MAX_ROWS {
+        return Err(eyre!(
+            "{schema}.{view} has {rows} rows, more than the {} an XLSX worksheet holds; narrow it with `--object`, `--category`, `--from` or `--to`, or export to CSV or Parquet",
+            XLSX_MAX_ROWS - 1
+        ));
+    }
+    Ok(())
+}
+
+fn export_view_xlsx(
+    con: &duckdb::Connection,
+    query: &str,
+    view: &str,
+    path: &std::path::Path,
+) -> Result<()> {
+    use duckdb::types::Value;
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let datetime_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+    let worksheet = workbook.add_worksheet_with_constant_memory();
+    let sheet_name = view
+        .chars()
+        .filter(|ch| !matches!(ch, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect::<String>();
+    if !sheet_name.is_empty() {
+        worksheet.set_name(sheet_name)?;
+    }
+
+    let mut stmt = con.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let column_names = rows
+        .as_ref()
+        .context("Query result must have a statement")?
+        .column_names();
+    for (col, name) in column_names.iter().enumerate() {
+        worksheet.write_string(0, u16::try_from(col)?, name)?;
+    }
+
+    let mut row_idx = 0u32;
+    while let Some(row) = rows.next()? {
+        row_idx += 1;
+        for col in 0..column_names.len() {
+            let col_idx = u16::try_from(col)?;
+            let written = match row.get::<_, Value>(col)? {
+                Value::Null => continue,
+                Value::Boolean(value) => worksheet.write_boolean(row_idx, col_idx, value),
+                Value::TinyInt(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::SmallInt(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::Int(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::BigInt(value) => worksheet.write_number(row_idx, col_idx, value as f64),
+                Value::UTinyInt(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::USmallInt(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::UInt(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::UBigInt(value) => worksheet.write_number(row_idx, col_idx, value as f64),
+                Value::Float(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::Double(value) => worksheet.write_number(row_idx, col_idx, value),
+                Value::Decimal(value) => match value.to_string().parse::<f64>() {
+                    Ok(number) => worksheet.write_number(row_idx, col_idx, number),
+                    Err(_) => worksheet.write_string(row_idx, col_idx, value.to_string()),
+                },
+                Value::Text(value) => worksheet.write_string(row_idx, col_idx, value),
+                Value::Timestamp(unit, value) => {
+                    let seconds = unit.to_micros(value).div_euclid(1_000_000);
+                    worksheet.write_datetime_with_format(
+                        row_idx,
+                        col_idx,
+                        ExcelDateTime::from_timestamp(seconds)?,
+                        &datetime_format,
+                    )
+                },
+                other => worksheet.write_string(row_idx, col_idx, display_value(other)),
+            };
+            written.map_err(|err| eyre!("Failed to write row {row_idx} of {view}: {err}"))?;
+        }
+    }
+
+    workbook.save(path)?;
+    Ok(())
+}
+
+fn export_views(args: ExportArgs) -> Result<()> {
+    let con = open_read_only_connection(&args.input)?;
+    let views = select_export_views(&con, &args.view)?;
+
+    let extension = args.format.extension();
+    let outputs = if views.len() == 1 {
+        vec![args.out.clone()]
+    } else {
+        std::fs::create_dir_all(&args.out)?;
+        views
+            .iter()
+            .map(|(schema, view)| args.out.join(format!("{schema}.{view}.{extension}")))
+            .collect()
+    };
+
+    for ((schema, view), path) in views.iter().zip(outputs) {
+        let query = export_view_query(&con, schema, view, &args)?;
+        if args.format == ExportFormat::Xlsx {
+            check_xlsx_row_count(&con, &query, schema, view)?;
+        }
+        if path.exists() {
+            if !args.force {
+                return Err(eyre!(
+                    "Output file already exists: \"{}\". Re-run with `--force` to overwrite it",
+                    path.display().to_string().bold()
+                ));
+            }
+            std::fs::remove_file(&path)?;
+        }
+
+        match args.format {
+            ExportFormat::Csv | ExportFormat::Parquet => {
+                con.execute_batch(&format!(
+                    "COPY ({query}) TO {} (FORMAT {extension}{});",
+                    sql_string_literal(&path.to_string_lossy()),
+                    if args.format == ExportFormat::Csv {
+                        ", HEADER"
+                    } else {
+                        ""
+                    }
+                ))?;
+            },
+            ExportFormat::Jsonl => export_view_jsonl(&con, &query, &path)?,
+            ExportFormat::Xlsx => export_view_xlsx(&con, &query, view, &path)?,
+        }
+        println!(
+            "{} {}.{} {} {}",
+            "Exported".green(),
+            schema,
+            view,
+            "to".green(),
+            path.display().to_string().blue()
+        );
+    }
+    Ok(())
+}
+
+fn query_table(con: &duckdb::Connection, query: &str, limit: Option<usize>) -> Result<Table> {
+    let mut stmt = con.prepare(query)?;
+    let mut rows = stmt.query([])?;
+    let column_names = rows
+        .as_ref()
+        .context("Query result must have a statement")?
+        .column_names();
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record(column_names.iter().cloned());
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    while remaining > 0
+        && let Some(row) = rows.next()?
+    {
+        remaining -= 1;
+        let mut record = Vec::with_capacity(column_names.len());
+        for idx in 0..column_names.len() {
+            record.push(display_value(row.get(idx)?));
+        }
+        builder.push_record(record);
+    }
+    let mut table = builder.build();
+    table.with(Style::rounded());
+    Ok(table)
+}
+
+fn run_query(args: QueryArgs) -> Result<()> {
+    let sql = match (args.sql, args.file.as_ref()) {
+        (Some(sql), _) => sql,
+        (None, Some(path)) => std::fs::read_to_string(path)?,
+        (None, None) => return Err(eyre!("Provide SQL as an argument or with `--file`")),
+    };
+    if sql.trim().is_empty() {
+        return Err(eyre!("SQL query is empty"));
+    }
+
+    let con = match args.input.as_ref() {
+        Some(input) if args.read_write => {
+            if !input.exists() {
+                return Err(eyre!("DuckDB file does not exist: {}", input.display()));
+            }
+            duckdb::Connection::open(input)?
+        },
+        Some(input) => open_read_only_connection(input)?,
+        None => duckdb::Connection::open_in_memory()?,
+    };
+    crate::register_table_functions(&con)?;
+
+    if args.format == QueryFormat::Parquet {
+        let path = args
+            .out
+            .as_ref()
+            .ok_or_else(|| eyre!("`--format parquet` requires `--out`"))?;
+        return write_query_parquet(&con, &sql, args.limit, path);
+    }
+
+    let mut writer: Box<dyn std::io::Write> = match args.out.as_ref() {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match args.format {
+        QueryFormat::Table => {
+            writeln!(writer, "{}", query_table(&con, &sql, args.limit)?)?;
+            writer.flush()?;
+        },
+        QueryFormat::Jsonl => write_query_jsonl(&con, &sql, args.limit, &mut writer)?,
+        QueryFormat::Csv => write_query_csv(&con, &sql, args.limit, &mut writer)?,
+        QueryFormat::Parquet => unreachable!("parquet output is handled above"),
+    }
+    Ok(())
+}
+
+const DIFF_SIDES: [(&str, &str); 2] = [("base", "diff_base"), ("other", "diff_other")];
+
+/// `--match-by` for two attached databases: `guid` only when both have the
+/// `raw.objects.guid` column added in schema version 3.
+fn resolve_diff_match_by(
+    con: &duckdb::Connection,
+    requested: Option<DiffMatchBy>,
+) -> Result<DiffMatchBy> {
+    let mut versions = Vec::with_capacity(DIFF_SIDES.len());
+    for (_, catalog) in DIFF_SIDES {
+        let version = query_rows(
+            con,
+            &format!("SELECT value FROM {catalog}.main.plexos2duckdb WHERE key = 'schema_version'"),
+            |row| row.get::<_, String>(0),
+        )?
+        .first()
+        .map(|value| {
+            value
+                .trim()
+                .parse::<u32>()
+                .map_err(|err| eyre!("Invalid schema_version {value:?} in {catalog}: {err}"))
+        })
+        .transpose()?
+        .unwrap_or(1);
+        versions.push(version);
+    }
+    let has_guids = versions.iter().all(|version| *version >= 3);
+    match requested {
+        Some(DiffMatchBy::Guid) if !has_guids => Err(eyre!(
+            "`--match-by guid` needs schema version 3 or later in both databases (found {} and {}); run `migrate` or use `--match-by name`",
+            versions[0],
+            versions[1]
+        )),
+        Some(match_by) => Ok(match_by),
+        None if has_guids => Ok(DiffMatchBy::Guid),
+        None => Ok(DiffMatchBy::Name),
+    }
+}
+
+/// Create temp views that give objects, memberships and property units of each
+/// attached database a key that is stable across conversions. Objects match on
+/// class and name, or on their GUID.
+fn create_diff_dimension_views(con: &duckdb::Connection, match_by: DiffMatchBy) -> Result<()> {
+    let object_key = match match_by {
+        DiffMatchBy::Name => "c.name || '/' || o.name",
+        DiffMatchBy::Guid => "COALESCE(NULLIF(o.guid, ''), c.name || '/' || o.name)",
+    };
+    for (side, catalog) in DIFF_SIDES {
+        con.execute_batch(&format!(
+            "
+            CREATE OR REPLACE TEMP VIEW {side}_objects AS
+              SELECT o.object_id, c.name AS class, o.name, {object_key} AS object_key
+              FROM {catalog}.raw.objects o
+              JOIN {catalog}.raw.classes c ON o.class_id = c.class_id;
+
+            CREATE OR REPLACE TEMP VIEW {side}_memberships AS
+              SELECT
+                m.membership_id,
+                col.name AS collection,
+                p.class || '/' || p.name AS parent,
+                ch.class || '/' || ch.name AS child,
+                col.name || '|' || p.object_key || '|' || ch.object_key AS membership_key
+              FROM {catalog}.raw.memberships m
+              JOIN {catalog}.raw.collections col ON m.collection_id = col.collection_id
+              JOIN {side}_objects p ON m.parent_id = p.object_id
+              JOIN {side}_objects ch ON m.child_id = ch.object_id;
+
+            CREATE OR REPLACE TEMP VIEW {side}_units AS
+                SELECT c.name AS collection, p.name AS property, u.unit_name AS unit
+                FROM {catalog}.raw.properties p
+                LEFT JOIN {catalog}.raw.collections c ON p.collection_id = c.collection_id
+                LEFT JOIN {catalog}.raw.units u ON p.unit_id = u.unit_id
+              UNION ALL
+                SELECT c.name AS collection, p.summary_name AS property, u.unit_name AS unit
+                FROM {catalog}.raw.properties p
+                LEFT JOIN {catalog}.raw.collections c ON p.collection_id = c.collection_id
+                LEFT JOIN {catalog}.raw.units u ON p.summary_unit_id = u.unit_id;
+            "
+        ))?;
+    }
+    Ok(())
+}
+
+fn create_diff_tables(con: &duckdb::Connection, top: usize) -> Result<()> {
+    con.execute_batch(
+        "
+        CREATE SCHEMA IF NOT EXISTS diff;
+
+        CREATE OR REPLACE TABLE diff.tables AS
+          WITH b AS (
+            SELECT table_schema, table_name FROM information_schema.tables
+            WHERE table_catalog = 'diff_base'
+          ), o AS (
+            SELECT table_schema, table_name FROM information_schema.tables
+            WHERE table_catalog = 'diff_other'
+          )
+          SELECT
+            CASE WHEN o.table_name IS NULL THEN 'only_base' ELSE 'only_other' END AS status,
+            COALESCE(b.table_schema, o.table_schema) AS table_schema,
+            COALESCE(b.table_name, o.table_name) AS table_name
+          FROM b FULL OUTER JOIN o
+            ON b.table_schema = o.table_schema AND b.table_name = o.table_name
+          WHERE b.table_name IS NULL OR o.table_name IS NULL
+          ORDER BY table_schema, table_name;
+
+        CREATE OR REPLACE TABLE diff.objects AS
+          SELECT
+            CASE
+              WHEN o.object_key IS NULL THEN 'removed'
+              WHEN b.object_key IS NULL THEN 'added'
+              ELSE 'renamed'
+            END AS status,
+            COALESCE(b.class, o.class) AS class,
+            CASE
+              WHEN b.object_key IS NOT NULL AND o.object_key IS NOT NULL THEN b.name || ' -> ' || o.name
+              ELSE COALESCE(b.name, o.name)
+            END AS name
+          FROM base_objects b FULL OUTER JOIN other_objects o ON b.object_key = o.object_key
+          WHERE b.object_key IS NULL OR o.object_key IS NULL OR b.name <> o.name
+          ORDER BY class, name, status;
+
+        CREATE OR REPLACE TABLE diff.memberships AS
+          SELECT
+            CASE WHEN o.membership_key IS NULL THEN 'removed' ELSE 'added' END AS status,
+            COALESCE(b.collection, o.collection) AS collection,
+            COALESCE(b.parent, o.parent) AS parent,
+            COALESCE(b.child, o.child) AS child
+          FROM base_memberships b
+          FULL OUTER JOIN other_memberships o ON b.membership_key = o.membership_key
+          WHERE b.membership_key IS NULL OR o.membership_key IS NULL
+          ORDER BY collection, parent, child, status;
+
+        CREATE OR REPLACE TABLE diff.units AS
+          SELECT DISTINCT b.collection, b.property, b.unit AS base_unit, o.unit AS other_unit
+          FROM base_units b
+          JOIN other_units o ON b.collection = o.collection AND b.property = o.property
+          WHERE b.unit IS DISTINCT FROM o.unit
+          ORDER BY b.collection, b.property;
+
+        CREATE OR REPLACE TABLE diff.data_summary (
+          table_name VARCHAR,
+          matched_rows BIGINT,
+          only_base_rows BIGINT,
+          only_other_rows BIGINT,
+          max_abs_delta DOUBLE,
+          total_delta DOUBLE
+        );
+
+        CREATE OR REPLACE TABLE diff.data_top_objects (
+          table_name VARCHAR,
+          object VARCHAR,
+          total_abs_delta DOUBLE,
+          total_delta DOUBLE
+        );
+        ",
+    )?;
+
+    let mut stmt = con.prepare(
+        "
+        SELECT b.table_name
+        FROM information_schema.tables b
+        JOIN information_schema.tables o
+          ON o.table_catalog = 'diff_other' AND o.table_schema = 'data' AND o.table_name = b.table_name
+        WHERE b.table_catalog = 'diff_base' AND b.table_schema = 'data'
+        ORDER BY b.table_name
+        ",
+    )?;
+    let data_tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for table_name in data_tables {
+        let table_ident = quote_ident(&table_name);
+        let table_literal = sql_string_literal(&table_name);
+        con.execute_batch(&format!(
+            "
+            CREATE OR REPLACE TEMP TABLE diff_joined AS
+              WITH b AS (
+                SELECT m.membership_key, m.child AS object, d.sample_id, d.band_id, d.block_id, d.value
+                FROM diff_base.data.{table_ident} d
+                JOIN base_memberships m ON d.membership_id = m.membership_id
+              ), o AS (
+                SELECT m.membership_key, m.child AS object, d.sample_id, d.band_id, d.block_id, d.value
+                FROM diff_other.data.{table_ident} d
+                JOIN other_memberships m ON d.membership_id = m.membership_id
+              )
+              SELECT
+                COALESCE(b.object, o.object) AS object,
+                b.membership_key IS NOT NULL AS in_base,
+                o.membership_key IS NOT NULL AS in_other,
+                b.value AS base_value,
+                o.value AS other_value,
+                COALESCE(o.value, 0) - COALESCE(b.value, 0) AS delta
+              FROM b FULL OUTER JOIN o
+                ON b.membership_key = o.membership_key
+                AND b.sample_id = o.sample_id
+                AND b.band_id = o.band_id
+                AND b.block_id = o.block_id;
+
+            INSERT INTO diff.data_summary
+              SELECT
+                {table_literal},
+                COUNT(*) FILTER (WHERE in_base AND in_other),
+                COUNT(*) FILTER (WHERE NOT in_other),
+                COUNT(*) FILTER (WHERE NOT in_base),
+                COALESCE(MAX(ABS(delta)), 0),
+                COALESCE(SUM(delta), 0)
+              FROM diff_joined;
+
+            INSERT INTO diff.data_top_objects
+              SELECT {table_literal}, object, SUM(ABS(delta)) AS total_abs_delta, SUM(delta)
+              FROM diff_joined
+              GROUP BY object
+              HAVING SUM(ABS(delta)) > 0
+              ORDER BY total_abs_delta DESC, object
+              LIMIT {top};
+            "
+        ))?;
+    }
+    con.execute_batch("DROP TABLE IF EXISTS diff_joined;")?;
+    Ok(())
+}
+
+fn query_rows<T>(
+    con: &duckdb::Connection,
+    sql: &str,
+    f: impl FnMut(&duckdb::Row<'_>) -> duckdb::Result<T>,
+) -> Result<Vec<T>> {
+    let mut stmt = con.prepare(sql)?;
+    let rows = stmt.query_map([], f)?;
+    Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+fn load_diff_report(con: &duckdb::Connection, args: &DiffArgs) -> Result<DiffJsonOutput> {
+    Ok(DiffJsonOutput {
+        base: args.base.display().to_string(),
+        other: args.other.display().to_string(),
+        tables: query_rows(
+            con,
+            "SELECT status, table_schema, table_name FROM diff.tables",
+            |row| {
+                Ok(DiffTableRow {
+                    status: row.get(0)?,
+                    schema: row.get(1)?,
+                    table: row.get(2)?,
+                })
+            },
+        )?,
+        objects: query_rows(con, "SELECT status, class, name FROM diff.objects", |row| {
+            Ok(DiffObjectRow {
+                status: row.get(0)?,
+                class: row.get(1)?,
+                name: row.get(2)?,
+            })
+        })?,
+        memberships: query_rows(
+            con,
+            "SELECT status, collection, parent, child FROM diff.memberships",
+            |row| {
+                Ok(DiffMembershipRow {
+                    status: row.get(0)?,
+                    collection: row.get(1)?,
+                    parent: row.get(2)?,
+                    child: row.get(3)?,
+                })
+            },
+        )?,
+        units: query_rows(
+            con,
+            "SELECT collection, property, base_unit, other_unit FROM diff.units",
+            |row| {
+                Ok(DiffUnitRow {
+                    collection: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                    property: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    base_unit: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    other_unit: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            },
+        )?,
+        data: query_rows(
+            con,
+            "SELECT * FROM diff.data_summary ORDER BY table_name",
+            |row| {
+                Ok(DiffDataRow {
+                    table: row.get(0)?,
+                    matched_rows: row.get(1)?,
+                    only_base_rows: row.get(2)?,
+                    only_other_rows: row.get(3)?,
+                    max_abs_delta: row.get(4)?,
+                    total_delta: row.get(5)?,
+                })
+            },
+        )?,
+        top_objects: query_rows(
+            con,
+            "SELECT * FROM diff.data_top_objects ORDER BY table_name, total_abs_delta DESC, object",
+            |row| {
+                Ok(DiffTopObjectRow {
+                    table: row.get(0)?,
+                    object: row.get(1)?,
+                    total_abs_delta: row.get(2)?,
+                    total_delta: row.get(3)?,
+                })
+            },
+        )?,
+    })
+}
+
+fn print_diff_section<T: Tabled>(title: &str, rows: Vec<T>) {
+    println!("{title}");
+    if rows.is_empty() {
+        println!("{}", "no differences".dimmed());
+    } else {
+        println!("{}", Table::new(rows).with(Style::rounded()));
+    }
+    println!();
+}
+
+fn diff_databases(args: DiffArgs) -> Result<()> {
+    for path in [&args.base, &args.other] {
+        if !path.exists() {
+            return Err(eyre!("DuckDB file does not exist: {}", path.display()));
+        }
+    }
+
+    let con = if let Some(out) = args.out.as_ref() {
+        if out.exists() {
+            if !args.force {
+                return Err(eyre!(
+                    "Output file already exists: \"{}\". Re-run with `--force` to overwrite it",
+                    out.display().to_string().bold()
+                ));
+            }
+            std::fs::remove_file(out)?;
+        }
+        duckdb::Connection::open(out)?
+    } else {
+        duckdb::Connection::open_in_memory()?
+    };
+    for ((_, catalog), path) in DIFF_SIDES.iter().zip([&args.base, &args.other]) {
+        con.execute_batch(&format!(
+            "ATTACH {} AS {catalog} (READ_ONLY);",
+            sql_string_literal(&path.to_string_lossy())
+        ))?;
+    }
+
+    let match_by = resolve_diff_match_by(&con, args.match_by)?;
+    create_diff_dimension_views(&con, match_by)?;
+    create_diff_tables(&con, args.top)?;
+    let report = load_diff_report(&con, &args)?;
+    for (_, catalog) in DIFF_SIDES {
+        con.execute_batch(&format!("DETACH {catalog};"))?;
+    }
+    drop(con);
+
+    if args.format_diagnostics == OutputFormat::Json {
+        return print_json(&report);
+    }
+
+    println!(
+        "{} {} {} {}",
+        "Comparing".green(),
+        report.base.blue(),
+        "with".green(),
+        report.other.blue()
+    );
+    println!();
+    print_diff_section("Tables present in only one database", report.tables);
+    print_diff_section("Objects added or removed", report.objects);
+    print_diff_section("Memberships added or removed", report.memberships);
+    print_diff_section("Property units changed", report.units);
+    print_diff_section("Data value differences", report.data);
+    print_diff_section("Objects with the largest differences", report.top_objects);
+    if let Some(out) = args.out.as_ref() {
+        println!(
+            "{} {}",
+            "Diff tables written to:".green(),
+            out.display().to_string().blue()
+        );
+    }
+    Ok(())
+}
+
+/// Dimension tables whose IDs come from the PLEXOS schema rather than the
+/// model, so they are stored once in a merged database instead of per scenario.
+const MERGE_SHARED_RAW_TABLES: [(&str, &str); 5] = [
+    ("classes", "class_id"),
+    ("class_groups", "class_group_id"),
+    ("collections", "collection_id"),
+    ("properties", "property_id"),
+    ("units", "unit_id"),
+];
+
+/// Release part of a `plexos2duckdb_version` value, without git describe or build info.
+fn converter_release(version: &str) -> &str {
+    static RELEASE: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| {
+        regex::Regex::new(r"^\d+\.\d+\.\d+(-[A-Za-z]+\.\d+)?").expect("valid release regex")
+    });
+    RELEASE
+        .find(version)
+        .map(|release| release.as_str())
+        .unwrap_or(version)
+}
+
+fn merge_scenario_names(args: &MergeArgs) -> Result<Vec<String>> {
+    let names = if args.scenario.is_empty() {
+        args.input
+            .iter()
+            .map(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| eyre!("Cannot derive a scenario name from {}", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else if args.scenario.len() == args.input.len() {
+        args.scenario.clone()
+    } else {
+        return Err(eyre!(
+            "Expected {} `--scenario` names, one per `--input`, but got {}",
+            args.input.len(),
+            args.scenario.len()
+        ));
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    for name in &names {
+        if !seen.insert(name) {
+            return Err(eyre!(
+                "Duplicate scenario name {name:?}; pass unique `--scenario` names"
+            ));
+        }
+    }
+    Ok(names)
+}
+
+fn merge_source_tables(
+    con: &duckdb::Connection,
+    catalogs: &[String],
+    schema: &str,
+) -> Result<std::collections::BTreeMap<String, Vec<usize>>> {
+    let mut tables = std::collections::BTreeMap::<String, Vec<usize>>::new();
+    for (idx, catalog) in catalogs.iter().enumerate() {
+        let names = query_rows(
+            con,
+            &format!(
+                "SELECT table_name FROM information_schema.tables
+                 WHERE table_catalog = {} AND table_schema = {}",
+                sql_string_literal(catalog),
+                sql_string_literal(schema)
+            ),
+            |row| row.get::<_, String>(0),
+        )?;
+        for name in names {
+            tables.entry(name).or_default().push(idx);
+        }
+    }
+    Ok(tables)
+}
+
+fn merge_union_sql(
+    catalogs: &[String],
+    scenarios: &[String],
+    sources: &[usize],
+    schema: &str,
+    table: &str,
+    with_scenario: bool,
+) -> String {
+    sources
+        .iter()
+        .map(|&idx| {
+            let scenario = if with_scenario {
+                format!("{} AS scenario, ", sql_string_literal(&scenarios[idx]))
+            } else {
+                String::new()
+            };
+            format!(
+                "SELECT {scenario}* FROM {}.{}.{}",
+                quote_ident(&catalogs[idx]),
+                quote_ident(schema),
+                quote_ident(table)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" UNION ALL BY NAME ")
+}
+
+/// Removes a file on drop unless [`Self::disarm`] is called, so a failed
+/// merge does not leave a partial database behind.
+struct CreatedFileGuard {
+    path: Option<std::path::PathBuf>,
+}
+
+impl CreatedFileGuard {
+    fn disarm(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for CreatedFileGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = std::fs::remove_file(&path);
+            let mut wal = path.into_os_string();
+            wal.push(".wal");
+            let _ = std::fs::remove_file(wal);
+        }
+    }
+}
+
+fn merge_databases(args: MergeArgs) -> Result<()> {
+    let scenarios = merge_scenario_names(&args)?;
+    for path in &args.input {
+        if !path.exists() {
+            return Err(eyre!("DuckDB file does not exist: {}", path.display()));
+        }
+    }
+    if args.output.exists() {
+        if !args.force {
+            return Err(eyre!(
+                "Output file already exists: \"{}\". Re-run with `--force` to overwrite it",
+                args.output.display().to_string().bold()
+            ));
+        }
+        std::fs::remove_file(&args.output)?;
+    }
+
+    let output_guard = CreatedFileGuard {
+        path: Some(args.output.clone()),
+    };
+    let con = duckdb::Connection::open(&args.output)?;
+    let catalogs = (0..args.input.len())
+        .map(|idx| format!("merge_source_{idx}"))
+        .collect::<Vec<_>>();
+    let mut metadata = Vec::with_capacity(args.input.len());
+    for (catalog, path) in catalogs.iter().zip(&args.input) {
+        con.execute_batch(&format!(
+            "ATTACH {} AS {} (READ_ONLY);",
+            sql_string_literal(&path.to_string_lossy()),
+            quote_ident(catalog)
+        ))?;
+        let mut source = load_database_metadata_from(&con, catalog)?;
+        source.database = path.display().to_string();
+        metadata.push(source);
+    }
+
+    let expected_release = converter_release(&metadata[0].converter_version).to_string();
+    for source in &metadata[1..] {
+        let release = converter_release(&source.converter_version);
+        if release != expected_release && !args.allow_version_mismatch {
+            return Err(eyre!(
+                "{} was written by plexos2duckdb {} but {} by {}; re-run with `--allow-version-mismatch` to merge anyway",
+                source.database,
+                release,
+                metadata[0].database,
+                expected_release
+            ));
+        }
+    }
+
+    con.execute_batch(
+        "CREATE SCHEMA IF NOT EXISTS raw;
+         CREATE SCHEMA IF NOT EXISTS data;",
+    )?;
+
+    let raw_tables = merge_source_tables(&con, &catalogs, "raw")?;
+    for (table, sources) in &raw_tables {
+        let shared_id = MERGE_SHARED_RAW_TABLES
+            .iter()
+            .find(|(name, _)| name == table)
+            .map(|(_, id)| *id);
+        let table_ident = quote_ident(table);
+        let union = merge_union_sql(
+            &catalogs,
+            &scenarios,
+            sources,
+            "raw",
+            table,
+            shared_id.is_none(),
+        );
+        match shared_id {
+            Some(id) => {
+                con.execute_batch(&format!(
+                    "CREATE TABLE raw.{table_ident} AS SELECT DISTINCT * FROM ({union}) ORDER BY {id};"
+                ))?;
+                let conflict = query_rows(
+                    &con,
+                    &format!(
+                        "SELECT {id} FROM raw.{table_ident} GROUP BY {id} HAVING COUNT(*) > 1 ORDER BY {id} LIMIT 1"
+                    ),
+                    |row| row.get::<_, i64>(0),
+                )?;
+                if let Some(conflict) = conflict.first() {
+                    return Err(eyre!(
+                        "Cannot reconcile raw.{table}: {id} {conflict} differs between scenarios"
+                    ));
+                }
+            },
+            None => {
+                con.execute_batch(&format!("CREATE TABLE raw.{table_ident} AS {union};"))?;
+            },
+        }
+    }
+
+    let data_tables = merge_source_tables(&con, &catalogs, "data")?;
+    for (table, sources) in &data_tables {
+        let union = merge_union_sql(&catalogs, &scenarios, sources, "data", table, true);
+        con.execute_batch(&format!(
+            "CREATE TABLE data.{} AS {union};",
+            quote_ident(table)
+        ))
+        .wrap_err_with(|| format!("Cannot copy data.{table} into the merged database"))?;
+    }
+
+    con.execute_batch(
+        "CREATE TABLE main.scenarios (
+           scenario VARCHAR,
+           database VARCHAR,
+           plexos_file VARCHAR,
+           model_name VARCHAR,
+           plexos2duckdb_version VARCHAR
+         );
+         CREATE TABLE main.plexos2duckdb (key TEXT, value TEXT);",
+    )?;
+    let mut appender = con.appender("scenarios")?;
+    for (scenario, source) in scenarios.iter().zip(&metadata) {
+        appender.append_row(duckdb::params![
+            scenario,
+            source.database,
+            source.source_file,
+            source.model_name,
+            source.converter_version
+        ])?;
+    }
+    appender.flush()?;
+    drop(appender);
+    let mut appender = con.appender("plexos2duckdb")?;
+    appender.append_row(duckdb::params![
+        "plexos2duckdb_version",
+        crate::utils::version()
+    ])?;
+    appender.append_row(duckdb::params![
+        "schema_version",
+        crate::SCHEMA_VERSION.to_string()
+    ])?;
+    appender.append_row(duckdb::params![
+        "duckdb_file_created_at",
+        chrono::Utc::now().to_string()
+    ])?;
+    let join = |values: Vec<&str>| values.join(", ");
+    appender.append_row(duckdb::params![
+        "plexos_file",
+        join(
+            metadata
+                .iter()
+                .map(|source| source.source_file.as_str())
+                .collect()
+        )
+    ])?;
+    appender.append_row(duckdb::params![
+        "model_name",
+        join(
+            metadata
+                .iter()
+                .map(|source| source.model_name.as_str())
+                .collect()
+        )
+    ])?;
+    appender.append_row(duckdb::params![
+        "scenarios",
+        join(scenarios.iter().map(String::as_str).collect())
+    ])?;
+    appender.flush()?;
+    drop(appender);
+
+    let timestamp_blocks = raw_tables
+        .keys()
+        .filter_map(|table| table.strip_prefix("timestamp_block_").map(str::to_string))
+        .collect::<Vec<_>>();
+    let data_tables = data_tables
+        .keys()
+        .map(|table| {
+            let block = crate::sink::timestamp_block_name(table);
+            (table.clone(), timestamp_blocks.contains(&block))
+        })
+        .collect::<Vec<_>>();
+    con.execute_batch(&crate::SolutionDataset::processed_views_sql_for_blocks(
+        timestamp_blocks,
+        crate::ViewDialect::MergedDuckDb,
+    ))?;
+    con.execute_batch(&crate::SolutionDataset::report_views_sql_for_tables(
+        &data_tables,
+        crate::ViewDialect::MergedDuckDb,
+    )?)?;
+
+    for catalog in &catalogs {
+        con.execute_batch(&format!("DETACH {};", quote_ident(catalog)))?;
+    }
+    con.execute_batch("CHECKPOINT;")?;
+    drop(con);
+    output_guard.disarm();
+
+    println!(
+        "{} {} {} {}",
+        "Merged".green(),
+        scenarios.len(),
+        "scenarios into:".green(),
+        args.output.display().to_string().blue()
+    );
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct MigrateJsonOutput {
+    database: String,
+    from_version: u32,
+    to_version: u32,
+    created_tables: Vec<String>,
+    added_columns: Vec<String>,
+    recreated_views: usize,
 }
 
-fn inspect_database(args: InspectArgs) -> Result<()> {
+fn migrate_database(args: MigrateArgs) -> Result<()> {
     if !args.input.exists() {
         return Err(eyre!(
             "DuckDB file does not exist: {}",
             args.input.display()
         ));
     }
-    let con = duckdb::Connection::open(&args.input)?;
-    let mut metadata = load_database_metadata(&con)?;
-    metadata.database = args.input.display().to_string();
-    let inventory = load_table_inventory(&con)?;
+    let mut con = duckdb::Connection::open(&args.input)?;
+    let summary = crate::migrate_database(&mut con)?;
+    drop(con);
 
     if args.format_diagnostics == OutputFormat::Json {
-        return print_json(&InspectJsonOutput {
-            metadata,
-            inventory,
+        return print_json(&MigrateJsonOutput {
+            database: args.input.display().to_string(),
+            from_version: summary.from_version,
+            to_version: summary.to_version,
+            created_tables: summary.created_tables,
+            added_columns: summary.added_columns,
+            recreated_views: summary.recreated_views,
         });
     }
-
-    let metadata_rows = vec![
-        MetadataRow {
-            field: "database".to_string(),
-            value: metadata.database,
-        },
-        MetadataRow {
-            field: "converter version".to_string(),
-            value: metadata.converter_version,
-        },
-        MetadataRow {
-            field: "source file".to_string(),
-            value: metadata.source_file,
-        },
-        MetadataRow {
-            field: "model name".to_string(),
-            value: metadata.model_name,
-        },
-    ];
-
-    println!("Metadata");
-    println!("{}", Table::new(metadata_rows).with(Style::rounded()));
-    println!();
-    println!("Inventory");
-    println!("{}", Table::new(inventory).with(Style::rounded()));
+    if summary.from_version == summary.to_version {
+        println!(
+            "{} {} {}",
+            args.input.display().to_string().blue(),
+            "is already at schema version".green(),
+            summary.to_version
+        );
+        return Ok(());
+    }
+    for table in &summary.created_tables {
+        println!("  created table {table}");
+    }
+    for column in &summary.added_columns {
+        println!("  added column {column}");
+    }
+    println!("  recreated {} views", summary.recreated_views);
+    println!(
+        "{} {} {} {} {}",
+        "Migrated".green(),
+        args.input.display().to_string().blue(),
+        "from schema version".green(),
+        summary.from_version,
+        format!("to {}", summary.to_version).green()
+    );
     Ok(())
 }
 
@@ -389,13 +3241,74 @@ fn print_total_time(start_time: Option<Instant>) {
     }
 }
 
+/// Cancel token shared by every convert run in the process. The Ctrl-C handler
+/// can only be installed once, so each run resets this token instead of
+/// installing a handler of its own.
+static CANCEL_TOKEN: std::sync::LazyLock<std::sync::Arc<std::sync::atomic::AtomicBool>> =
+    std::sync::LazyLock::new(Default::default);
+
+/// Install the Ctrl-C handler on first use and return the reset cancel token.
+/// The first Ctrl-C cancels the conversion so partial output is cleaned up; a
+/// second one exits immediately.
+fn convert_cancel_token() -> Result<std::sync::Arc<std::sync::atomic::AtomicBool>> {
+    static HANDLER: std::sync::OnceLock<std::result::Result<(), String>> =
+        std::sync::OnceLock::new();
+    HANDLER
+        .get_or_init(|| {
+            let cancel_token = CANCEL_TOKEN.clone();
+            ctrlc::set_handler(move || {
+                if cancel_token.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                    let _ = Term::stderr().show_cursor();
+                    eprintln!();
+                    std::process::exit(130);
+                }
+            })
+            .map_err(|err| err.to_string())
+        })
+        .clone()
+        .map_err(|err| eyre!("Failed to install the Ctrl-C handler: {err}"))?;
+    CANCEL_TOKEN.store(false, std::sync::atomic::Ordering::Relaxed);
+    Ok(CANCEL_TOKEN.clone())
+}
+
 fn convert(args: ConvertArgs) -> Result<()> {
     let json_mode = args.format_diagnostics == OutputFormat::Json;
-    let input_path = resolve_input_path(&args.input)?;
+    let input = args
+        .input
+        .as_deref()
+        .context("`--input` is required unless the config file sets `input`")?;
+    let input_path = resolve_input_path(input)?;
     let input_dir = input_path
         .parent()
         .ok_or_else(|| eyre!("Input path has no parent directory"))?;
-    let output_path = resolve_output_path(&input_path, args.output, args.force)?;
+    if args.format != ConvertFormat::Duckdb && args.external_data_parquet_dir.is_some() {
+        return Err(eyre!(
+            "`--external-data-parquet-dir` cannot be combined with `--format {}`",
+            args.format.name()
+        ));
+    }
+    if matches!(args.format, ConvertFormat::Sqlite | ConvertFormat::Arrow)
+        && args.external_data_parquet_layout != ParquetLayout::Table
+    {
+        return Err(eyre!(
+            "`--external-data-parquet-layout` cannot be combined with `--format {}`",
+            args.format.name()
+        ));
+    }
+    if args.format != ConvertFormat::Arrow && args.arrow_compression != ArrowCompression::None {
+        return Err(eyre!("`--arrow-compression` requires `--format arrow`"));
+    }
+    if args.format == ConvertFormat::Duckdb
+        && args.external_data_parquet_dir.is_none()
+        && args.external_data_parquet_layout != ParquetLayout::Table
+    {
+        return Err(eyre!(
+            "`--external-data-parquet-layout` requires `--external-data-parquet-dir` or `--format parquet`"
+        ));
+    }
+    let convert_config = convert_config_json(&args)?;
+    let output_path =
+        resolve_output_path(&input_path, args.output.clone(), args.format, args.force)?;
     let table_name_pattern =
         if let Some(pattern) = args.table_name_pattern.as_deref() {
             Some(regex::Regex::new(pattern).map_err(|err| {
@@ -404,6 +3317,15 @@ fn convert(args: ConvertArgs) -> Result<()> {
         } else {
             None
         };
+    let data_key_filter = data_key_filter(&args)?;
+    let data_time_window = data_time_window(&args)?;
+    let downsample = downsample(&args)?;
+    if downsample.is_some() && args.format != ConvertFormat::Duckdb {
+        return Err(eyre!(
+            "`--downsample` cannot be combined with `--format {}`",
+            args.format.name()
+        ));
+    }
 
     let mut mp = None;
     let mut pb = None;
@@ -433,13 +3355,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
         mp = Some(multi);
         term = Some(term_handle);
     }
-    if let Some(term) = term.clone() {
-        let _ = ctrlc::set_handler(move || {
-            let _ = term.show_cursor();
-            eprintln!();
-            std::process::exit(130);
-        });
-    }
+    let cancel_token = convert_cancel_token()?;
     struct CursorGuard(Option<Term>);
     impl Drop for CursorGuard {
         fn drop(&mut self) {
@@ -456,29 +3372,29 @@ fn convert(args: ConvertArgs) -> Result<()> {
             });
             return;
         }
-        if let Some(spinner) = pb.as_ref() {
-            if msg != last_msg {
-                let now = Instant::now();
-                if !last_msg.is_empty() {
-                    let delta = last_mark.map(|s| now.duration_since(s)).unwrap_or_default();
-                    let line = format!("[+{:>6.2}s]", delta.as_secs_f64())
-                        .dimmed()
-                        .to_string();
-                    let msg = last_msg.cyan().to_string();
-                    spinner.println(format!("{line} {msg}"));
-                }
-                spinner.set_message(msg.to_string());
-                last_msg.clear();
-                last_msg.push_str(msg);
-                last_mark = Some(now);
+        if let Some(spinner) = pb.as_ref()
+            && msg != last_msg
+        {
+            let now = Instant::now();
+            if !last_msg.is_empty() {
+                let delta = last_mark.map(|s| now.duration_since(s)).unwrap_or_default();
+                let line = format!("[+{:>6.2}s]", delta.as_secs_f64())
+                    .dimmed()
+                    .to_string();
+                let msg = last_msg.cyan().to_string();
+                spinner.println(format!("{line} {msg}"));
             }
+            spinner.set_message(msg.to_string());
+            last_msg.clear();
+            last_msg.push_str(msg);
+            last_mark = Some(now);
         }
     };
 
-    let mut report_data = |event: plexos2duckdb::ProgressEvent| {
+    let mut report_data = |event: crate::ProgressEvent| {
         if json_mode {
             let json_event = match event {
-                plexos2duckdb::ProgressEvent::DataTableStart {
+                crate::ProgressEvent::DataTableStart {
                     index,
                     total,
                     table_name,
@@ -489,8 +3405,8 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     table_name,
                     keys,
                 },
-                plexos2duckdb::ProgressEvent::DataTableEnd => ConvertJsonEvent::DataTableEnd,
-                plexos2duckdb::ProgressEvent::DataWorkerTableStart {
+                crate::ProgressEvent::DataTableEnd => ConvertJsonEvent::DataTableEnd,
+                crate::ProgressEvent::DataWorkerTableStart {
                     worker_id,
                     index,
                     total,
@@ -503,7 +3419,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     table_name,
                     keys,
                 },
-                plexos2duckdb::ProgressEvent::DataWorkerTableEnd {
+                crate::ProgressEvent::DataWorkerTableEnd {
                     worker_id,
                     index,
                     total,
@@ -512,7 +3428,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     index,
                     total,
                 },
-                plexos2duckdb::ProgressEvent::DataMergeTableStart {
+                crate::ProgressEvent::DataMergeTableStart {
                     index,
                     total,
                     table_name,
@@ -521,7 +3437,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     total,
                     table_name,
                 },
-                plexos2duckdb::ProgressEvent::DataMergeTableEnd { index, total } => {
+                crate::ProgressEvent::DataMergeTableEnd { index, total } => {
                     ConvertJsonEvent::DataMergeTableEnd { index, total }
                 },
             };
@@ -532,7 +3448,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
             return;
         }
         match event {
-            plexos2duckdb::ProgressEvent::DataTableStart {
+            crate::ProgressEvent::DataTableStart {
                 index,
                 total,
                 table_name,
@@ -543,18 +3459,18 @@ fn convert(args: ConvertArgs) -> Result<()> {
                 }
                 last_data_table_was_final = index == total;
                 current_table = Some(table_name);
-                if data_tables_pb.is_none() {
-                    if let Some(multi) = mp.as_ref() {
-                        let bar = multi.add(ProgressBar::new(total as u64));
-                        bar.set_style(
+                if data_tables_pb.is_none()
+                    && let Some(multi) = mp.as_ref()
+                {
+                    let bar = multi.add(ProgressBar::new(total as u64));
+                    bar.set_style(
                             ProgressStyle::with_template(
                                 "{prefix:>9.bold} {bar:10.cyan/blue} {pos:>2}/{len:2} {elapsed_precise:.dim} {msg:.cyan}",
                             )
                             .unwrap(),
                         );
-                        bar.set_prefix("tables");
-                        data_tables_pb = Some(bar);
-                    }
+                    bar.set_prefix("tables");
+                    data_tables_pb = Some(bar);
                 }
                 if let Some(bar) = data_tables_pb.as_ref() {
                     bar.set_length(total as u64);
@@ -563,33 +3479,31 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     bar.set_message(format!("{table} ({keys} keys)"));
                 }
             },
-            plexos2duckdb::ProgressEvent::DataTableEnd => {
+            crate::ProgressEvent::DataTableEnd => {
                 current_table = None;
-                if last_data_table_was_final {
-                    if let Some(bar) = data_tables_pb.as_ref() {
-                        bar.set_message("done");
-                    }
+                if last_data_table_was_final && let Some(bar) = data_tables_pb.as_ref() {
+                    bar.set_message("done");
                 }
             },
-            plexos2duckdb::ProgressEvent::DataWorkerTableStart {
+            crate::ProgressEvent::DataWorkerTableStart {
                 worker_id,
                 index,
                 total,
                 table_name,
                 keys,
             } => {
-                if !worker_tables_pb.contains_key(&worker_id) {
-                    if let Some(multi) = mp.as_ref() {
-                        let bar = multi.add(ProgressBar::new(total as u64));
-                        bar.set_style(
+                if !worker_tables_pb.contains_key(&worker_id)
+                    && let Some(multi) = mp.as_ref()
+                {
+                    let bar = multi.add(ProgressBar::new(total as u64));
+                    bar.set_style(
                             ProgressStyle::with_template(
                                 "{prefix:>9.bold} {bar:10.green/blue} {pos:>2}/{len:2} {elapsed_precise:.dim} {msg:.green}",
                             )
                             .unwrap(),
                         );
-                        bar.set_prefix(format!("thread-{}", worker_id + 1));
-                        worker_tables_pb.insert(worker_id, bar);
-                    }
+                    bar.set_prefix(format!("thread-{}", worker_id + 1));
+                    worker_tables_pb.insert(worker_id, bar);
                 }
                 if let Some(bar) = worker_tables_pb.get(&worker_id) {
                     bar.set_length(total as u64);
@@ -597,7 +3511,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     bar.set_message(format!("{table_name} ({keys} keys)"));
                 }
             },
-            plexos2duckdb::ProgressEvent::DataWorkerTableEnd {
+            crate::ProgressEvent::DataWorkerTableEnd {
                 worker_id,
                 index,
                 total,
@@ -610,23 +3524,23 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     }
                 }
             },
-            plexos2duckdb::ProgressEvent::DataMergeTableStart {
+            crate::ProgressEvent::DataMergeTableStart {
                 index,
                 total,
                 table_name,
             } => {
-                if data_merge_pb.is_none() {
-                    if let Some(multi) = mp.as_ref() {
-                        let bar = multi.add(ProgressBar::new(total as u64));
-                        bar.set_style(
+                if data_merge_pb.is_none()
+                    && let Some(multi) = mp.as_ref()
+                {
+                    let bar = multi.add(ProgressBar::new(total as u64));
+                    bar.set_style(
                             ProgressStyle::with_template(
                                 "{prefix:>9.bold} {bar:10.yellow/blue} {pos:>3}/{len:3} {elapsed_precise:.dim} {msg:.yellow}",
                             )
                             .unwrap(),
                         );
-                        bar.set_prefix("merge");
-                        data_merge_pb = Some(bar);
-                    }
+                    bar.set_prefix("merge");
+                    data_merge_pb = Some(bar);
                 }
                 if let Some(bar) = data_merge_pb.as_ref() {
                     bar.set_length(total as u64);
@@ -634,7 +3548,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                     bar.set_message(table_name);
                 }
             },
-            plexos2duckdb::ProgressEvent::DataMergeTableEnd { index, total } => {
+            crate::ProgressEvent::DataMergeTableEnd { index, total } => {
                 if let Some(bar) = data_merge_pb.as_ref() {
                     bar.set_length(total as u64);
                     bar.set_position(index as u64);
@@ -646,16 +3560,8 @@ fn convert(args: ConvertArgs) -> Result<()> {
         }
     };
 
-    let file_name = input_path
-        .file_name()
-        .context("File name must exist")?
-        .to_str()
-        .context("File name must be valid UTF-8")?;
-    let model_name = file_name
-        .trim_start_matches("Model ")
-        .trim_end_matches(" Solution")
-        .trim_end_matches(" Solution.zip")
-        .trim_end_matches(" Solution.xml");
+    let model_name = model_name(&input_path)?;
+    let model_name = model_name.as_str();
 
     let dataset = {
         let actual_input_path = if input_path.is_dir() {
@@ -664,7 +3570,7 @@ fn convert(args: ConvertArgs) -> Result<()> {
                 .map(|e| e.path())
                 .filter(|p| {
                     p.extension()
-                        .map_or(false, |ext| ext.eq_ignore_ascii_case("zip"))
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
                 })
                 .collect::<Vec<_>>();
             if zip_files.len() == 1 {
@@ -679,9 +3585,10 @@ fn convert(args: ConvertArgs) -> Result<()> {
         };
         if actual_input_path
             .extension()
-            .map_or(false, |ext| ext.eq_ignore_ascii_case("zip"))
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
         {
-            let mut ds = plexos2duckdb::SolutionDataset::default()
+            let mut ds = crate::SolutionDataset::default()
+                .with_cancel_token(cancel_token.clone())
                 .with_model_name(model_name.to_string())
                 .with_zip_file_with_progress(&actual_input_path, &mut report)?;
             let log_path = actual_input_path
@@ -696,9 +3603,10 @@ fn convert(args: ConvertArgs) -> Result<()> {
             ds
         } else if actual_input_path
             .extension()
-            .map_or(false, |ext| ext.eq_ignore_ascii_case("xml"))
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
         {
-            let mut ds = plexos2duckdb::SolutionDataset::default()
+            let mut ds = crate::SolutionDataset::default()
+                .with_cancel_token(cancel_token.clone())
                 .with_model_name(model_name.to_string())
                 .with_xml_file_with_progress(&actual_input_path, &mut report)?;
             let log_path = input_dir.join(format!("Model ( {} ) Log.txt", model_name));
@@ -757,14 +3665,33 @@ fn convert(args: ConvertArgs) -> Result<()> {
         return Ok(());
     }
 
-    report("Creating DuckDB database");
-    let mut builder = dataset.to_duckdb(&output_path);
+    report(match args.format {
+        ConvertFormat::Duckdb => "Creating DuckDB database",
+        ConvertFormat::Parquet => "Creating Parquet dataset",
+        ConvertFormat::Sqlite => "Creating SQLite database",
+        ConvertFormat::Arrow => "Creating Arrow IPC dataset",
+    });
+    let mut builder = dataset
+        .to_duckdb(&output_path)
+        .with_output_format(args.format.into())
+        .with_external_data_parquet_layout(args.external_data_parquet_layout.into())
+        .with_arrow_ipc_compression(args.arrow_compression.into())
+        .with_metadata("convert_config", convert_config);
     if let Some(threads) = args.n_threads {
         builder = builder.with_data_write_threads(threads.get());
     }
     if let Some(pattern) = table_name_pattern {
         builder = builder.with_data_table_name_pattern(pattern);
     }
+    if let Some(filter) = data_key_filter {
+        builder = builder.with_data_key_filter(filter);
+    }
+    if !data_time_window.is_unbounded() {
+        builder = builder.with_data_time_window(data_time_window);
+    }
+    if let Some(downsample) = downsample {
+        builder = builder.with_downsample(downsample);
+    }
     if let Some(path) = args.external_data_parquet_dir.as_ref() {
         builder = builder.with_external_data_parquet_dir(path);
     }
@@ -807,9 +3734,15 @@ fn convert(args: ConvertArgs) -> Result<()> {
         bar.finish_and_clear();
     }
     print_total_time(start_time);
+    let created_at = match args.format {
+        ConvertFormat::Duckdb => "DuckDB database created at:",
+        ConvertFormat::Parquet => "Parquet dataset created at:",
+        ConvertFormat::Sqlite => "SQLite database created at:",
+        ConvertFormat::Arrow => "Arrow IPC dataset created at:",
+    };
     println!(
         "{} {}",
-        "DuckDB database created at:".green(),
+        created_at.green(),
         output_path.display().to_string().blue()
     );
     Ok(())
@@ -831,8 +3764,48 @@ fn generate_completions(args: CompletionsArgs) {
     }
 }
 
+pub fn run(cli: Cli, matches: &clap::ArgMatches) -> Result<()> {
+    match cli.command {
+        Command::Convert(args) => {
+            let json_mode = args.format_diagnostics == OutputFormat::Json;
+            let convert_matches = matches.subcommand_matches("convert").unwrap_or(matches);
+            apply_convert_profile(*args, convert_matches)
+                .and_then(convert)
+                .inspect_err(|err| {
+                    if json_mode {
+                        let _ = print_convert_json_event(convert_json_error(err));
+                    }
+                })
+        },
+        Command::Inspect(args) => inspect_database(args),
+        Command::Export(args) => export_views(args),
+        Command::Query(args) => run_query(args),
+        Command::Diff(args) => diff_databases(args),
+        Command::Merge(args) => merge_databases(args),
+        Command::Migrate(args) => migrate_database(args),
+        Command::Completions(args) => {
+            generate_completions(args);
+            Ok(())
+        },
+    }
+}
+
+/// Process exit status for a failed command: 130 when the run was cancelled
+/// with Ctrl-C, 1 otherwise.
+pub fn exit_code(err: &color_eyre::Report) -> i32 {
+    let cancelled = err.chain().any(|err| {
+        matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Cancelled)
+        )
+    });
+    if cancelled { 130 } else { 1 }
+}
+
 #[cfg(test)]
 mod tests {
+    use clap::FromArgMatches;
+
     use super::*;
 
     #[test]
@@ -850,15 +3823,584 @@ mod tests {
     fn total_time_line_is_absent_without_start_time() {
         assert_eq!(total_time_line(None, Instant::now()), None);
     }
-}
 
-pub fn run(cli: Cli) -> Result<()> {
-    match cli.command {
-        Command::Convert(args) => convert(args),
-        Command::Inspect(args) => inspect_database(args),
-        Command::Completions(args) => {
-            generate_completions(args);
-            Ok(())
-        },
+    #[test]
+    fn converter_release_drops_git_describe_and_build_info() {
+        assert_eq!(
+            converter_release("0.1.0-beta.12-g1a2b3c4 (2025-01-01 x86_64-unknown-linux-gnu)"),
+            "0.1.0-beta.12"
+        );
+        assert_eq!(
+            converter_release("0.2.0 (2025-01-01 aarch64-apple-darwin)"),
+            "0.2.0"
+        );
+    }
+
+    /// Write `dataset` to a DuckDB file at `path`, then run `sql` on it.
+    fn write_fixture_database(dataset: &crate::SolutionDataset, path: &std::path::Path, sql: &str) {
+        let con = duckdb::Connection::open(path).unwrap();
+        let mut sink = crate::DuckdbSink::new(&con);
+        dataset.to_sink(&mut sink).run().unwrap();
+        con.execute_batch(sql).unwrap();
+    }
+
+    #[test]
+    fn merge_keeps_scenarios_apart_in_views_and_copies_data_views() {
+        let (dir, dataset) = crate::tests::file_period_fixture().unwrap();
+        let inputs = ["base", "high"].map(|name| dir.path().join(format!("{name}.duckdb")));
+        for input in &inputs {
+            write_fixture_database(
+                &dataset,
+                input,
+                "CREATE VIEW data.ST__Interval__FileFixture__MetricCopy AS
+                 SELECT * FROM data.ST__Interval__FileFixture__MetricA;",
+            );
+        }
+        let output = dir.path().join("merged.duckdb");
+        merge_databases(MergeArgs {
+            input: inputs.to_vec(),
+            scenario: Vec::new(),
+            output: output.clone(),
+            force: false,
+            allow_version_mismatch: false,
+        })
+        .unwrap();
+
+        let con = duckdb::Connection::open(&output).unwrap();
+        let count = |sql: &str| con.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        let data_rows = count("SELECT COUNT(*) FROM data.ST__Interval__FileFixture__MetricA");
+        assert_eq!(
+            count("SELECT COUNT(*) FROM report.ST__Interval__FileFixture__MetricA"),
+            data_rows
+        );
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM report.ST__Interval__FileFixture__MetricA
+                 WHERE timestamp IS NULL"
+            ),
+            0
+        );
+        assert_eq!(
+            count("SELECT COUNT(DISTINCT scenario) FROM report.ST__Interval__FileFixture__MetricA"),
+            2
+        );
+        assert_eq!(
+            count("SELECT COUNT(*) FROM data.ST__Interval__FileFixture__MetricCopy"),
+            data_rows
+        );
+    }
+
+    #[test]
+    fn diff_matches_renamed_objects_by_guid() {
+        let (dir, dataset) = crate::tests::file_period_fixture().unwrap();
+        let inputs = [("base", "Gen A"), ("other", "Gen A (new)")]
+            .map(|(name, object)| (dir.path().join(format!("{name}.duckdb")), object));
+        for (input, object) in &inputs {
+            write_fixture_database(
+                &dataset,
+                input,
+                &format!(
+                    "INSERT INTO raw.classes (class_id, name) VALUES (1, 'System'), (2, 'Generator');
+                     INSERT INTO raw.collections (collection_id, name) VALUES (1, 'Generators');
+                     INSERT INTO raw.objects (object_id, class_id, name, guid)
+                       VALUES (1, 1, 'System', 'guid-system'), (2, 2, '{object}', 'guid-gen-a');
+                     INSERT INTO raw.memberships (membership_id, collection_id, parent_id, child_id)
+                       SELECT 400 + i, 1, 1, 2 FROM range(4) t(i);"
+                ),
+            );
+        }
+
+        let diff = |match_by| {
+            let out = dir.path().join(format!("diff-{match_by:?}.duckdb"));
+            diff_databases(DiffArgs {
+                base: inputs[0].0.clone(),
+                other: inputs[1].0.clone(),
+                top: 10,
+                match_by,
+                out: Some(out.clone()),
+                force: false,
+                format_diagnostics: OutputFormat::Json,
+            })
+            .unwrap();
+            let con = duckdb::Connection::open(out).unwrap();
+            let objects = query_rows(
+                &con,
+                "SELECT status, name FROM diff.objects ORDER BY status",
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .unwrap();
+            let data = query_rows(
+                &con,
+                "SELECT SUM(matched_rows), SUM(only_base_rows), SUM(only_other_rows)
+                 FROM diff.data_summary",
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                    ))
+                },
+            )
+            .unwrap();
+            (objects, data[0])
+        };
+
+        let (objects, (matched, only_base, only_other)) = diff(None);
+        assert_eq!(
+            objects,
+            vec![("renamed".to_string(), "Gen A -> Gen A (new)".to_string())]
+        );
+        assert!(matched > 0);
+        assert_eq!((only_base, only_other), (0, 0));
+
+        let (objects, (matched, only_base, only_other)) = diff(Some(DiffMatchBy::Name));
+        assert_eq!(
+            objects
+                .iter()
+                .map(|(status, _)| status.as_str())
+                .collect::<Vec<_>>(),
+            vec!["added", "removed"]
+        );
+        assert_eq!(matched, 0);
+        assert!(only_base > 0 && only_base == only_other);
+    }
+
+    #[test]
+    fn merge_removes_the_output_when_it_fails() {
+        let (dir, dataset) = crate::tests::file_period_fixture().unwrap();
+        let inputs = ["base", "high"].map(|name| dir.path().join(format!("{name}.duckdb")));
+        for (idx, input) in inputs.iter().enumerate() {
+            write_fixture_database(
+                &dataset,
+                input,
+                &format!("INSERT INTO raw.classes (class_id, name) VALUES (1, 'Class {idx}');"),
+            );
+        }
+        let output = dir.path().join("merged.duckdb");
+        let err = merge_databases(MergeArgs {
+            input: inputs.to_vec(),
+            scenario: Vec::new(),
+            output: output.clone(),
+            force: false,
+            allow_version_mismatch: false,
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("raw.classes"), "{err}");
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn force_only_replaces_directories_written_by_a_conversion() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("Model Base Solution.zip");
+
+        let other = dir.path().join("notes");
+        std::fs::create_dir(&other).unwrap();
+        std::fs::write(other.join("todo.txt"), "keep").unwrap();
+        assert!(
+            resolve_output_path(&input, Some(other.clone()), ConvertFormat::Parquet, true).is_err()
+        );
+        assert!(other.join("todo.txt").exists());
+
+        let dataset = dir.path().join("dataset");
+        std::fs::create_dir(&dataset).unwrap();
+        std::fs::write(
+            dataset.join("manifest.json"),
+            r#"{"plexos2duckdb_version": "0.1.0"}"#,
+        )
+        .unwrap();
+        let output =
+            resolve_output_path(&input, Some(dataset.clone()), ConvertFormat::Parquet, true)
+                .unwrap();
+        assert_eq!(output, dataset);
+        assert!(!dataset.exists());
+    }
+
+    #[test]
+    fn xlsx_export_rejects_views_longer_than_a_worksheet() {
+        let con = duckdb::Connection::open_in_memory().unwrap();
+        let fits = format!("SELECT * FROM range({})", XLSX_MAX_ROWS - 1);
+        assert!(check_xlsx_row_count(&con, &fits, "report", "fits").is_ok());
+        let too_long = format!("SELECT * FROM range({})", XLSX_MAX_ROWS);
+        let err = check_xlsx_row_count(&con, &too_long, "report", "too_long").unwrap_err();
+        assert!(err.to_string().contains("report.too_long has 1048576 rows"));
+    }
+
+    #[test]
+    fn export_selects_only_views_and_writes_enum_labels_to_xlsx() -> Result<()> {
+        use std::io::Read as _;
+
+        let dir = tempfile::TempDir::new()?;
+        let con = duckdb::Connection::open_in_memory()?;
+        con.execute_batch(
+            "CREATE SCHEMA raw;
+             CREATE TYPE kind AS ENUM ('object', 'membership');
+             CREATE TABLE raw.memberships AS SELECT 'object'::kind AS kind, 1.50::DECIMAL(4, 2) AS d;
+             CREATE VIEW raw.membership_kinds AS SELECT * FROM raw.memberships;",
+        )?;
+        let err = select_export_views(&con, &["raw.memberships".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("No views match raw.memberships"));
+        assert_eq!(
+            select_export_views(&con, &["raw.*".to_string()])?,
+            vec![("raw".to_string(), "membership_kinds".to_string())]
+        );
+
+        let path = dir.path().join("kinds.xlsx");
+        export_view_xlsx(
+            &con,
+            "SELECT * FROM raw.membership_kinds",
+            "membership_kinds",
+            &path,
+        )?;
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path)?)?;
+        let mut sheet = String::new();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")?
+            .read_to_string(&mut sheet)?;
+        assert!(sheet.contains(">object<"), "{sheet}");
+        assert!(sheet.contains("<v>1.5</v>"), "{sheet}");
+        assert!(!sheet.contains("Enum("), "{sheet}");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_convert_datetime_accepts_dates_and_times() {
+        assert_eq!(
+            parse_convert_datetime("2024-01-08", "--to")
+                .unwrap()
+                .to_string(),
+            "2024-01-08 00:00:00 UTC"
+        );
+        assert_eq!(
+            parse_convert_datetime("2024-01-08T06:30", "--from")
+                .unwrap()
+                .to_string(),
+            "2024-01-08 06:30:00 UTC"
+        );
+        assert!(parse_convert_datetime("08/01/2024", "--from").is_err());
+    }
+
+    #[test]
+    fn convert_json_error_serializes_structured_library_errors() {
+        let err = color_eyre::Report::from(crate::Error::MissingKeyIndex { key_id: 12 })
+            .wrap_err("Data worker 1 failed: key index not found for 12");
+        let event = serde_json::to_value(convert_json_error(&err)).unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({
+                "event": "error",
+                "message": "Data worker 1 failed: key index not found for 12",
+                "error": { "kind": "missing_key_index", "key_id": 12 },
+            })
+        );
+
+        let event = serde_json::to_value(convert_json_error(&eyre!("plain failure"))).unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({ "event": "error", "message": "plain failure" })
+        );
+    }
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("Gen 1"), "Gen 1");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn query_limit_keeps_trailing_comments_and_multiple_statements() -> Result<()> {
+        use parquet::file::reader::FileReader as _;
+
+        let dir = tempfile::TempDir::new()?;
+        let query = |sql: &str, format: QueryFormat, out: &str| -> Result<std::path::PathBuf> {
+            let out = dir.path().join(out);
+            run_query(QueryArgs {
+                input: None,
+                sql: Some(sql.to_string()),
+                file: None,
+                format,
+                out: Some(out.clone()),
+                limit: Some(2),
+                read_write: false,
+            })?;
+            Ok(out)
+        };
+
+        let csv = query(
+            "SELECT range AS n FROM range(5)\n-- trailing comment",
+            QueryFormat::Csv,
+            "comment.csv",
+        )?;
+        assert_eq!(std::fs::read_to_string(csv)?, "n\n0\n1\n");
+
+        let jsonl = query(
+            "CREATE TEMP TABLE t AS SELECT range AS n FROM range(5); SELECT n FROM t;",
+            QueryFormat::Jsonl,
+            "statements.jsonl",
+        )?;
+        assert_eq!(std::fs::read_to_string(jsonl)?, "{\"n\":0}\n{\"n\":1}\n");
+
+        let parquet = query(
+            "SELECT range AS n FROM range(5000) -- trailing comment",
+            QueryFormat::Parquet,
+            "comment.parquet",
+        )?;
+        let reader =
+            parquet::file::reader::SerializedFileReader::new(std::fs::File::open(parquet)?)?;
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn query_output_renders_decimal_temporal_enum_and_list_values() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let sql = "SELECT 1.50::DECIMAL(4, 2) AS d, DATE '2024-01-02' AS dt, TIME '01:02:03' AS t,
+                          TIMESTAMP '2024-01-02 03:04:05' AS ts, INTERVAL 36 HOUR AS i,
+                          ['a', NULL] AS l, 'x'::ENUM('x', 'y') AS e,
+                          170141183460469231731687303715884105727::HUGEINT AS h";
+        let query = |format: QueryFormat, out: &str| -> Result<String> {
+            let out = dir.path().join(out);
+            run_query(QueryArgs {
+                input: None,
+                sql: Some(sql.to_string()),
+                file: None,
+                format,
+                out: Some(out.clone()),
+                limit: None,
+                read_write: false,
+            })?;
+            Ok(std::fs::read_to_string(out)?)
+        };
+
+        let row = "1.50,2024-01-02,01:02:03,2024-01-02 03:04:05,P0M0DT129600S,\"[a, NULL]\",x,\
+                   170141183460469231731687303715884105727";
+        assert_eq!(
+            query(QueryFormat::Csv, "values.csv")?,
+            format!("d,dt,t,ts,i,l,e,h\n{row}\n")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&query(QueryFormat::Jsonl, "values.jsonl")?)?;
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "d": 1.5,
+                "dt": "2024-01-02",
+                "t": "01:02:03",
+                "ts": "2024-01-02 03:04:05",
+                "i": "P0M0DT129600S",
+                "l": ["a", null],
+                "e": "x",
+                "h": "170141183460469231731687303715884105727",
+            })
+        );
+
+        let table = query(QueryFormat::Table, "values.txt")?;
+        for value in ["1.50", "2024-01-02", "01:02:03", "[a, NULL]", "│ x "] {
+            assert!(table.contains(value), "missing {value} in\n{table}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_parquet_argument_extracts_single_path_expression() {
+        assert_eq!(
+            read_parquet_argument(
+                "CREATE VIEW data.\"a\" AS SELECT * FROM read_parquet(['x(1).parquet', root() || '/y.parquet']);"
+            ),
+            Some("['x(1).parquet', root() || '/y.parquet']")
+        );
+        assert_eq!(
+            read_parquet_argument(
+                "SELECT * FROM read_parquet('d/*.parquet', hive_partitioning = true)"
+            ),
+            None
+        );
+        assert_eq!(read_parquet_argument("SELECT 1"), None);
+    }
+
+    #[test]
+    fn inventory_counts_rows_without_scanning_and_groups_data_tables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let parquet = dir.path().join("b.parquet");
+        let con = duckdb::Connection::open_in_memory().unwrap();
+        con.execute_batch(&format!(
+            "CREATE SCHEMA data;
+             CREATE TABLE data.ST__Interval__Generators__Generation AS SELECT range AS value FROM range(5);
+             COPY (SELECT range AS value FROM range(7)) TO {path} (FORMAT parquet);
+             CREATE VIEW data.ST__Interval__Generators__Price AS SELECT * FROM read_parquet({path});
+             CREATE VIEW data.LT__Year__Generators__Generation AS SELECT 1 AS value;",
+            path = sql_string_literal(&parquet.to_string_lossy())
+        ))
+        .unwrap();
+
+        let inventory = load_table_inventory(&con).unwrap();
+        let counts = inventory
+            .iter()
+            .map(|row| {
+                (
+                    row.table.as_str(),
+                    row.kind.as_str(),
+                    row.row_count.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![
+                ("LT__Year__Generators__Generation", "view", "-"),
+                ("ST__Interval__Generators__Generation", "table", "5"),
+                ("ST__Interval__Generators__Price", "view", "7"),
+            ]
+        );
+        assert_eq!(
+            data_table_groups(&inventory),
+            vec![
+                DataTableGroupRow {
+                    phase: "LT".to_string(),
+                    period: "Year".to_string(),
+                    tables: 1,
+                    rows: "-".to_string(),
+                },
+                DataTableGroupRow {
+                    phase: "ST".to_string(),
+                    period: "Interval".to_string(),
+                    tables: 2,
+                    rows: "12".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn inspect_rejects_sections_that_do_not_apply_to_the_input() {
+        let Command::Inspect(args) = Cli::parse_from([
+            "plexos2duckdb",
+            "inspect",
+            "--input",
+            "x.zip",
+            "--section",
+            "horizon,bin-files",
+        ])
+        .command
+        else {
+            panic!("expected inspect command");
+        };
+        assert!(args.shows(InspectSection::BinFiles));
+        assert!(!args.shows(InspectSection::Inventory));
+        assert!(
+            args.reject_sections(&[InspectSection::Inventory], "solution files")
+                .is_ok()
+        );
+        assert_eq!(
+            args.reject_sections(&[InspectSection::BinFiles], "DuckDB databases")
+                .unwrap_err()
+                .to_string(),
+            "--section bin-files is not available for DuckDB databases"
+        );
+    }
+
+    fn convert_args_from(argv: &[&str]) -> (ConvertArgs, clap::ArgMatches) {
+        let matches = Cli::command().try_get_matches_from(argv).unwrap();
+        let Command::Convert(args) = Cli::from_arg_matches(&matches).unwrap().command else {
+            panic!("expected convert command");
+        };
+        (
+            *args,
+            matches.subcommand_matches("convert").unwrap().clone(),
+        )
+    }
+
+    #[test]
+    fn convert_profile_fills_options_not_given_on_the_command_line() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("team.toml");
+        std::fs::write(
+            &config,
+            r#"
+input = "solutions/Model X Solution.zip"
+format = "parquet"
+n-threads = 4
+class = ["Generator"]
+property = ["Generation", "Price"]
+external-data-parquet-layout = "hive"
+
+[profiles.week]
+from = "2024-01-01"
+to = "2024-01-08"
+n-threads = 8
+"#,
+        )
+        .unwrap();
+        let config_arg = config.to_string_lossy().into_owned();
+
+        let (args, matches) = convert_args_from(&[
+            "plexos2duckdb",
+            "convert",
+            "--config",
+            &config_arg,
+            "--profile",
+            "week",
+            "--format",
+            "duckdb",
+            "--class",
+            "Battery",
+        ]);
+        let args = apply_convert_profile(args, &matches).unwrap();
+        assert_eq!(
+            args.input,
+            Some(dir.path().join("solutions/Model X Solution.zip"))
+        );
+        assert_eq!(args.format, ConvertFormat::Duckdb);
+        assert_eq!(args.n_threads, std::num::NonZeroUsize::new(8));
+        assert_eq!(args.classes, vec!["Battery".to_string()]);
+        assert_eq!(
+            args.properties,
+            vec!["Generation".to_string(), "Price".to_string()]
+        );
+        assert_eq!(args.from.as_deref(), Some("2024-01-01"));
+        assert_eq!(args.external_data_parquet_layout, ParquetLayout::Hive);
+
+        let recorded: serde_json::Value =
+            serde_json::from_str(&convert_config_json(&args).unwrap()).unwrap();
+        assert_eq!(recorded["format"], "duckdb");
+        assert_eq!(recorded["n-threads"], 8);
+        assert_eq!(recorded["profile"], "week");
+
+        let yaml = dir.path().join("team.yaml");
+        std::fs::write(&yaml, "input: x.zip\nunknown-flag: true\n").unwrap();
+        let yaml_arg = yaml.to_string_lossy().into_owned();
+        let (args, matches) =
+            convert_args_from(&["plexos2duckdb", "convert", "--config", &yaml_arg]);
+        assert!(apply_convert_profile(args, &matches).is_err());
+
+        let (args, matches) = convert_args_from(&[
+            "plexos2duckdb",
+            "convert",
+            "--config",
+            &config_arg,
+            "--profile",
+            "month",
+        ]);
+        assert!(
+            apply_convert_profile(args, &matches)
+                .unwrap_err()
+                .to_string()
+                .contains("available profiles: week")
+        );
+    }
+
+    #[test]
+    fn glob_to_regex_matches_whole_view_names() {
+        let regex = crate::glob_to_regex("report.ST__*__Generators__Generation").unwrap();
+        assert!(regex.is_match("report.ST__Interval__Generators__Generation"));
+        assert!(!regex.is_match("report.ST__Interval__Generators__Generation Cost"));
+        assert!(!regex.is_match("xreport.ST__Interval__Generators__Generation"));
+        assert!(
+            crate::glob_to_regex("report.LT__Interval__?atteries__Load")
+                .unwrap()
+                .is_match("report.LT__Interval__Batteries__Load")
+        );
     }
 }
@@ -0,0 +1,287 @@
+//! DuckDB table functions `plexos_tables` and `read_plexos`, which query PLEXOS
+//! solution files in place without a conversion step.
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use color_eyre::{Result, eyre::eyre};
+use duckdb::{
+    arrow::{
+        array::{ArrayRef, Int64Array, StringArray},
+        datatypes::{DataType, Field, Schema, SchemaRef},
+        error::ArrowError,
+        record_batch::{RecordBatch, RecordBatchIterator, RecordBatchReader},
+    },
+    core::{DataChunkHandle, LogicalTypeHandle, LogicalTypeId},
+    vtab::{
+        BindInfo, InitInfo, TableFunctionInfo, VTab, record_batch_to_duckdb_data_chunk,
+        to_duckdb_logical_type_for_field,
+    },
+};
+
+use crate::{DATA_DIMENSIONS_RECORD_BATCH_SCHEMA, DATA_RECORD_BATCH_SCHEMA, SolutionDataset};
+
+type VTabResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// DuckDB's STANDARD_VECTOR_SIZE: an output chunk holds at most this many rows.
+const DUCKDB_VECTOR_SIZE: usize = 2048;
+
+static PLEXOS_TABLES_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        Field::new("table_name", DataType::Utf8, false),
+        Field::new("phase", DataType::Utf8, true),
+        Field::new("period", DataType::Utf8, true),
+        Field::new("collection", DataType::Utf8, true),
+        Field::new("property", DataType::Utf8, true),
+        Field::new("unit", DataType::Utf8, true),
+        Field::new("keys", DataType::Int64, false),
+        Field::new("values", DataType::Int64, false),
+    ]))
+});
+
+/// Register `read_plexos` and `plexos_tables` on `con`.
+pub fn register_table_functions(con: &duckdb::Connection) -> Result<()> {
+    con.register_table_function::<ReadPlexosVTab>("read_plexos")?;
+    con.register_table_function::<PlexosTablesVTab>("plexos_tables")?;
+    Ok(())
+}
+
+fn load_dataset(path: &str) -> VTabResult<SolutionDataset> {
+    let path = std::path::Path::new(path);
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let dataset = match extension.as_deref() {
+        Some("zip") => SolutionDataset::default().with_zip_file(path),
+        Some("xml") => SolutionDataset::default().with_xml_file(path),
+        _ => Err(eyre!(
+            "PLEXOS solution must be a .zip or .xml file: {}",
+            path.display()
+        )),
+    };
+    dataset.map_err(|err| format!("{err:#}").into())
+}
+
+fn add_result_columns(bind: &BindInfo, schema: &Schema) -> VTabResult<()> {
+    for field in schema.fields() {
+        bind.add_result_column(field.name(), to_duckdb_logical_type_for_field(field)?);
+    }
+    Ok(())
+}
+
+/// Record batches re-sliced into DuckDB-sized output chunks.
+struct ChunkedBatches {
+    reader: Box<dyn RecordBatchReader + Send>,
+    current: Option<RecordBatch>,
+    offset: usize,
+}
+
+impl ChunkedBatches {
+    fn new(reader: Box<dyn RecordBatchReader + Send>) -> Self {
+        Self {
+            reader,
+            current: None,
+            offset: 0,
+        }
+    }
+
+    fn next_chunk(&mut self) -> std::result::Result<Option<RecordBatch>, ArrowError> {
+        loop {
+            if let Some(batch) = &self.current
+                && self.offset < batch.num_rows()
+            {
+                let len = (batch.num_rows() - self.offset).min(DUCKDB_VECTOR_SIZE);
+                let chunk = batch.slice(self.offset, len);
+                self.offset += len;
+                return Ok(Some(chunk));
+            }
+            match self.reader.next() {
+                Some(batch) => {
+                    self.current = Some(batch?);
+                    self.offset = 0;
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Scan state, opened by the first `func` call since that is where the bind
+/// data is available.
+#[derive(Default)]
+struct ScanState(Mutex<Option<ChunkedBatches>>);
+
+impl ScanState {
+    fn write_next_chunk(
+        &self,
+        output: &mut DataChunkHandle,
+        open: impl FnOnce() -> VTabResult<Box<dyn RecordBatchReader + Send>>,
+    ) -> VTabResult<()> {
+        let mut state = self.0.lock().map_err(|_| "PLEXOS scan state poisoned")?;
+        if state.is_none() {
+            *state = Some(ChunkedBatches::new(open()?));
+        }
+        let chunks = state.as_mut().expect("scan state was just opened");
+        match chunks.next_chunk()? {
+            Some(chunk) => record_batch_to_duckdb_data_chunk(&chunk, output)?,
+            None => output.set_len(0),
+        }
+        Ok(())
+    }
+}
+
+/// `read_plexos(path, table := ..., dimensions := false)`
+struct ReadPlexosVTab;
+
+struct ReadPlexosBindData {
+    dataset: Arc<SolutionDataset>,
+    table_name: String,
+    dimensions: bool,
+}
+
+impl VTab for ReadPlexosVTab {
+    type InitData = ScanState;
+    type BindData = ReadPlexosBindData;
+
+    fn bind(bind: &BindInfo) -> VTabResult<Self::BindData> {
+        let path = bind.get_parameter(0).to_string();
+        let table_name = bind
+            .get_named_parameter("table")
+            .map(|value| value.to_string())
+            .ok_or("read_plexos requires a data table, e.g. `table := 'ST__Interval__Generators__Generation'`")?;
+        let dimensions = bind
+            .get_named_parameter("dimensions")
+            .is_some_and(|value| value.to_bool());
+
+        let dataset = load_dataset(&path)?;
+        let mut values = 0u64;
+        for key in dataset
+            .data_table_keys(&table_name)
+            .map_err(|err| err.to_string())?
+        {
+            values += dataset
+                .key_index(key.key_id)
+                .map_err(|err| err.to_string())?
+                .length;
+        }
+        bind.set_cardinality(values, true);
+
+        let schema = if dimensions {
+            DATA_DIMENSIONS_RECORD_BATCH_SCHEMA.clone()
+        } else {
+            DATA_RECORD_BATCH_SCHEMA.clone()
+        };
+        add_result_columns(bind, &schema)?;
+
+        Ok(ReadPlexosBindData {
+            dataset: Arc::new(dataset),
+            table_name,
+            dimensions,
+        })
+    }
+
+    fn init(_: &InitInfo) -> VTabResult<Self::InitData> {
+        Ok(ScanState::default())
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> VTabResult<()> {
+        let bind_data = func.get_bind_data();
+        func.get_init_data().write_next_chunk(output, || {
+            let mut reader = bind_data
+                .dataset
+                .shared_data_table_reader(&bind_data.table_name)
+                .map_err(|err| format!("{err:#}"))?;
+            if bind_data.dimensions {
+                reader = reader.with_dimensions();
+            }
+            Ok(Box::new(reader))
+        })
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)])
+    }
+
+    fn named_parameters() -> Option<Vec<(String, LogicalTypeHandle)>> {
+        Some(vec![
+            (
+                "table".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Varchar),
+            ),
+            (
+                "dimensions".to_string(),
+                LogicalTypeHandle::from(LogicalTypeId::Boolean),
+            ),
+        ])
+    }
+}
+
+/// `plexos_tables(path)`: one row per data table with its name parts, unit,
+/// key count and value count.
+struct PlexosTablesVTab;
+
+impl VTab for PlexosTablesVTab {
+    type InitData = ScanState;
+    type BindData = RecordBatch;
+
+    fn bind(bind: &BindInfo) -> VTabResult<Self::BindData> {
+        let path = bind.get_parameter(0).to_string();
+        let dataset = load_dataset(&path)?;
+
+        let table_names = dataset.data_table_names();
+        let mut parts = [const { Vec::new() }; 4];
+        let mut units = Vec::with_capacity(table_names.len());
+        let mut keys = Vec::with_capacity(table_names.len());
+        let mut values = Vec::with_capacity(table_names.len());
+        for table_name in &table_names {
+            let mut split = table_name.splitn(4, "__");
+            for part in &mut parts {
+                part.push(split.next());
+            }
+            units.push(dataset.data_table_unit(table_name));
+            let table_keys = dataset
+                .data_table_keys(table_name)
+                .map_err(|err| err.to_string())?;
+            let mut table_values = 0u64;
+            for key in &table_keys {
+                table_values += dataset
+                    .key_index(key.key_id)
+                    .map_err(|err| err.to_string())?
+                    .length;
+            }
+            keys.push(i64::try_from(table_keys.len())?);
+            values.push(i64::try_from(table_values)?);
+        }
+        bind.set_cardinality(table_names.len() as u64, true);
+
+        let [phases, periods, collections, properties] = parts;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(table_names)),
+            Arc::new(StringArray::from(phases)),
+            Arc::new(StringArray::from(periods)),
+            Arc::new(StringArray::from(collections)),
+            Arc::new(StringArray::from(properties)),
+            Arc::new(StringArray::from(units)),
+            Arc::new(Int64Array::from(keys)),
+            Arc::new(Int64Array::from(values)),
+        ];
+        add_result_columns(bind, &PLEXOS_TABLES_SCHEMA)?;
+        Ok(RecordBatch::try_new(PLEXOS_TABLES_SCHEMA.clone(), columns)?)
+    }
+
+    fn init(_: &InitInfo) -> VTabResult<Self::InitData> {
+        Ok(ScanState::default())
+    }
+
+    fn func(func: &TableFunctionInfo<Self>, output: &mut DataChunkHandle) -> VTabResult<()> {
+        let batch = func.get_bind_data().clone();
+        func.get_init_data().write_next_chunk(output, || {
+            let schema = batch.schema();
+            Ok(Box::new(RecordBatchIterator::new([Ok(batch)], schema)))
+        })
+    }
+
+    fn parameters() -> Option<Vec<LogicalTypeHandle>> {
+        Some(vec![LogicalTypeHandle::from(LogicalTypeId::Varchar)])
+    }
+}
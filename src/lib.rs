@@ -6,17 +6,68 @@ use std::sync::{Arc, LazyLock};
 
 use color_eyre::{Result, eyre::eyre};
 use duckdb::arrow::{
-    array::{ArrayRef, Float64Array, Int64Array},
-    datatypes::{DataType, Field, Schema, SchemaRef},
-    record_batch::RecordBatch,
+    array::{ArrayRef, AsArray, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray},
+    compute::concat_batches,
+    datatypes::{DataType, Field, Float64Type, Int64Type, Schema, SchemaRef, TimeUnit},
+    error::ArrowError,
+    record_batch::{RecordBatch, RecordBatchReader},
+};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::Compression,
+    file::{metadata::KeyValue, properties::WriterProperties},
 };
-use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
 use roxmltree::{Document, Node};
-
+use sink::quote_ident;
+
+// Command line interface shared by the `plexos2duckdb` binary and the
+// Python console script; not part of the library API.
+#[doc(hidden)]
+pub mod cli;
+mod extension;
+mod migrate;
+mod sink;
 pub mod utils;
 
+pub use extension::register_table_functions;
+pub use migrate::{
+    MigrationSummary, SCHEMA_VERSION, check_schema_version, database_schema_version,
+    migrate_database,
+};
+pub use sink::{
+    ArrowIpcCompression, ArrowIpcSink, DuckdbSink, OutputSink, SinkColumn, SinkColumnType,
+    SinkRows, SinkTable, SqliteSink,
+};
+
+#[cfg(feature = "python")]
+mod python;
+
 const DATA_APPEND_BATCH_VALUES: u64 = 262_144;
 
+/// `main.plexos2duckdb` keys written by plexos2duckdb itself, which
+/// [`DuckdbBuilder::with_metadata`] may not override.
+const RESERVED_METADATA_KEYS: &[&str] = &[
+    "plexos2duckdb_version",
+    "schema_version",
+    "duckdb_file_created_at",
+    "plexos_file",
+    "model_name",
+    "simulation_log",
+    "run_stats",
+    "plexos_file_sha256",
+    "plexos_file_size_bytes",
+    "plexos_file_modified_at",
+    "convert_options",
+    "host",
+    "step_timings",
+    "elapsed_seconds",
+    "tables_written",
+    "rows_written",
+    "schema_migrated_from",
+    "schema_migrated_at",
+    "schema_migrated_by",
+];
+
 static DATA_RECORD_BATCH_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
     Arc::new(Schema::new(vec![
         Field::new("key_id", DataType::Int64, false),
@@ -28,59 +79,156 @@ static DATA_RECORD_BATCH_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
     ]))
 });
 
+/// `DATA_RECORD_BATCH_SCHEMA` plus the dimension columns of the report views.
+static DATA_DIMENSIONS_RECORD_BATCH_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    let mut fields = DATA_RECORD_BATCH_SCHEMA
+        .fields()
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    fields.extend([
+        Arc::new(Field::new("sample_name", DataType::Utf8, true)),
+        Arc::new(Field::new("name", DataType::Utf8, true)),
+        Arc::new(Field::new("category", DataType::Utf8, true)),
+        Arc::new(Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            true,
+        )),
+        Arc::new(Field::new("unit", DataType::Utf8, true)),
+    ]);
+    Arc::new(Schema::new(fields))
+});
+
+/// Structured library errors, found in a `color_eyre` report with `downcast_ref`.
+/// Serializes as JSON tagged with `kind`, e.g. `{"kind":"missing_key_index","key_id":12}`.
+#[derive(Debug, thiserror::Error, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{entity} not found for {id}")]
+    NotFound { entity: &'static str, id: i64 },
+    #[error("no keys found for {object} {property} in collection {collection}")]
+    MissingSeries {
+        collection: String,
+        property: String,
+        object: String,
+    },
+    #[error("key with {key_id} not found")]
+    MissingKey { key_id: i64 },
+    #[error("key index not found for {key_id}")]
+    MissingKeyIndex { key_id: i64 },
+    #[error("data table not found: {table_name}")]
+    MissingDataTable { table_name: String },
+    #[error("period type not found: {period_type_id}")]
+    MissingPeriodData { period_type_id: i64 },
+    #[error("BIN position misaligned for key_id {key_id} (pos_bytes={offset})")]
+    MisalignedPosition { key_id: i64, offset: u64 },
+    #[error(
+        "failed reading {}period data for key_id {key_id} at byte offset {offset}: {source}",
+        if *materialized { "materialized " } else { "" }
+    )]
+    PeriodDataRead {
+        key_id: i64,
+        offset: u64,
+        materialized: bool,
+        #[serde(skip)]
+        source: std::io::Error,
+    },
+    #[error(
+        "BIN file '{path}' uses unsupported ZIP compression method {method}; only stored and deflated BIN entries can be converted"
+    )]
+    UnsupportedCompression { path: String, method: String },
+    #[error("no XML file found in the zip archive {path}")]
+    MissingXmlFile { path: String },
+    #[error("missing {tag} element in {element}")]
+    MissingXmlElement { tag: String, element: String },
+    #[error("invalid value for {tag} in {element}: {value:?}")]
+    InvalidXmlValue {
+        tag: String,
+        element: String,
+        value: String,
+    },
+    #[error("{quantity} overflow for key_id {key_id}")]
+    Overflow { quantity: &'static str, key_id: i64 },
+    #[error(
+        "data table name '{table_name}' does not follow the Phase__Period__Collection__Property convention"
+    )]
+    InvalidDataTableName { table_name: String },
+    /// Conflicting or malformed conversion options, e.g. a filter pattern that
+    /// does not compile.
+    #[error("{message}")]
+    InvalidOptions { message: String },
+    #[error("conversion cancelled")]
+    Cancelled,
+}
+
+/// Unit of measure from `t_unit`, e.g. `MW` or `$000`.
 #[derive(Debug, Default, Clone)]
-struct Unit {
-    id: i64,
-    value: String,
-    lang_id: i64,
+#[non_exhaustive]
+pub struct Unit {
+    pub id: i64,
+    /// Unit name, e.g. `MW`
+    pub name: String,
+    pub lang_id: i64,
 }
 
 type Band = i64;
 
+/// Object category from `t_category`, scoped to a class.
 #[derive(Debug, Default, Clone)]
-struct Category {
-    category_id: i64,
-    name: String,
-    rank: i64,
-    class_id: i64,
+#[non_exhaustive]
+pub struct Category {
+    pub category_id: i64,
+    pub name: String,
+    pub rank: i64,
+    pub class_id: i64,
 }
 
+/// Parent/child object pair in a collection, from `t_membership`.
 #[derive(Debug, Default, Clone)]
-struct Membership {
-    membership_id: i64,
-    parent_class_id: i64,
-    child_class_id: i64,
-    collection_id: i64,
-    parent_object_id: i64,
-    child_object_id: i64,
+#[non_exhaustive]
+pub struct Membership {
+    pub membership_id: i64,
+    pub parent_class_id: i64,
+    pub child_class_id: i64,
+    pub collection_id: i64,
+    pub parent_object_id: i64,
+    pub child_object_id: i64,
     // additional metadata
     collection_idx: usize,
 }
 
+/// Model from `t_model`.
 #[derive(Debug, Default, Clone)]
-struct Model {
-    model_id: i64,
-    name: String,
+#[non_exhaustive]
+pub struct Model {
+    pub model_id: i64,
+    pub name: String,
 }
 
+/// Object from `t_object`, e.g. a single generator or region.
 #[derive(Debug, Default, Clone)]
-struct Object {
-    object_id: i64,
-    name: String,
-    index: i64,
-    show: bool,
-    class_id: i64,
-    category_id: i64,
-    guid: Option<String>,
+#[non_exhaustive]
+pub struct Object {
+    pub object_id: i64,
+    pub name: String,
+    pub index: i64,
+    pub show: bool,
+    pub class_id: i64,
+    pub category_id: i64,
+    pub guid: Option<String>,
 }
 
+/// Object class from `t_class`, e.g. `Generator`.
 #[derive(Debug, Default, Clone)]
-struct Class {
-    class_id: i64,
-    name: String,
-    state: Option<i64>,
-    lang_id: i64,
-    class_group_id: i64,
+#[non_exhaustive]
+pub struct Class {
+    pub class_id: i64,
+    pub name: String,
+    pub state: Option<i64>,
+    pub lang_id: i64,
+    pub class_group_id: i64,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -91,14 +239,16 @@ struct ClassGroup {
     state: Option<i64>,
 }
 
+/// Collection from `t_collection`, relating a parent class to a child class.
 #[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
-struct Collection {
-    collection_id: i64,
-    name: String,
-    lang_id: i64,
-    complement_name: Option<String>,
-    parent_class_id: i64,
-    child_class_id: i64,
+#[non_exhaustive]
+pub struct Collection {
+    pub collection_id: i64,
+    pub name: String,
+    pub lang_id: i64,
+    pub complement_name: Option<String>,
+    pub parent_class_id: i64,
+    pub child_class_id: i64,
     // additional metadata
     n_members: usize,
 }
@@ -203,7 +353,10 @@ type Phase2 = Phase; // PASA period
 type Phase3 = Phase; // MT period
 type Phase4 = Phase; // ST period
 
+type PeriodTypeExtractor = fn(&SolutionDataset, i64) -> Result<PeriodType>;
+
 #[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 enum PhaseType {
     LT(Phase),
     PASA(Phase),
@@ -240,10 +393,12 @@ impl PhaseType {
     }
 }
 
+/// Monte Carlo sample from `t_sample`.
 #[derive(Debug, Default, Clone)]
-struct Sample {
-    sample_id: i64,
-    name: Option<String>,
+#[non_exhaustive]
+pub struct Sample {
+    pub sample_id: i64,
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -253,37 +408,68 @@ struct SampleWeight {
     weight: f64,
 }
 
+/// Timeslice from `t_timeslice`.
 #[derive(Debug, Default, Clone)]
-struct Timeslice {
-    timeslice_id: i64,
-    name: String,
+#[non_exhaustive]
+pub struct Timeslice {
+    pub timeslice_id: i64,
+    pub name: String,
 }
 
+/// Solution key from `t_key`: one property of one membership for a given
+/// phase, band, sample and timeslice.
 #[derive(Debug, Default, Clone)]
-struct Key {
-    key_id: i64,
-    phase_id: i64,
+#[non_exhaustive]
+pub struct Key {
+    pub key_id: i64,
+    pub phase_id: i64,
     // period_type_id is 1 for summary keys and 0 for non-summary keys
     // renamed to is_summary for clarity
-    is_summary: bool,
-    band_id: i64,
-    membership_id: i64,
-    model_id: i64,
-    property_id: i64,
-    sample_id: i64,
-    timeslice_id: i64,
+    pub is_summary: bool,
+    pub band_id: i64,
+    pub membership_id: i64,
+    pub model_id: i64,
+    pub property_id: i64,
+    pub sample_id: i64,
+    pub timeslice_id: i64,
 }
 
-type PeriodTypeId = i64;
-type KeyId = i64;
+pub type PeriodTypeId = i64;
+pub type KeyId = i64;
 
+/// Location of a key's values in the `t_data_<period_type_id>.BIN` file, from
+/// `t_key_index`.
 #[derive(Debug, Default, Clone)]
-struct KeyIndex {
-    key_id: KeyId,                // key_id
-    period_type_id: PeriodTypeId, // period_type_id
-    length: u64,                  // in 8-byte (64-bit float) increments
-    position: u64,                // bytes from binary file start
-    period_offset: i64,           // temporal data offset (if any) in stored times
+#[non_exhaustive]
+pub struct KeyIndex {
+    pub key_id: KeyId,                // key_id
+    pub period_type_id: PeriodTypeId, // period_type_id
+    pub length: u64,                  // in 8-byte (64-bit float) increments
+    pub position: u64,                // bytes from binary file start
+    pub period_offset: i64,           // temporal data offset (if any) in stored times
+}
+
+/// A `t_data_<period_type_id>.BIN` file, as listed by
+/// [`SolutionDataset::period_data_files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PeriodDataFile {
+    pub period_type_id: PeriodTypeId,
+    /// `file` for BIN files read from disk, otherwise the ZIP compression
+    /// method: `stored` or `deflated`
+    pub storage: &'static str,
+    pub size_bytes: u64,
+    /// Bytes taken in the ZIP; smaller than `size_bytes` when deflated
+    pub stored_size_bytes: u64,
+}
+
+/// Keys and rows of a data table, as planned by `to_duckdb` without filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DataTableEstimate {
+    pub table_name: String,
+    pub keys: usize,
+    pub estimated_rows: u128,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -304,19 +490,21 @@ struct Attribute {
     input_mask: Option<String>,
 }
 
+/// Reported property from `t_property`, e.g. `Generation`.
 #[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
-struct Property {
-    property_id: i64,
-    name: String,
-    summary_name: String,
-    lang_id: i64,
-    enum_id: i64,
-    is_multi_band: bool,
-    is_period: bool,
-    is_summary: bool,
-    unit_id: i64,
-    summary_unit_id: i64,
-    collection_id: i64,
+#[non_exhaustive]
+pub struct Property {
+    pub property_id: i64,
+    pub name: String,
+    pub summary_name: String,
+    pub lang_id: i64,
+    pub enum_id: i64,
+    pub is_multi_band: bool,
+    pub is_period: bool,
+    pub is_summary: bool,
+    pub unit_id: i64,
+    pub summary_unit_id: i64,
+    pub collection_id: i64,
     // additional metadata
     band_id: i64,
 }
@@ -394,6 +582,7 @@ impl ZipPeriodData {
 struct CompressedZipPeriodData {
     archive_file: Arc<std::fs::File>,
     data_len: u64,
+    compressed_len: u64,
     name: String,
 }
 
@@ -435,6 +624,20 @@ struct DataRangeWriteTask {
     period_offset: i64,
 }
 
+impl DataRangeWriteTask {
+    /// Splits off the first `values` values as their own task and keeps the
+    /// rest in `self`.
+    fn split_front(&mut self, values: u64) -> Self {
+        let values = values.min(self.value_count);
+        let mut front = self.clone();
+        front.value_count = values;
+        self.position += values * 8;
+        self.start_value_index += values;
+        self.value_count -= values;
+        front
+    }
+}
+
 #[derive(Debug)]
 struct StagedDataFiles {
     table_files: std::collections::BTreeMap<String, Vec<std::path::PathBuf>>,
@@ -452,21 +655,29 @@ struct DataValueMetadata {
 
 impl DataValueMetadata {
     fn block_id(&self, chunk_index: usize) -> Result<i64> {
-        let chunk_index = u64::try_from(chunk_index).map_err(|_| {
-            eyre!(
-                "block_id chunk index exceeds u64 for key_id {}",
-                self.key_id
-            )
+        let chunk_index = u64::try_from(chunk_index).map_err(|_| Error::Overflow {
+            quantity: "block_id chunk index",
+            key_id: self.key_id,
         })?;
         let block_idx = self
             .start_value_index
             .checked_add(chunk_index)
-            .ok_or_else(|| eyre!("block_id overflow for key_id {}", self.key_id))?;
+            .ok_or(Error::Overflow {
+                quantity: "block_id",
+                key_id: self.key_id,
+            })?;
         i64::try_from(block_idx)
-            .map_err(|_| eyre!("block_id exceeds i64 for key_id {}", self.key_id))?
+            .map_err(|_| Error::Overflow {
+                quantity: "block_id",
+                key_id: self.key_id,
+            })?
             .checked_add(self.period_offset)
             .and_then(|v| v.checked_add(1))
-            .ok_or_else(|| eyre!("block_id overflow for key_id {}", self.key_id))
+            .ok_or(Error::Overflow {
+                quantity: "block_id",
+                key_id: self.key_id,
+            })
+            .map_err(Into::into)
     }
 }
 
@@ -490,18 +701,33 @@ impl StagedDataFiles {
 }
 
 impl MaterializedPeriodDataFiles {
-    fn new(dir: tempfile::TempDir, files: std::collections::BTreeMap<i64, std::fs::File>) -> Self {
-        Self { _dir: dir, files }
+    fn new(dir: tempfile::TempDir) -> Self {
+        Self {
+            dir,
+            files: Default::default(),
+        }
     }
 
     fn get(&self, period_type_id: i64) -> Option<&std::fs::File> {
         self.files.get(&period_type_id)
     }
+
+    /// Extracts a compressed BIN file into the directory, once per period type.
+    fn materialize(&mut self, period_type_id: i64, entry: &CompressedZipPeriodData) -> Result<()> {
+        if self.files.contains_key(&period_type_id) {
+            return Ok(());
+        }
+        let path = self.dir.path().join(format!("t_data_{period_type_id}.BIN"));
+        entry.materialize_to(&path)?;
+        self.files
+            .insert(period_type_id, std::fs::File::open(&path)?);
+        Ok(())
+    }
 }
 
 impl DataValueMetadata {
     fn record_batch(&self, bytes: &[u8]) -> Result<RecordBatch> {
-        if bytes.len() % 8 != 0 {
+        if !bytes.len().is_multiple_of(8) {
             return Err(eyre!(
                 "Data chunk byte length is not a multiple of f64 width for key_id {}",
                 self.key_id
@@ -587,6 +813,10 @@ pub struct SolutionDataset {
     timestamp_block: std::collections::HashMap<String, Vec<(chrono::DateTime<chrono::Utc>, i64)>>,
     table_key_index_mapping: std::collections::HashMap<String, Vec<i64>>,
     table_units_mapping: std::collections::HashMap<String, (String, i64)>,
+    cancel_token: Option<Arc<std::sync::atomic::AtomicBool>>,
+    // source provenance, recorded while loading
+    source_sha256: Option<String>,
+    load_seconds: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -621,223 +851,1117 @@ pub enum ProgressEvent {
     },
 }
 
-#[derive(Debug)]
-enum DuckdbProgress {
-    Report(String),
-    Event(ProgressEvent),
-}
-
-#[derive(Debug, Clone)]
-struct DataTableWritePlan {
-    table_name: String,
-    key_ids: Vec<i64>,
-    estimated_values: u128,
-}
-
-#[derive(Debug)]
-struct MaterializedPeriodDataFiles {
-    _dir: tempfile::TempDir,
-    files: std::collections::BTreeMap<i64, std::fs::File>,
+/// Directory layout used for external time series Parquet files
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalDataParquetLayout {
+    /// One directory per data table: `data/<table>/<table>.part-00001.parquet`
+    #[default]
+    Table,
+    /// Hive partitions `data/phase=../period=../collection=../property=../part-00001.parquet`,
+    /// with values escaped as Hive does (`/` becomes `%2F`).
+    Hive,
 }
 
-#[derive(Debug)]
-enum DataWriteWorkerEvent {
-    TableStarted {
-        worker_id: usize,
-        index: usize,
-        total: usize,
-        table_name: String,
-        keys: usize,
-    },
-    TableCompleted {
-        worker_id: usize,
-        index: usize,
-        total: usize,
-        table_name: String,
-        keys: usize,
-    },
+/// Output target written by [`DuckdbBuilder::run`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A single DuckDB database file
+    #[default]
+    DuckDb,
+    /// A folder of Parquet files with a `manifest.json` and a `views.sql` that recreates the
+    /// `processed` and `report` views on top of them
+    Parquet,
+    /// A single SQLite file with `raw_*` and `data_*` tables and `processed_*`/`report_*` views
+    Sqlite,
+    /// A folder of Arrow IPC (Feather v2) files with a `manifest.json`, see
+    /// [`DuckdbBuilder::with_arrow_ipc_compression`]
+    ArrowIpc,
 }
 
-pub struct DuckdbBuilder<'a> {
-    dataset: &'a SolutionDataset,
-    db_path: std::path::PathBuf,
-    data_write_threads: Option<usize>,
-    data_table_name_pattern: Option<regex::Regex>,
-    external_data_parquet_dir: Option<std::path::PathBuf>,
-    report: Option<&'a mut dyn FnMut(&str)>,
-    progress: Option<&'a mut dyn FnMut(ProgressEvent)>,
+/// Key-level filters applied when planning time series writes. A key is written
+/// when every non-empty list has a pattern matching its membership.
+#[derive(Debug, Default, Clone)]
+pub struct DataKeyFilter {
+    pub classes: Vec<regex::Regex>,
+    pub collections: Vec<regex::Regex>,
+    pub categories: Vec<regex::Regex>,
+    pub objects: Vec<regex::Regex>,
+    pub properties: Vec<regex::Regex>,
+    /// Sample ids to write; empty writes every sample
+    pub samples: Vec<i64>,
+    /// Band ids to write; empty writes every band
+    pub bands: Vec<i64>,
+    /// Drop keys, memberships and objects that no selected key refers to from the `raw` tables
+    pub trim_dimensions: bool,
 }
 
-impl<'a> DuckdbBuilder<'a> {
-    fn new<P: AsRef<std::path::Path>>(dataset: &'a SolutionDataset, db_path: P) -> Self {
-        Self {
-            dataset,
-            db_path: db_path.as_ref().to_path_buf(),
-            data_write_threads: None,
-            data_table_name_pattern: None,
-            external_data_parquet_dir: None,
-            report: None,
-            progress: None,
+impl DataKeyFilter {
+    /// Compile a filter pattern that must match the whole name: `re:<regex>` is
+    /// a regular expression, anything else a glob (`*`, `?`).
+    pub fn pattern(pattern: &str) -> Result<regex::Regex> {
+        if let Some(regex) = pattern.strip_prefix("re:") {
+            return regex::Regex::new(&format!("^(?:{regex})$")).map_err(|err| {
+                Error::InvalidOptions {
+                    message: format!("Invalid filter regex {regex:?}: {err}"),
+                }
+                .into()
+            });
         }
+        glob_to_regex(pattern)
     }
 
-    pub fn with_data_write_threads(mut self, threads: usize) -> Self {
-        self.data_write_threads = Some(threads.max(1));
-        self
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+            && self.collections.is_empty()
+            && self.categories.is_empty()
+            && self.objects.is_empty()
+            && self.properties.is_empty()
+            && self.samples.is_empty()
+            && self.bands.is_empty()
     }
 
-    pub fn with_data_table_name_pattern(mut self, pattern: regex::Regex) -> Self {
-        self.data_table_name_pattern = Some(pattern);
-        self
+    fn matches_any(patterns: &[regex::Regex], value: &str) -> bool {
+        patterns.is_empty() || patterns.iter().any(|pattern| pattern.is_match(value))
     }
+}
 
-    pub fn with_external_data_parquet_dir<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
-        self.external_data_parquet_dir = Some(path.as_ref().to_path_buf());
-        self
+fn invalid_options(message: impl Into<String>) -> color_eyre::Report {
+    Error::InvalidOptions {
+        message: message.into(),
     }
+    .into()
+}
 
-    pub fn with_progress(mut self, report: &'a mut dyn FnMut(&str)) -> Self {
-        self.report = Some(report);
-        self
+/// Translate a shell-style glob (`*`, `?`) into a regex that matches the whole name.
+pub(crate) fn glob_to_regex(pattern: &str) -> Result<regex::Regex> {
+    let mut regex = String::from("^");
+    for ch in pattern.chars() {
+        match ch {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&ch.to_string())),
+        }
     }
+    regex.push('$');
+    regex::Regex::new(&regex).map_err(|err| {
+        Error::InvalidOptions {
+            message: format!("Invalid glob pattern {pattern:?}: {err}"),
+        }
+        .into()
+    })
+}
 
-    pub fn with_events(mut self, progress: &'a mut dyn FnMut(ProgressEvent)) -> Self {
-        self.progress = Some(progress);
-        self
+/// Time range of values written for each key. `from` is inclusive and `to` is
+/// exclusive; values are cut through the `timestamp_block` of the key's phase and
+/// period, so keys without timestamps are written in full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DataTimeWindow {
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl DataTimeWindow {
+    pub fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
     }
 
-    pub fn run(mut self) -> Result<()> {
-        let mut report = self.report.take();
-        let mut progress = self.progress.take();
-        let has_callbacks = report.is_some() || progress.is_some();
-        let mut combined = |update: DuckdbProgress| match update {
-            DuckdbProgress::Report(msg) => {
-                if let Some(report) = report.as_mut() {
-                    report(msg.as_str());
-                }
-            },
-            DuckdbProgress::Event(event) => {
-                if let Some(progress) = progress.as_mut() {
-                    progress(event);
-                }
-            },
-        };
-        let combined_opt = if has_callbacks {
-            Some(&mut combined as &mut dyn FnMut(DuckdbProgress))
-        } else {
-            None
-        };
-        self.dataset.to_duckdb_impl(
-            &self.db_path,
-            combined_opt,
-            self.data_write_threads,
-            self.data_table_name_pattern.as_ref(),
-            self.external_data_parquet_dir.as_deref(),
-        )
+    fn contains(&self, datetime: chrono::DateTime<chrono::Utc>) -> bool {
+        self.from.is_none_or(|from| datetime >= from) && self.to.is_none_or(|to| datetime < to)
     }
 }
 
-impl SolutionDataset {
-    fn with_duckdb_step<R>(
-        progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
-        index: &mut usize,
-        total: usize,
-        label: &str,
-        f: impl FnOnce(&mut Option<&mut dyn FnMut(DuckdbProgress)>) -> Result<R>,
-    ) -> Result<R> {
-        *index += 1;
-        if let Some(report) = progress.as_mut() {
-            report(DuckdbProgress::Event(ProgressEvent::DataTableStart {
-                index: *index,
-                total,
-                table_name: label.to_string(),
-                keys: 0,
-            }));
-        }
-        let result = f(progress);
-        if let Some(report) = progress.as_mut() {
-            report(DuckdbProgress::Event(ProgressEvent::DataTableEnd));
-        }
-        result
-    }
+/// Coarser period that `Interval` data is aggregated to, see [`Downsample`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownsamplePeriod {
+    Hour,
+    Day,
+    Month,
+}
 
-    fn report_duckdb_progress(progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>, msg: &str) {
-        if let Some(report) = progress.as_mut() {
-            report(DuckdbProgress::Report(msg.to_string()));
+impl DownsamplePeriod {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Hour => "Hour",
+            Self::Day => "Day",
+            Self::Month => "Month",
         }
     }
+}
 
-    fn report_progress(progress: &mut Option<&mut dyn FnMut(&str)>, msg: &str) {
-        if let Some(report) = progress.as_mut() {
-            report(msg);
+/// How the interval values inside one downsampled period are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownsampleAggregation {
+    Mean,
+    Sum,
+    Min,
+    Max,
+}
+
+impl DownsampleAggregation {
+    /// Default aggregation for a unit: amounts such as energy (`MWh`, `GWh`) and
+    /// cost (`$`, `$000`) are summed, rates and levels such as `MW` or `$/MWh` are averaged.
+    pub fn for_unit(unit: &str) -> Self {
+        let unit = unit.trim();
+        if unit.ends_with("Wh") || (unit.starts_with('$') && !unit.contains('/')) {
+            Self::Sum
+        } else {
+            Self::Mean
         }
     }
 
-    /// Get a unit by its ID
-    fn get_unit(&self, id: i64) -> Option<&Unit> {
-        self.unit.get(&id)
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mean => "mean",
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+        }
     }
+}
 
-    /// Get a category by its ID
-    fn get_category(&self, id: i64) -> Option<&Category> {
-        self.category.get(&id)
-    }
+/// Aggregate `Interval` data tables to a coarser period while writing, e.g.
+/// `ST__Interval__Generators__Generation` to `ST__Interval_to_Day__Generators__Generation`.
+#[derive(Debug, Clone)]
+pub struct Downsample {
+    pub period: DownsamplePeriod,
+    /// Aggregation by property name as it appears in the table name; other
+    /// properties use [`DownsampleAggregation::for_unit`]
+    pub aggregations: std::collections::HashMap<String, DownsampleAggregation>,
+}
 
-    /// Get all categories of a specific class
-    fn get_categories_by_class(&self, class_id: i64) -> Vec<&Category> {
-        self.category
-            .values()
-            .filter(|c| c.class_id == class_id)
-            .collect()
+impl Downsample {
+    pub fn new(period: DownsamplePeriod) -> Self {
+        Self {
+            period,
+            aggregations: Default::default(),
+        }
     }
 
-    pub fn with_model_name(mut self, model_name: String) -> Self {
-        if model_name.is_empty() {
-            return self;
+    fn table_name(&self, table_name: &str) -> Option<String> {
+        let parts = table_name.split("__").collect::<Vec<_>>();
+        match parts.as_slice() {
+            [phase, "Interval", collection, property] => Some(format!(
+                "{phase}__{}__{collection}__{property}",
+                self.block_suffix()
+            )),
+            _ => None,
         }
-        self.model_name = model_name;
-        self
     }
 
-    pub fn with_period_data(mut self, period_data: indexmap::IndexMap<i64, std::fs::File>) -> Self {
-        self.period_data = period_data
-            .into_iter()
-            .map(|(k, v)| (k, PeriodData::File(v)))
-            .collect();
-        self
+    fn block_suffix(&self) -> String {
+        format!("Interval_to_{}", self.period.name())
     }
+}
 
-    pub fn with_file<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
-        self.file = path.as_ref().to_path_buf();
-        self
-    }
+/// Keys read by [`SolutionDataset::series`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesSelector<'a> {
+    /// Every key of a `phase__period__collection__property` data table.
+    Table(&'a str),
+    /// Every key of one object's property in a collection, across all phases
+    /// and periods. Summary keys match on the property's summary name.
+    Object {
+        collection: &'a str,
+        property: &'a str,
+        object: &'a str,
+    },
+}
 
-    pub fn with_simulation_log(mut self, log: String) -> Self {
-        if log.is_empty() {
-            return self;
-        }
-        self.simulation_log = Some(log);
-        self
+impl<'a> From<&'a str> for SeriesSelector<'a> {
+    fn from(table_name: &'a str) -> Self {
+        Self::Table(table_name)
     }
+}
 
-    pub fn with_run_stats(mut self, run_stats: String) -> Self {
-        if run_stats.is_empty() {
-            return self;
+impl<'a> From<(&'a str, &'a str, &'a str)> for SeriesSelector<'a> {
+    fn from((collection, property, object): (&'a str, &'a str, &'a str)) -> Self {
+        Self::Object {
+            collection,
+            property,
+            object,
         }
-        self.run_stats = Some(run_stats);
-        self
     }
+}
 
-    fn is_valid_bin_filename(name: &str) -> Option<i64> {
-        // Only allow specific pattern: t_data_[digits].BIN
-        // Returns the digit if valid, None otherwise
-        lazy_static::lazy_static! {
-            static ref RE: regex::Regex = regex::Regex::new(r"^t_data_(\d+)\.BIN$").unwrap();
-        }
+/// One value of a series. `datetime` is `None` when the key's phase has no
+/// timestamp for the block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct SeriesValue {
+    pub key_id: i64,
+    pub band_id: i64,
+    pub sample_id: i64,
+    pub block_id: i64,
+    pub datetime: Option<chrono::DateTime<chrono::Utc>>,
+    pub value: f64,
+}
 
-        RE.captures(name)
-            .and_then(|cap| cap.get(1))
-            .and_then(|m| m.as_str().parse::<i64>().ok())
+/// Iterator over the values of the keys picked by a [`SeriesSelector`], read
+/// straight from the period data key by key.
+#[derive(Debug)]
+pub struct Series<'a> {
+    dataset: &'a SolutionDataset,
+    key_ids: std::vec::IntoIter<i64>,
+    current: Option<SeriesCursor>,
+    materialized_period_data: Option<MaterializedPeriodDataFiles>,
+    block_datetimes: std::collections::HashMap<
+        String,
+        std::collections::HashMap<i64, chrono::DateTime<chrono::Utc>>,
+    >,
+    values: std::collections::VecDeque<SeriesValue>,
+}
+
+#[derive(Debug)]
+struct SeriesCursor {
+    // values of the key not read yet
+    task: DataRangeWriteTask,
+    block_name: String,
+}
+
+impl Series<'_> {
+    fn next_cursor(&mut self) -> Result<Option<SeriesCursor>> {
+        let Some(key_id) = self.key_ids.next() else {
+            return Ok(None);
+        };
+        let key = self.dataset.key(key_id)?;
+        let ki = self.dataset.key_index(key_id)?;
+        Ok(Some(SeriesCursor {
+            task: self
+                .dataset
+                .data_range_write_task(key_id, &(0..ki.length))?,
+            block_name: format!(
+                "{}__{}",
+                self.dataset.phase_name(key.phase_id),
+                self.dataset.period_name(ki.period_type_id)
+            ),
+        }))
+    }
+
+    /// Reads the next chunk of values into the buffer. Returns `false` once
+    /// every key is exhausted.
+    fn fill(&mut self) -> Result<bool> {
+        let mut cursor = loop {
+            match self.current.take() {
+                Some(cursor) if cursor.task.value_count > 0 => break cursor,
+                _ => match self.next_cursor()? {
+                    Some(cursor) => self.current = Some(cursor),
+                    None => return Ok(false),
+                },
+            }
+        };
+        let chunk = cursor.task.split_front(DATA_APPEND_BATCH_VALUES);
+        self.dataset.materialize_compressed_period_data(
+            chunk.period_type_id,
+            &mut self.materialized_period_data,
+        )?;
+
+        let dataset = self.dataset;
+        let datetimes = self
+            .block_datetimes
+            .entry(cursor.block_name.clone())
+            .or_insert_with(|| {
+                dataset
+                    .timestamp_block_datetimes(&cursor.block_name)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect()
+            });
+        let values = &mut self.values;
+        dataset.write_data_range_task_batches(
+            &chunk,
+            self.materialized_period_data.as_ref(),
+            |batch| {
+                let block_ids = batch.column(4).as_primitive::<Int64Type>();
+                let batch_values = batch.column(5).as_primitive::<Float64Type>();
+                for (block_id, value) in block_ids.values().iter().zip(batch_values.values()) {
+                    values.push_back(SeriesValue {
+                        key_id: chunk.key_id,
+                        band_id: chunk.band_id,
+                        sample_id: chunk.sample_id,
+                        block_id: *block_id,
+                        datetime: datetimes.get(block_id).copied(),
+                        value: *value,
+                    });
+                }
+                Ok(())
+            },
+        )?;
+
+        self.current = Some(cursor);
+        Ok(true)
+    }
+}
+
+impl Iterator for Series<'_> {
+    type Item = Result<SeriesValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(value) = self.values.pop_front() {
+                return Some(Ok(value));
+            }
+            match self.fill() {
+                Ok(true) => continue,
+                Ok(false) => return None,
+                Err(err) => {
+                    // stop after the first error instead of skipping a key
+                    self.key_ids = Vec::new().into_iter();
+                    self.current = None;
+                    return Some(Err(err));
+                },
+            }
+        }
+    }
+}
+
+/// Arrow reader over one data table, created by [`SolutionDataset::data_table_reader`].
+/// Batches have the column layout of the `data` tables.
+#[derive(Debug)]
+pub struct DataTableReader<'a> {
+    dataset: DatasetRef<'a>,
+    table_name: String,
+    tasks: std::collections::VecDeque<DataRangeWriteTask>,
+    materialized_period_data: Option<MaterializedPeriodDataFiles>,
+    // `block_id -> datetime` of the table's timestamp block, set by `with_dimensions`
+    block_datetimes: Option<std::collections::HashMap<i64, chrono::DateTime<chrono::Utc>>>,
+}
+
+impl DataTableReader<'_> {
+    /// Adds the `sample_name`, `name`, `category`, `timestamp` and `unit`
+    /// columns of the matching `report` view to every batch.
+    pub fn with_dimensions(mut self) -> Self {
+        let block_name = sink::timestamp_block_name(&self.table_name);
+        self.block_datetimes = Some(
+            self.dataset
+                .timestamp_block_datetimes(&block_name)
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        );
+        self
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut batches = Vec::new();
+        let mut rows = 0;
+        while rows < DATA_APPEND_BATCH_VALUES {
+            let Some(mut task) = self.tasks.pop_front() else {
+                break;
+            };
+            let chunk = task.split_front(DATA_APPEND_BATCH_VALUES - rows);
+            if task.value_count > 0 {
+                self.tasks.push_front(task);
+            }
+            rows += chunk.value_count;
+
+            self.dataset.materialize_compressed_period_data(
+                chunk.period_type_id,
+                &mut self.materialized_period_data,
+            )?;
+            let dataset = &self.dataset;
+            let block_datetimes = self.block_datetimes.as_ref();
+            dataset.write_data_range_task_batches(
+                &chunk,
+                self.materialized_period_data.as_ref(),
+                |batch| {
+                    batches.push(match block_datetimes {
+                        Some(block_datetimes) => {
+                            dataset.data_batch_with_dimensions(&chunk, batch, block_datetimes)?
+                        },
+                        None => batch,
+                    });
+                    Ok(())
+                },
+            )?;
+        }
+        match batches.as_slice() {
+            [] => Ok(None),
+            [batch] => Ok(Some(batch.clone())),
+            [first, ..] => Ok(Some(concat_batches(&first.schema(), &batches)?)),
+        }
+    }
+}
+
+impl Iterator for DataTableReader<'_> {
+    type Item = std::result::Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_batch() {
+            Ok(batch) => batch.map(Ok),
+            Err(err) => {
+                self.tasks.clear();
+                Some(Err(ArrowError::ExternalError(err.into())))
+            },
+        }
+    }
+}
+
+impl RecordBatchReader for DataTableReader<'_> {
+    fn schema(&self) -> SchemaRef {
+        if self.block_datetimes.is_some() {
+            DATA_DIMENSIONS_RECORD_BATCH_SCHEMA.clone()
+        } else {
+            DATA_RECORD_BATCH_SCHEMA.clone()
+        }
+    }
+}
+
+/// Dataset read by a [`DataTableReader`], either borrowed or kept alive by an `Arc`.
+#[derive(Debug, Clone)]
+enum DatasetRef<'a> {
+    Borrowed(&'a SolutionDataset),
+    Shared(Arc<SolutionDataset>),
+}
+
+impl std::ops::Deref for DatasetRef<'_> {
+    type Target = SolutionDataset;
+
+    fn deref(&self) -> &SolutionDataset {
+        match self {
+            Self::Borrowed(dataset) => dataset,
+            Self::Shared(dataset) => dataset,
+        }
+    }
+}
+
+/// Which data tables, keys and value ranges a conversion writes.
+#[derive(Debug, Clone, Copy)]
+struct DataSelection<'a> {
+    /// Data tables with their selected keys, resolved once per conversion by
+    /// [`SolutionDataset::selected_data_tables`]
+    tables: &'a [(String, Vec<SelectedKey>)],
+    table_name_pattern: Option<&'a regex::Regex>,
+    key_filter: &'a DataKeyFilter,
+    time_window: DataTimeWindow,
+    downsample: Option<&'a Downsample>,
+    cancel: Option<&'a std::sync::atomic::AtomicBool>,
+}
+
+/// How a conversion writes the selected data tables: worker threads and,
+/// when data goes outside the database, the external Parquet directory.
+#[derive(Debug, Clone, Copy)]
+struct DataWriteOptions<'a> {
+    selection: &'a DataSelection<'a>,
+    threads: Option<usize>,
+    external_parquet_dir: Option<&'a std::path::Path>,
+    external_parquet_layout: ExternalDataParquetLayout,
+}
+
+impl DataWriteOptions<'_> {
+    /// Layout of the external Parquet files, or `None` when data tables are
+    /// staged and merged into the database.
+    fn external_layout(&self) -> Option<ExternalDataParquetLayout> {
+        self.external_parquet_dir
+            .map(|_| self.external_parquet_layout)
+    }
+}
+
+/// SQL flavour of the generated `processed` and `report` views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewDialect {
+    DuckDb,
+    /// SQLite has no schemas, so `raw.objects` is the table `raw_objects`.
+    Sqlite,
+    /// DuckDB over a merged database, where the per-scenario `raw` and `data`
+    /// tables start with a `scenario` column.
+    MergedDuckDb,
+}
+
+impl ViewDialect {
+    fn table(self, schema: &str, name: &str) -> String {
+        match self {
+            Self::DuckDb | Self::MergedDuckDb => {
+                format!("{schema}.{}", quote_ident(name))
+            },
+            Self::Sqlite => quote_ident(&sink::sqlite_table_name(schema, name)),
+        }
+    }
+
+    fn create_schema(self, schema: &str) -> String {
+        match self {
+            Self::DuckDb | Self::MergedDuckDb => {
+                format!("CREATE SCHEMA IF NOT EXISTS {schema};\n")
+            },
+            Self::Sqlite => String::new(),
+        }
+    }
+
+    /// Block ids numbered in insertion order. SQLite needs an explicit order.
+    fn insertion_row_number(self) -> &'static str {
+        match self {
+            Self::DuckDb => "ROW_NUMBER() OVER ()",
+            Self::Sqlite => "ROW_NUMBER() OVER (ORDER BY rowid)",
+            Self::MergedDuckDb => "ROW_NUMBER() OVER (PARTITION BY scenario ORDER BY rowid)",
+        }
+    }
+
+    /// `column, ` selecting the scenario of a merged database.
+    fn scenario_select(self, column: &str) -> String {
+        match self {
+            Self::MergedDuckDb => format!("{column}, "),
+            Self::DuckDb | Self::Sqlite => String::new(),
+        }
+    }
+
+    /// ` AND left = right` matching rows of the same scenario.
+    fn scenario_join(self, left: &str, right: &str) -> String {
+        match self {
+            Self::MergedDuckDb => format!(" AND {left} = {right}"),
+            Self::DuckDb | Self::Sqlite => String::new(),
+        }
+    }
+}
+
+/// Value range of one key selected for writing.
+#[derive(Debug, Clone)]
+struct SelectedKey {
+    key_id: i64,
+    values: std::ops::Range<u64>,
+}
+
+#[derive(Debug)]
+enum DuckdbProgress {
+    Report(String),
+    Event(ProgressEvent),
+}
+
+type PopulateTableFn =
+    fn(&SolutionDataset, &mut dyn OutputSink, Option<&DimensionTrim>) -> Result<()>;
+
+/// Ids kept in the `raw` tables when dimensions are trimmed: the selected keys
+/// and the memberships and objects they refer to.
+struct DimensionTrim {
+    key_ids: std::collections::HashSet<i64>,
+    membership_ids: std::collections::HashSet<i64>,
+    object_ids: std::collections::HashSet<i64>,
+}
+
+impl DimensionTrim {
+    fn keeps_key(trim: Option<&Self>, key_id: i64) -> bool {
+        trim.is_none_or(|trim| trim.key_ids.contains(&key_id))
+    }
+
+    fn keeps_membership(trim: Option<&Self>, membership_id: i64) -> bool {
+        trim.is_none_or(|trim| trim.membership_ids.contains(&membership_id))
+    }
+
+    fn keeps_object(trim: Option<&Self>, object_id: i64) -> bool {
+        trim.is_none_or(|trim| trim.object_ids.contains(&object_id))
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ParquetManifest {
+    plexos2duckdb_version: String,
+    created_at: String,
+    plexos_file: String,
+    model_name: String,
+    layout: String,
+    views_sql: String,
+    tables: Vec<ParquetManifestTable>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ParquetManifestTable {
+    schema: String,
+    name: String,
+    files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ArrowIpcManifest {
+    plexos2duckdb_version: String,
+    created_at: String,
+    plexos_file: String,
+    model_name: String,
+    compression: String,
+    tables: Vec<ArrowIpcManifestTable>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct ArrowIpcManifestTable {
+    schema: String,
+    name: String,
+    file: String,
+    rows: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamps: Option<ArrowIpcManifestTimestamps>,
+}
+
+/// Where the `block_id` of a data table finds its timestamp.
+#[derive(Debug, serde::Serialize)]
+struct ArrowIpcManifestTimestamps {
+    file: String,
+    /// `interval_id` when `block_id` matches that column, `row_number` when it
+    /// is the 1-based row of the timestamp file.
+    block_id: &'static str,
+}
+
+#[derive(Debug, Clone)]
+struct DataTableWritePlan {
+    table_name: String,
+    key_ids: Vec<i64>,
+    // value range written for each entry of `key_ids`
+    value_ranges: Vec<std::ops::Range<u64>>,
+    estimated_values: u128,
+    // set when `table_name` is a downsampled table aggregated while writing
+    downsample: Option<DataTableDownsample>,
+}
+
+/// Aggregation of an `Interval` data table into its downsampled table, applied
+/// to each key's values as they are written.
+#[derive(Debug, Clone)]
+struct DataTableDownsample {
+    source_table_name: String,
+    aggregation: DownsampleAggregation,
+    // interval `block_id` -> `block_id` of the downsampled period
+    target_blocks: Arc<std::collections::HashMap<i64, i64>>,
+}
+
+/// Running aggregate of the values of one downsampled block.
+#[derive(Debug, Clone, Copy)]
+struct DownsampleAccumulator {
+    sum: f64,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl DownsampleAccumulator {
+    fn new(value: f64) -> Self {
+        Self {
+            sum: value,
+            count: 1,
+            min: value,
+            max: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn finish(self, aggregation: DownsampleAggregation) -> f64 {
+        match aggregation {
+            DownsampleAggregation::Mean => self.sum / self.count as f64,
+            DownsampleAggregation::Sum => self.sum,
+            DownsampleAggregation::Min => self.min,
+            DownsampleAggregation::Max => self.max,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MaterializedPeriodDataFiles {
+    dir: tempfile::TempDir,
+    files: std::collections::BTreeMap<i64, std::fs::File>,
+}
+
+/// Removes a directory on drop if this conversion created it, so a failed or
+/// cancelled run does not leave partial output behind. Call [`Self::disarm`]
+/// once the output is complete.
+struct CreatedDirGuard {
+    path: Option<std::path::PathBuf>,
+}
+
+impl CreatedDirGuard {
+    fn create(path: &std::path::Path) -> Result<Self> {
+        let created = !path.exists();
+        std::fs::create_dir_all(path)?;
+        Ok(Self {
+            path: created.then(|| path.to_path_buf()),
+        })
+    }
+
+    fn disarm(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for CreatedDirGuard {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            let _ = std::fs::remove_dir_all(path);
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DataWriteWorkerEvent {
+    TableStarted {
+        worker_id: usize,
+        index: usize,
+        total: usize,
+        table_name: String,
+        keys: usize,
+    },
+    TableCompleted {
+        worker_id: usize,
+        index: usize,
+        total: usize,
+        table_name: String,
+        keys: usize,
+        rows: u64,
+    },
+}
+
+pub struct DuckdbBuilder<'a> {
+    dataset: &'a SolutionDataset,
+    db_path: std::path::PathBuf,
+    data_write_threads: Option<usize>,
+    data_table_name_pattern: Option<regex::Regex>,
+    data_key_filter: DataKeyFilter,
+    data_time_window: DataTimeWindow,
+    downsample: Option<Downsample>,
+    external_data_parquet_dir: Option<std::path::PathBuf>,
+    external_data_parquet_layout: ExternalDataParquetLayout,
+    output_format: OutputFormat,
+    arrow_ipc_compression: ArrowIpcCompression,
+    metadata: Vec<(String, String)>,
+    report: Option<&'a mut dyn FnMut(&str)>,
+    progress: Option<&'a mut dyn FnMut(ProgressEvent)>,
+    cancel_token: Option<Arc<std::sync::atomic::AtomicBool>>,
+    sink: Option<&'a mut dyn OutputSink>,
+}
+
+impl<'a> DuckdbBuilder<'a> {
+    fn new<P: AsRef<std::path::Path>>(dataset: &'a SolutionDataset, db_path: P) -> Self {
+        Self {
+            dataset,
+            db_path: db_path.as_ref().to_path_buf(),
+            data_write_threads: None,
+            data_table_name_pattern: None,
+            data_key_filter: DataKeyFilter::default(),
+            data_time_window: DataTimeWindow::default(),
+            downsample: None,
+            external_data_parquet_dir: None,
+            external_data_parquet_layout: ExternalDataParquetLayout::default(),
+            output_format: OutputFormat::default(),
+            arrow_ipc_compression: ArrowIpcCompression::default(),
+            metadata: Vec::new(),
+            report: None,
+            progress: None,
+            cancel_token: dataset.cancel_token.clone(),
+            sink: None,
+        }
+    }
+
+    pub fn with_data_write_threads(mut self, threads: usize) -> Self {
+        self.data_write_threads = Some(threads.max(1));
+        self
+    }
+
+    pub fn with_data_table_name_pattern(mut self, pattern: regex::Regex) -> Self {
+        self.data_table_name_pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_data_key_filter(mut self, filter: DataKeyFilter) -> Self {
+        self.data_key_filter = filter;
+        self
+    }
+
+    pub fn with_data_time_window(mut self, window: DataTimeWindow) -> Self {
+        self.data_time_window = window;
+        self
+    }
+
+    /// Aggregate `Interval` data to a coarser period while it is written.
+    /// Supported for DuckDB output, with or without an external Parquet dir.
+    pub fn with_downsample(mut self, downsample: Downsample) -> Self {
+        self.downsample = Some(downsample);
+        self
+    }
+
+    pub fn with_external_data_parquet_dir<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.external_data_parquet_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_external_data_parquet_layout(mut self, layout: ExternalDataParquetLayout) -> Self {
+        self.external_data_parquet_layout = layout;
+        self
+    }
+
+    /// Select the output target. With [`OutputFormat::Parquet`] the builder path is
+    /// treated as the output directory and no DuckDB file is written.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Buffer compression for [`OutputFormat::ArrowIpc`]; uncompressed files can
+    /// be memory-mapped without a copy.
+    pub fn with_arrow_ipc_compression(mut self, compression: ArrowIpcCompression) -> Self {
+        self.arrow_ipc_compression = compression;
+        self
+    }
+
+    /// Extra `main.plexos2duckdb` row for DuckDB output. Keys that plexos2duckdb
+    /// writes itself, such as `schema_version`, are rejected.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let key = key.into();
+        self.metadata.retain(|(existing, _)| *existing != key);
+        self.metadata.push((key, value.into()));
+        self
+    }
+
+    pub fn with_progress(mut self, report: &'a mut dyn FnMut(&str)) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    pub fn with_events(mut self, progress: &'a mut dyn FnMut(ProgressEvent)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stop the conversion with [`Error::Cancelled`] once `token` is set.
+    /// Defaults to the dataset's token from [`SolutionDataset::with_cancel_token`].
+    /// Staging files and any output directory created by the run are removed.
+    pub fn with_cancel_token(mut self, token: Arc<std::sync::atomic::AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Fail for option combinations the selected output can't write.
+    fn check_options(&self) -> Result<()> {
+        if let Some((key, _)) = self
+            .metadata
+            .iter()
+            .find(|(key, _)| RESERVED_METADATA_KEYS.contains(&key.as_str()))
+        {
+            return Err(invalid_options(format!(
+                "Metadata key {key:?} is written by plexos2duckdb and cannot be set"
+            )));
+        }
+        let output = match (&self.sink, self.output_format) {
+            (Some(_), _) => "custom sink",
+            (None, OutputFormat::DuckDb) => return Ok(()),
+            (None, OutputFormat::Parquet) => "Parquet",
+            (None, OutputFormat::Sqlite) => "SQLite",
+            (None, OutputFormat::ArrowIpc) => "Arrow IPC",
+        };
+        if self.external_data_parquet_dir.is_some() {
+            return Err(invalid_options(format!(
+                "An external data parquet directory cannot be combined with {output} output"
+            )));
+        }
+        if self.downsample.is_some() {
+            return Err(invalid_options(format!(
+                "Downsampling cannot be combined with {output} output"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        let mut report = self.report.take();
+        let mut progress = self.progress.take();
+        let has_callbacks = report.is_some() || progress.is_some();
+        let mut combined = |update: DuckdbProgress| match update {
+            DuckdbProgress::Report(msg) => {
+                if let Some(report) = report.as_mut() {
+                    report(msg.as_str());
+                }
+            },
+            DuckdbProgress::Event(event) => {
+                if let Some(progress) = progress.as_mut() {
+                    progress(event);
+                }
+            },
+        };
+        let combined_opt = if has_callbacks {
+            Some(&mut combined as &mut dyn FnMut(DuckdbProgress))
+        } else {
+            None
+        };
+        self.check_options()?;
+        let tables = self.dataset.selected_data_tables(
+            self.data_table_name_pattern.as_ref(),
+            &self.data_key_filter,
+            &self.data_time_window,
+        )?;
+        let selection = DataSelection {
+            tables: &tables,
+            table_name_pattern: self.data_table_name_pattern.as_ref(),
+            key_filter: &self.data_key_filter,
+            time_window: self.data_time_window,
+            downsample: self.downsample.as_ref(),
+            cancel: self.cancel_token.as_deref(),
+        };
+        if let Some(sink) = self.sink.take() {
+            return self.dataset.to_sink_impl(sink, combined_opt, &selection);
+        }
+        let write_options = DataWriteOptions {
+            selection: &selection,
+            threads: self.data_write_threads,
+            external_parquet_dir: self.external_data_parquet_dir.as_deref(),
+            external_parquet_layout: self.external_data_parquet_layout,
+        };
+        match self.output_format {
+            OutputFormat::DuckDb => self.dataset.to_duckdb_impl(
+                &self.db_path,
+                combined_opt,
+                &write_options,
+                &self.metadata,
+            ),
+            OutputFormat::Parquet => {
+                self.dataset
+                    .to_parquet_impl(&self.db_path, combined_opt, &write_options)
+            },
+            OutputFormat::Sqlite => {
+                let mut views_sql = self
+                    .dataset
+                    .processed_views_sql(&selection, ViewDialect::Sqlite)?;
+                views_sql.push_str(
+                    &self
+                        .dataset
+                        .report_views_sql(&selection, ViewDialect::Sqlite)?,
+                );
+                let mut sink = SqliteSink::create(&self.db_path)?.with_views_sql(views_sql);
+                self.dataset
+                    .to_sink_impl(&mut sink, combined_opt, &selection)
+            },
+            OutputFormat::ArrowIpc => self.dataset.to_arrow_ipc_impl(
+                &self.db_path,
+                combined_opt,
+                &selection,
+                self.arrow_ipc_compression,
+            ),
+        }
+    }
+}
+
+impl SolutionDataset {
+    fn with_duckdb_step<R>(
+        progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        index: &mut usize,
+        total: usize,
+        label: &str,
+        f: impl FnOnce(&mut Option<&mut dyn FnMut(DuckdbProgress)>) -> Result<R>,
+    ) -> Result<R> {
+        *index += 1;
+        if let Some(report) = progress.as_mut() {
+            report(DuckdbProgress::Event(ProgressEvent::DataTableStart {
+                index: *index,
+                total,
+                table_name: label.to_string(),
+                keys: 0,
+            }));
+        }
+        let result = f(progress);
+        if let Some(report) = progress.as_mut() {
+            report(DuckdbProgress::Event(ProgressEvent::DataTableEnd));
+        }
+        result
+    }
+
+    fn report_duckdb_progress(progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>, msg: &str) {
+        if let Some(report) = progress.as_mut() {
+            report(DuckdbProgress::Report(msg.to_string()));
+        }
+    }
+
+    fn report_progress(progress: &mut Option<&mut dyn FnMut(&str)>, msg: &str) {
+        if let Some(report) = progress.as_mut() {
+            report(msg);
+        }
+    }
+
+    /// Get a unit by its ID
+    fn get_unit(&self, id: i64) -> Option<&Unit> {
+        self.unit.get(&id)
+    }
+
+    /// Get a category by its ID
+    fn get_category(&self, id: i64) -> Option<&Category> {
+        self.category.get(&id)
+    }
+
+    /// Get all categories of a specific class
+    fn get_categories_by_class(&self, class_id: i64) -> Vec<&Category> {
+        self.category
+            .values()
+            .filter(|c| c.class_id == class_id)
+            .collect()
+    }
+
+    pub fn with_model_name(mut self, model_name: String) -> Self {
+        if model_name.is_empty() {
+            return self;
+        }
+        self.model_name = model_name;
+        self
+    }
+
+    pub fn with_period_data(mut self, period_data: indexmap::IndexMap<i64, std::fs::File>) -> Self {
+        self.period_data = period_data
+            .into_iter()
+            .map(|(k, v)| (k, PeriodData::File(v)))
+            .collect();
+        self
+    }
+
+    pub fn with_file<P: AsRef<std::path::Path>>(mut self, path: P) -> Self {
+        self.file = path.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn with_simulation_log(mut self, log: String) -> Self {
+        if log.is_empty() {
+            return self;
+        }
+        self.simulation_log = Some(log);
+        self
+    }
+
+    pub fn with_run_stats(mut self, run_stats: String) -> Self {
+        if run_stats.is_empty() {
+            return self;
+        }
+        self.run_stats = Some(run_stats);
+        self
+    }
+
+    /// Share a cancellation flag with the loaders and with conversions started
+    /// from this dataset. Once the flag is set, work stops at the next check
+    /// and returns [`Error::Cancelled`].
+    pub fn with_cancel_token(mut self, token: Arc<std::sync::atomic::AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    fn check_cancelled(cancel: Option<&std::sync::atomic::AtomicBool>) -> Result<()> {
+        if cancel.is_some_and(|cancel| cancel.load(std::sync::atomic::Ordering::Relaxed)) {
+            return Err(Error::Cancelled.into());
+        }
+        Ok(())
+    }
+
+    fn loading_step(&self, report: &mut Option<&mut dyn FnMut(&str)>, msg: &str) -> Result<()> {
+        Self::check_cancelled(self.cancel_token.as_deref())?;
+        Self::report_progress(report, msg);
+        Ok(())
+    }
+
+    fn is_valid_bin_filename(name: &str) -> Option<i64> {
+        // Only allow specific pattern: t_data_[digits].BIN
+        // Returns the digit if valid, None otherwise
+        lazy_static::lazy_static! {
+            static ref RE: regex::Regex = regex::Regex::new(r"^t_data_(\d+)\.BIN$").unwrap();
+        }
+
+        RE.captures(name)
+            .and_then(|cap| cap.get(1))
+            .and_then(|m| m.as_str().parse::<i64>().ok())
     }
 
     pub fn with_zip_file<P: AsRef<std::path::Path>>(self, path: P) -> Result<Self> {
@@ -855,6 +1979,7 @@ impl SolutionDataset {
     fn read_zip_archive(
         path: &std::path::Path,
         model_name: &str,
+        cancel: Option<&std::sync::atomic::AtomicBool>,
         report: &mut Option<&mut dyn FnMut(&str)>,
     ) -> Result<(String, indexmap::IndexMap<i64, PeriodData>)> {
         Self::report_progress(report, "Opening ZIP archive");
@@ -904,11 +2029,10 @@ impl SolutionDataset {
                     }
                     if model_name
                         .as_ref()
-                        .map_or(false, |mn| stem.to_lowercase().contains(mn.as_str()))
+                        .is_some_and(|mn| stem.to_lowercase().contains(mn.as_str()))
+                        && model_name_xml_index.is_none()
                     {
-                        if model_name_xml_index.is_none() {
-                            model_name_xml_index = Some(i);
-                        }
+                        model_name_xml_index = Some(i);
                     }
                 }
                 if first_xml_index.is_none() {
@@ -933,9 +2057,13 @@ impl SolutionDataset {
             );
             idx
         } else {
-            return Err(eyre!("No XML file found in the zip archive"));
+            return Err(Error::MissingXmlFile {
+                path: path.display().to_string(),
+            }
+            .into());
         };
 
+        Self::check_cancelled(cancel)?;
         Self::report_progress(report, "Reading XML from ZIP archive");
         let mut file = archive.by_index(xml_index_to_use)?;
         file.read_to_string(&mut xml_content)?;
@@ -946,6 +2074,7 @@ impl SolutionDataset {
 
         let archive_len = archive.len();
         for i in 0..archive_len {
+            Self::check_cancelled(cancel)?;
             let file = archive.by_index(i)?;
             let name = file.name().to_string();
 
@@ -964,16 +2093,17 @@ impl SolutionDataset {
                 }
 
                 if file.compression() != zip::CompressionMethod::Deflated {
-                    return Err(eyre!(
-                        "BIN file '{}' uses unsupported ZIP compression method {:?}; only stored and deflated BIN entries can be converted",
-                        name,
-                        file.compression()
-                    ));
+                    return Err(Error::UnsupportedCompression {
+                        path: name,
+                        method: format!("{:?}", file.compression()),
+                    }
+                    .into());
                 }
 
                 let entry = CompressedZipPeriodData {
                     archive_file: archive_file.clone(),
                     data_len: file.size(),
+                    compressed_len: file.compressed_size(),
                     name,
                 };
                 period_data.insert(digit, PeriodData::CompressedZipEntry(entry));
@@ -988,14 +2118,20 @@ impl SolutionDataset {
         path: P,
         mut report: Option<&mut dyn FnMut(&str)>,
     ) -> Result<Self> {
+        let started = std::time::Instant::now();
         let path = path.as_ref();
-        let (xml_content, period_data) =
-            Self::read_zip_archive(path, &self.model_name, &mut report)?;
+        let (xml_content, period_data) = Self::read_zip_archive(
+            path,
+            &self.model_name,
+            self.cancel_token.as_deref(),
+            &mut report,
+        )?;
         Self::report_progress(&mut report, "Parsing XML");
         let mut ds = self
             .with_file(path)
             .with_xml_string_impl(&xml_content, report)?;
         ds.period_data = period_data;
+        ds.load_seconds = Some(started.elapsed().as_secs_f64());
         Ok(ds)
     }
 
@@ -1016,12 +2152,22 @@ impl SolutionDataset {
         path: P,
         mut report: Option<&mut dyn FnMut(&str)>,
     ) -> Result<Self> {
+        use sha2::Digest as _;
+
+        let started = std::time::Instant::now();
+        let path = path.as_ref();
         Self::report_progress(&mut report, "Reading XML file");
         let mut file = std::fs::File::open(path)?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
+        let source_sha256 = format!("{:x}", sha2::Sha256::digest(content.as_bytes()));
         Self::report_progress(&mut report, "Parsing XML");
-        self.with_xml_string_impl(&content, report)
+        let mut ds = self
+            .with_file(path)
+            .with_xml_string_impl(&content, report)?;
+        ds.source_sha256 = Some(source_sha256);
+        ds.load_seconds = Some(started.elapsed().as_secs_f64());
+        Ok(ds)
     }
 
     pub fn with_xml_string(self, xml: &str) -> Result<Self> {
@@ -1033,80 +2179,80 @@ impl SolutionDataset {
         xml: &str,
         mut report: Option<&mut dyn FnMut(&str)>,
     ) -> Result<Self> {
-        Self::report_progress(&mut report, "Parsing XML document");
+        self.loading_step(&mut report, "Parsing XML document")?;
         let doc = Document::parse(xml)?;
 
         let root = doc.root_element();
-        Self::report_progress(&mut report, "Parsing attribute data");
+        self.loading_step(&mut report, "Parsing attribute data")?;
         self.parse_attribute_data(&root)?;
-        Self::report_progress(&mut report, "Parsing attributes");
+        self.loading_step(&mut report, "Parsing attributes")?;
         self.parse_attribute(&root)?;
-        Self::report_progress(&mut report, "Parsing properties");
+        self.loading_step(&mut report, "Parsing properties")?;
         self.parse_property(&root)?;
-        Self::report_progress(&mut report, "Parsing bands");
+        self.loading_step(&mut report, "Parsing bands")?;
         self.parse_band(&root)?;
-        Self::report_progress(&mut report, "Parsing categories");
+        self.loading_step(&mut report, "Parsing categories")?;
         self.parse_category(&root)?;
-        Self::report_progress(&mut report, "Parsing class groups");
+        self.loading_step(&mut report, "Parsing class groups")?;
         self.parse_class_group(&root)?;
-        Self::report_progress(&mut report, "Parsing classes");
+        self.loading_step(&mut report, "Parsing classes")?;
         self.parse_classes(&root)?;
-        Self::report_progress(&mut report, "Parsing collections");
+        self.loading_step(&mut report, "Parsing collections")?;
         self.parse_collection(&root)?;
-        Self::report_progress(&mut report, "Parsing config");
+        self.loading_step(&mut report, "Parsing config")?;
         self.parse_config(&root)?;
-        Self::report_progress(&mut report, "Parsing key indexes");
+        self.loading_step(&mut report, "Parsing key indexes")?;
         self.parse_key_index(&root)?;
-        Self::report_progress(&mut report, "Parsing keys");
+        self.loading_step(&mut report, "Parsing keys")?;
         self.parse_key(&root)?;
-        Self::report_progress(&mut report, "Parsing memberships");
+        self.loading_step(&mut report, "Parsing memberships")?;
         self.parse_membership(&root)?;
-        Self::report_progress(&mut report, "Parsing models");
+        self.loading_step(&mut report, "Parsing models")?;
         self.parse_models(&root)?;
-        Self::report_progress(&mut report, "Parsing objects");
+        self.loading_step(&mut report, "Parsing objects")?;
         self.parse_object(&root)?;
-        Self::report_progress(&mut report, "Parsing period intervals");
+        self.loading_step(&mut report, "Parsing period intervals")?;
         self.parse_period0(&root)?;
-        Self::report_progress(&mut report, "Parsing period days");
+        self.loading_step(&mut report, "Parsing period days")?;
         self.parse_period1(&root)?;
-        Self::report_progress(&mut report, "Parsing period weeks");
+        self.loading_step(&mut report, "Parsing period weeks")?;
         self.parse_period2(&root)?;
-        Self::report_progress(&mut report, "Parsing period months");
+        self.loading_step(&mut report, "Parsing period months")?;
         self.parse_period3(&root)?;
-        Self::report_progress(&mut report, "Parsing period years");
+        self.loading_step(&mut report, "Parsing period years")?;
         self.parse_period4(&root)?;
-        Self::report_progress(&mut report, "Parsing period hours");
+        self.loading_step(&mut report, "Parsing period hours")?;
         self.parse_period6(&root)?;
-        Self::report_progress(&mut report, "Parsing period quarters");
+        self.loading_step(&mut report, "Parsing period quarters")?;
         self.parse_period7(&root)?;
-        Self::report_progress(&mut report, "Parsing phase LT");
+        self.loading_step(&mut report, "Parsing phase LT")?;
         self.parse_phase1(&root)?;
-        Self::report_progress(&mut report, "Parsing phase PASA");
+        self.loading_step(&mut report, "Parsing phase PASA")?;
         self.parse_phase2(&root)?;
-        Self::report_progress(&mut report, "Parsing phase MT");
+        self.loading_step(&mut report, "Parsing phase MT")?;
         self.parse_phase3(&root)?;
-        Self::report_progress(&mut report, "Parsing phase ST");
+        self.loading_step(&mut report, "Parsing phase ST")?;
         self.parse_phase4(&root)?;
-        Self::report_progress(&mut report, "Parsing samples");
+        self.loading_step(&mut report, "Parsing samples")?;
         self.parse_sample(&root)?;
-        Self::report_progress(&mut report, "Parsing sample weights");
+        self.loading_step(&mut report, "Parsing sample weights")?;
         self.parse_sample_weight(&root)?;
-        Self::report_progress(&mut report, "Parsing timeslices");
+        self.loading_step(&mut report, "Parsing timeslices")?;
         self.parse_timeslice(&root)?;
-        Self::report_progress(&mut report, "Parsing units");
+        self.loading_step(&mut report, "Parsing units")?;
         self.parse_unit(&root)?;
-        Self::report_progress(&mut report, "Parsing memo objects");
+        self.loading_step(&mut report, "Parsing memo objects")?;
         self.parse_memo_object(&root)?;
-        Self::report_progress(&mut report, "Parsing custom columns");
+        self.loading_step(&mut report, "Parsing custom columns")?;
         self.parse_custom_column(&root)?;
 
-        Self::report_progress(&mut report, "Updating property band ids");
+        self.loading_step(&mut report, "Updating property band ids")?;
         self.update_property_band_id()?;
-        Self::report_progress(&mut report, "Building timestamp blocks");
+        self.loading_step(&mut report, "Building timestamp blocks")?;
         self.update_timestamp_block()?;
-        Self::report_progress(&mut report, "Updating collection membership counts");
+        self.loading_step(&mut report, "Updating collection membership counts")?;
         self.update_collection_membership_count()?;
-        Self::report_progress(&mut report, "Indexing table key mappings");
+        self.loading_step(&mut report, "Indexing table key mappings")?;
         self.update_table_key_indexes_mapping()?;
 
         Ok(self)
@@ -1251,7 +2397,7 @@ impl SolutionDataset {
 
             let unit = Unit {
                 id: unit_id,
-                value,
+                name: value,
                 lang_id,
             };
 
@@ -1776,7 +2922,7 @@ impl SolutionDataset {
     }
 
     fn update_timestamp_block(&mut self) -> Result<()> {
-        let extractors: Vec<(&str, fn(&Self, i64) -> Result<PeriodType>)> = vec![
+        let extractors: Vec<(&str, PeriodTypeExtractor)> = vec![
             ("Interval", Self::interval),
             ("Day", Self::day),
             ("Week", Self::week),
@@ -1866,7 +3012,7 @@ impl SolutionDataset {
                 property.unit_id
             };
             let unit = self.unit(unit_id)?;
-            let unit_name = unit.value.clone();
+            let unit_name = unit.name.clone();
             let period_offset = ki.period_offset;
 
             let table_name =
@@ -1889,27 +3035,41 @@ impl SolutionDataset {
         DuckdbBuilder::new(self, db_path)
     }
 
+    /// Write the tables through `sink` instead of to a file. Views, downsampling
+    /// and external Parquet data are only available for DuckDB output.
+    pub fn to_sink<'a>(&'a self, sink: &'a mut dyn OutputSink) -> DuckdbBuilder<'a> {
+        let mut builder = DuckdbBuilder::new(self, std::path::PathBuf::new());
+        builder.sink = Some(sink);
+        builder
+    }
+
     fn to_duckdb_impl<P: AsRef<std::path::Path>>(
         &self,
         db_path: P,
         mut progress: Option<&mut dyn FnMut(DuckdbProgress)>,
-        data_write_threads: Option<usize>,
-        data_table_name_pattern: Option<&regex::Regex>,
-        external_data_parquet_dir: Option<&std::path::Path>,
+        write_options: &DataWriteOptions<'_>,
+        metadata: &[(String, String)],
     ) -> Result<()> {
         let db_path = db_path.as_ref();
+        let selection = write_options.selection;
         let progress_steps = Self::duckdb_progress_step_plan();
         let total_steps = progress_steps.len();
         let mut progress_steps_iter = progress_steps.iter();
-        let mut next_progress_step = || {
-            *progress_steps_iter
+        let step_starts = std::cell::RefCell::new(Vec::with_capacity(total_steps));
+        let mut next_progress_step = || -> Result<&'static str> {
+            Self::check_cancelled(selection.cancel)?;
+            let label = *progress_steps_iter
                 .next()
-                .expect("duckdb progress plan and execution are out of sync")
+                .expect("duckdb progress plan and execution are out of sync");
+            step_starts
+                .borrow_mut()
+                .push((label, std::time::Instant::now()));
+            Ok(label)
         };
         let mut step_index = 0;
         let mut direct_stage_dir = None;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         let mut con = Self::with_duckdb_step(
             &mut progress,
@@ -1923,7 +3083,7 @@ impl SolutionDataset {
             },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
@@ -1936,7 +3096,7 @@ impl SolutionDataset {
             },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
@@ -1949,274 +3109,747 @@ impl SolutionDataset {
             },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let trim = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_metadata(&mut con, progress),
+            |_progress| self.dimension_trim(selection),
         )?;
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_config(&mut con, progress),
-        )?;
+        let mut sink = DuckdbSink::new(&con);
+        for (step_label, populate) in Self::raw_table_populate_steps() {
+            let label = next_progress_step()?;
+            debug_assert_eq!(label, *step_label);
+            Self::report_duckdb_progress(&mut progress, label);
+            Self::with_duckdb_step(
+                &mut progress,
+                &mut step_index,
+                total_steps,
+                label,
+                |_progress| populate(self, &mut sink, trim.as_ref()),
+            )?;
+        }
+        let mut rows_written = sink.rows_written("raw");
+        drop(sink);
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let (external_data_guards, data_rows_written) = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_memberships(&mut con, progress),
+            |progress| {
+                let external_data_parquet_dir = write_options
+                    .external_parquet_dir
+                    .map(|path| Self::resolve_external_data_parquet_dir(db_path, path))
+                    .transpose()?;
+                let mut guards = Vec::new();
+                if let Some(dir) = external_data_parquet_dir.as_deref() {
+                    guards.push(CreatedDirGuard::create(dir)?);
+                    guards.push(CreatedDirGuard::create(&dir.join("data"))?);
+                }
+                let resolved_options = DataWriteOptions {
+                    external_parquet_dir: external_data_parquet_dir.as_deref(),
+                    ..*write_options
+                };
+                let (_, rows_written) = self.populate_table_data(
+                    &mut con,
+                    progress,
+                    &resolved_options,
+                    Self::duckdb_staging_parent(db_path).as_path(),
+                )?;
+                Ok((guards, rows_written))
+            },
         )?;
+        rows_written += data_rows_written;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_collections(&mut con, progress),
+            |_progress| {
+                self.create_processed_views(&mut con, selection)?;
+                Ok(())
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_classes(&mut con, progress),
+            |_progress| {
+                self.create_report_views(&mut con, selection)?;
+                Ok(())
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_class_groups(&mut con, progress),
+            |progress| {
+                let options = Self::convert_options_json(write_options);
+                self.record_provenance(&con, progress, options, rows_written, metadata)
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
+        let stage_dir = direct_stage_dir
+            .take()
+            .expect("staging tempdir must be initialized before persist step");
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_categories(&mut con, progress),
+            |_progress| {
+                Self::persist_duckdb_database(con, stage_dir, db_path, |con| {
+                    self.record_step_timings(con, &step_starts.borrow())
+                })
+            },
         )?;
+        external_data_guards
+            .into_iter()
+            .for_each(CreatedDirGuard::disarm);
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_bands(&mut con, progress),
-        )?;
+        Ok(())
+    }
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_models(&mut con, progress),
-        )?;
+    /// Write the dataset as a folder of Parquet files plus `views.sql` and
+    /// `manifest.json`, built in an in-memory DuckDB connection.
+    fn to_parquet_impl<P: AsRef<std::path::Path>>(
+        &self,
+        output_dir: P,
+        mut progress: Option<&mut dyn FnMut(DuckdbProgress)>,
+        write_options: &DataWriteOptions<'_>,
+    ) -> Result<()> {
+        let output_dir = output_dir.as_ref();
+        let selection = write_options.selection;
+        let layout = write_options.external_parquet_layout;
+        let progress_steps = Self::parquet_progress_step_plan();
+        let total_steps = progress_steps.len();
+        let mut progress_steps_iter = progress_steps.iter();
+        let mut next_progress_step = || -> Result<&'static str> {
+            Self::check_cancelled(selection.cancel)?;
+            Ok(*progress_steps_iter
+                .next()
+                .expect("parquet progress plan and execution are out of sync"))
+        };
+        let mut step_index = 0;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let (mut con, output_dir_guard) = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_objects(&mut con, progress),
+            |_progress| {
+                let guard = CreatedDirGuard::create(output_dir)?;
+                let con = duckdb::Connection::open_in_memory()?;
+                con.execute_batch("SET preserve_insertion_order = false;")?;
+                Ok((con, guard))
+            },
         )?;
+        let output_dir = output_dir.canonicalize()?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_keys(&mut con, progress),
+            |_progress| {
+                con.execute_batch("CREATE SCHEMA IF NOT EXISTS raw;")?;
+                Ok(())
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let trim = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_key_indexes(&mut con, progress),
+            |_progress| self.dimension_trim(selection),
         )?;
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_properties(&mut con, progress),
-        )?;
+        let mut sink = DuckdbSink::new(&con);
+        for (step_label, populate) in Self::raw_table_populate_steps() {
+            let label = next_progress_step()?;
+            debug_assert_eq!(label, *step_label);
+            Self::report_duckdb_progress(&mut progress, label);
+            Self::with_duckdb_step(
+                &mut progress,
+                &mut step_index,
+                total_steps,
+                label,
+                |_progress| populate(self, &mut sink, trim.as_ref()),
+            )?;
+        }
+        drop(sink);
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let raw_files = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_timeslices(&mut con, progress),
+            |_progress| Self::export_raw_tables_to_parquet(&con, &output_dir),
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let (data_files, _) = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_samples(&mut con, progress),
+            |progress| {
+                let data_options = DataWriteOptions {
+                    external_parquet_dir: Some(output_dir.as_path()),
+                    ..*write_options
+                };
+                self.populate_table_data(
+                    &mut con,
+                    progress,
+                    &data_options,
+                    Self::duckdb_staging_parent(&output_dir).as_path(),
+                )
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_units(&mut con, progress),
+            |_progress| {
+                let sql = self.parquet_views_sql(
+                    &output_dir,
+                    &raw_files,
+                    &data_files,
+                    layout,
+                    selection,
+                )?;
+                std::fs::write(output_dir.join(Self::PARQUET_VIEWS_SQL_FILENAME), sql)?;
+                Ok(())
+            },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_memo_objects(&mut con, progress),
+            |_progress| {
+                let manifest =
+                    self.parquet_manifest(&output_dir, &raw_files, &data_files, layout)?;
+                std::fs::write(
+                    output_dir.join(Self::PARQUET_MANIFEST_FILENAME),
+                    serde_json::to_string_pretty(&manifest)?,
+                )?;
+                Ok(())
+            },
         )?;
+        output_dir_guard.disarm();
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_custom_columns(&mut con, progress),
-        )?;
+        Ok(())
+    }
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_attribute_data(&mut con, progress),
-        )?;
+    /// Write the `raw` tables and the selected data tables through a custom
+    /// [`OutputSink`]. Data tables are written one at a time, in name order.
+    fn to_sink_impl(
+        &self,
+        sink: &mut dyn OutputSink,
+        mut progress: Option<&mut dyn FnMut(DuckdbProgress)>,
+        selection: &DataSelection<'_>,
+    ) -> Result<()> {
+        let progress_steps = Self::sink_progress_step_plan();
+        let total_steps = progress_steps.len();
+        let mut progress_steps_iter = progress_steps.iter();
+        let mut next_progress_step = || -> Result<&'static str> {
+            Self::check_cancelled(selection.cancel)?;
+            Ok(*progress_steps_iter
+                .next()
+                .expect("sink progress plan and execution are out of sync"))
+        };
+        let mut step_index = 0;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
+        let trim = Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |progress| self.populate_table_attributes(&mut con, progress),
+            |_progress| self.dimension_trim(selection),
         )?;
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |progress| self.populate_table_timestamps_block(&mut con, progress),
-        )?;
+        for (step_label, populate) in Self::raw_table_populate_steps() {
+            let label = next_progress_step()?;
+            debug_assert_eq!(label, *step_label);
+            Self::report_duckdb_progress(&mut progress, label);
+            Self::with_duckdb_step(
+                &mut progress,
+                &mut step_index,
+                total_steps,
+                label,
+                |_progress| populate(self, sink, trim.as_ref()),
+            )?;
+        }
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
+        let staging_dir = tempfile::Builder::new()
+            .prefix("plexos2duckdb-sink-")
+            .tempdir()?;
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
             |progress| {
-                self.populate_table_data(
-                    &mut con,
+                let mut plans = self.build_data_table_plans(selection)?;
+                plans.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+                let materialized_period_data = self.materialize_compressed_period_data_for_plans(
+                    &plans,
+                    staging_dir.path(),
                     progress,
-                    data_write_threads,
-                    data_table_name_pattern,
-                    db_path,
-                    external_data_parquet_dir,
-                )
+                )?;
+                let total_tables = plans.len();
+                for (table_idx, plan) in plans.iter().enumerate() {
+                    Self::check_cancelled(selection.cancel)?;
+                    if let Some(report) = progress.as_mut() {
+                        report(DuckdbProgress::Event(ProgressEvent::DataTableStart {
+                            index: table_idx + 1,
+                            total: total_tables,
+                            table_name: plan.table_name.clone(),
+                            keys: plan.key_ids.len(),
+                        }));
+                    }
+                    for (key_id, values) in plan.key_ids.iter().copied().zip(&plan.value_ranges) {
+                        let task = self.data_range_write_task(key_id, values)?;
+                        self.write_data_range_task_batches(
+                            &task,
+                            materialized_period_data.as_ref(),
+                            |batch| {
+                                Self::check_cancelled(selection.cancel)?;
+                                sink.write_data_batch(&plan.table_name, &batch)
+                            },
+                        )?;
+                    }
+                    if let Some(report) = progress.as_mut() {
+                        report(DuckdbProgress::Event(ProgressEvent::DataTableEnd));
+                    }
+                }
+                Ok(())
             },
         )?;
 
-        let label = next_progress_step();
+        let label = next_progress_step()?;
         Self::report_duckdb_progress(&mut progress, label);
         Self::with_duckdb_step(
             &mut progress,
             &mut step_index,
             total_steps,
             label,
-            |_progress| {
-                self.create_processed_views(&mut con)?;
-                Ok(())
-            },
+            |_progress| sink.finalize(),
+        )
+    }
+
+    /// Write the dataset as a folder of Arrow IPC files through
+    /// [`ArrowIpcSink`], plus a `manifest.json` with each table's file, row
+    /// count, unit and timestamp block.
+    fn to_arrow_ipc_impl(
+        &self,
+        output_dir: &std::path::Path,
+        progress: Option<&mut dyn FnMut(DuckdbProgress)>,
+        selection: &DataSelection<'_>,
+        compression: ArrowIpcCompression,
+    ) -> Result<()> {
+        let output_dir_guard = CreatedDirGuard::create(output_dir)?;
+        let output_dir = output_dir.canonicalize()?;
+        let mut sink = ArrowIpcSink::create(&output_dir, compression)?;
+        self.to_sink_impl(&mut sink, progress, selection)?;
+
+        let mut tables = Vec::with_capacity(sink.tables().len());
+        for table in sink.tables() {
+            let (unit, timestamps) = if table.schema == "data" {
+                let timestamp_block_name = sink::timestamp_block_name(&table.name);
+                let timestamps = self
+                    .timestamp_block
+                    .contains_key(timestamp_block_name.as_str())
+                    .then(|| ArrowIpcManifestTimestamps {
+                        file: format!("raw/timestamp_block_{timestamp_block_name}.arrow"),
+                        block_id: if timestamp_block_name.contains("Interval") {
+                            "interval_id"
+                        } else {
+                            "row_number"
+                        },
+                    });
+                (
+                    self.table_units_mapping
+                        .get(&table.name)
+                        .map(|(unit, _)| unit.clone()),
+                    timestamps,
+                )
+            } else {
+                (None, None)
+            };
+            tables.push(ArrowIpcManifestTable {
+                schema: table.schema.clone(),
+                name: table.name.clone(),
+                file: Self::relative_path_string(&output_dir, &table.path)?,
+                rows: table.rows,
+                unit,
+                timestamps,
+            });
+        }
+        let manifest = ArrowIpcManifest {
+            plexos2duckdb_version: utils::version().to_string(),
+            created_at: chrono::Utc::now().to_string(),
+            plexos_file: self.file.to_string_lossy().into_owned(),
+            model_name: self.model_name.clone(),
+            compression: compression.name().to_string(),
+            tables,
+        };
+        std::fs::write(
+            output_dir.join(Self::ARROW_IPC_MANIFEST_FILENAME),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        output_dir_guard.disarm();
+        Ok(())
+    }
+
+    const ARROW_IPC_MANIFEST_FILENAME: &'static str = "manifest.json";
+    const PARQUET_VIEWS_SQL_FILENAME: &'static str = "views.sql";
+    const PARQUET_MANIFEST_FILENAME: &'static str = "manifest.json";
+    const PARQUET_METADATA_FILENAME: &'static str = "plexos2duckdb.parquet";
+
+    /// Copy `main.plexos2duckdb` and every `raw` table out of the in-memory
+    /// connection. Returns `(schema, table, path)` in export order.
+    fn export_raw_tables_to_parquet(
+        con: &duckdb::Connection,
+        output_dir: &std::path::Path,
+    ) -> Result<Vec<(String, String, std::path::PathBuf)>> {
+        let raw_dir = output_dir.join("raw");
+        std::fs::create_dir_all(&raw_dir)?;
+
+        let mut tables = vec![(
+            "main".to_string(),
+            "plexos2duckdb".to_string(),
+            output_dir.join(Self::PARQUET_METADATA_FILENAME),
+        )];
+        let mut stmt = con.prepare(
+            "SELECT table_name FROM duckdb_tables() WHERE schema_name = 'raw' ORDER BY table_name;",
         )?;
+        let raw_tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for table_name in raw_tables {
+            let path = raw_dir.join(format!("{table_name}.parquet"));
+            tables.push(("raw".to_string(), table_name, path));
+        }
+
+        for (schema, table_name, path) in &tables {
+            con.execute_batch(&format!(
+                "COPY {}.{} TO '{}' (FORMAT parquet);",
+                quote_ident(schema),
+                quote_ident(table_name),
+                Self::sql_string_literal(&Self::path_string(path))
+            ))?;
+        }
+        Ok(tables)
+    }
+
+    fn relative_path_string(
+        output_dir: &std::path::Path,
+        path: &std::path::Path,
+    ) -> Result<String> {
+        let relative_path = path.strip_prefix(output_dir).map_err(|err| {
+            eyre!(
+                "Parquet file '{}' is not under output directory '{}': {}",
+                path.display(),
+                output_dir.display(),
+                err
+            )
+        })?;
+        Ok(Self::path_string(relative_path))
+    }
+
+    /// DDL that recreates the DuckDB schemas on top of a Parquet output folder.
+    /// Paths are relative, so the script must be run from the folder itself or
+    /// after `SET file_search_path` points at it.
+    fn parquet_views_sql(
+        &self,
+        output_dir: &std::path::Path,
+        raw_files: &[(String, String, std::path::PathBuf)],
+        data_files: &std::collections::BTreeMap<String, Vec<std::path::PathBuf>>,
+        layout: ExternalDataParquetLayout,
+        selection: &DataSelection<'_>,
+    ) -> Result<String> {
+        let mut sql = format!(
+            "-- Generated by plexos2duckdb {}.\n\
+             -- Parquet paths are relative: run this script from the dataset directory,\n\
+             -- or `SET file_search_path = '<dataset directory>';` first.\n\
+             CREATE SCHEMA IF NOT EXISTS raw;\n\
+             CREATE SCHEMA IF NOT EXISTS data;\n",
+            utils::version()
+        );
+
+        for (schema, table_name, path) in raw_files {
+            sql.push_str(&format!(
+                "CREATE OR REPLACE VIEW {}.{} AS SELECT * FROM read_parquet('{}');\n",
+                quote_ident(schema),
+                quote_ident(table_name),
+                Self::sql_string_literal(&Self::relative_path_string(output_dir, path)?)
+            ));
+        }
+
+        for (table_name, files) in data_files {
+            let mut paths = Vec::with_capacity(files.len());
+            for file in files {
+                paths.push(Self::relative_path_string(output_dir, file)?);
+            }
+            sql.push_str(&format!(
+                "CREATE OR REPLACE VIEW data.{} AS SELECT * FROM read_parquet({});\n",
+                quote_ident(table_name),
+                Self::sql_string_list(paths)
+            ));
+        }
+
+        if layout == ExternalDataParquetLayout::Hive {
+            sql.push_str(
+                "CREATE OR REPLACE VIEW main.plexos2duckdb_external_data AS SELECT * FROM read_parquet(\
+                 'data/*/*/*/*/*.parquet', hive_partitioning = true, hive_types_autocast = false);\n",
+            );
+        }
+
+        sql.push_str(&self.processed_views_sql(selection, ViewDialect::DuckDb)?);
+        sql.push('\n');
+        sql.push_str(&self.report_views_sql(selection, ViewDialect::DuckDb)?);
+        Ok(sql)
+    }
+
+    fn parquet_manifest(
+        &self,
+        output_dir: &std::path::Path,
+        raw_files: &[(String, String, std::path::PathBuf)],
+        data_files: &std::collections::BTreeMap<String, Vec<std::path::PathBuf>>,
+        layout: ExternalDataParquetLayout,
+    ) -> Result<ParquetManifest> {
+        let mut tables = Vec::with_capacity(raw_files.len() + data_files.len());
+        for (schema, table_name, path) in raw_files {
+            tables.push(ParquetManifestTable {
+                schema: schema.clone(),
+                name: table_name.clone(),
+                files: vec![Self::relative_path_string(output_dir, path)?],
+                unit: None,
+            });
+        }
+        for (table_name, files) in data_files {
+            let mut relative_files = Vec::with_capacity(files.len());
+            for file in files {
+                relative_files.push(Self::relative_path_string(output_dir, file)?);
+            }
+            tables.push(ParquetManifestTable {
+                schema: "data".to_string(),
+                name: table_name.clone(),
+                files: relative_files,
+                unit: self
+                    .table_units_mapping
+                    .get(table_name)
+                    .map(|(unit, _)| unit.clone()),
+            });
+        }
+
+        Ok(ParquetManifest {
+            plexos2duckdb_version: utils::version().to_string(),
+            created_at: chrono::Utc::now().to_string(),
+            plexos_file: self.file.to_string_lossy().into_owned(),
+            model_name: self.model_name.clone(),
+            layout: match layout {
+                ExternalDataParquetLayout::Table => "table",
+                ExternalDataParquetLayout::Hive => "hive",
+            }
+            .to_string(),
+            views_sql: Self::PARQUET_VIEWS_SQL_FILENAME.to_string(),
+            tables,
+        })
+    }
+
+    /// Convert options as recorded under `convert_options` in `main.plexos2duckdb`.
+    fn convert_options_json(write_options: &DataWriteOptions<'_>) -> serde_json::Value {
+        let selection = write_options.selection;
+        let patterns = |patterns: &[regex::Regex]| {
+            patterns
+                .iter()
+                .map(|pattern| pattern.as_str().to_string())
+                .collect::<Vec<_>>()
+        };
+        let filter = selection.key_filter;
+        serde_json::json!({
+            "data_write_threads": write_options.threads,
+            "table_name_pattern": selection.table_name_pattern.map(regex::Regex::as_str),
+            "classes": patterns(&filter.classes),
+            "collections": patterns(&filter.collections),
+            "categories": patterns(&filter.categories),
+            "objects": patterns(&filter.objects),
+            "properties": patterns(&filter.properties),
+            "samples": filter.samples,
+            "bands": filter.bands,
+            "trim_dimensions": filter.trim_dimensions,
+            "from": selection.time_window.from.map(|from| from.to_rfc3339()),
+            "to": selection.time_window.to.map(|to| to.to_rfc3339()),
+            "downsample": selection.downsample.map(|downsample| {
+                serde_json::json!({
+                    "period": downsample.period.name(),
+                    "aggregations": downsample
+                        .aggregations
+                        .iter()
+                        .map(|(property, aggregation)| (property.clone(), aggregation.name()))
+                        .collect::<std::collections::BTreeMap<_, _>>(),
+                })
+            }),
+            "external_data_parquet_dir": write_options.external_parquet_dir.map(Self::path_string),
+            "external_data_parquet_layout": match write_options.external_parquet_layout {
+                ExternalDataParquetLayout::Table => "table",
+                ExternalDataParquetLayout::Hive => "hive",
+            },
+        })
+    }
+
+    /// SHA-256, size and modification time of the source file; `None` for
+    /// datasets that were not loaded from a file.
+    fn source_file_provenance(
+        &self,
+        progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+    ) -> Result<Option<(String, u64, String)>> {
+        use sha2::Digest as _;
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |_progress| {
-                self.create_report_views(&mut con, data_table_name_pattern)?;
-                Ok(())
+        if self.file.as_os_str().is_empty() {
+            return Ok(None);
+        }
+        let metadata = std::fs::metadata(&self.file)
+            .map_err(|err| eyre!("Could not read source file {}: {err}", self.file.display()))?;
+        let sha256 = match &self.source_sha256 {
+            Some(sha256) => sha256.clone(),
+            None => {
+                Self::report_duckdb_progress(progress, &format!("Hashing {}", self.file.display()));
+                let mut file = std::fs::File::open(&self.file)?;
+                let mut hasher = sha2::Sha256::new();
+                std::io::copy(&mut file, &mut hasher)?;
+                format!("{:x}", hasher.finalize())
             },
-        )?;
+        };
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+            .unwrap_or_default();
+        Ok(Some((sha256, metadata.len(), modified_at)))
+    }
 
-        let label = next_progress_step();
-        Self::report_duckdb_progress(&mut progress, label);
-        let stage_dir = direct_stage_dir
-            .take()
-            .expect("staging tempdir must be initialized before persist step");
-        Self::with_duckdb_step(
-            &mut progress,
-            &mut step_index,
-            total_steps,
-            label,
-            |_progress| Self::persist_duckdb_database(con, stage_dir, db_path),
+    /// Append the source checksum, convert options, host and written table and
+    /// row counts to `main.plexos2duckdb`.
+    fn record_provenance(
+        &self,
+        con: &duckdb::Connection,
+        progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        options: serde_json::Value,
+        rows_written: u64,
+        metadata: &[(String, String)],
+    ) -> Result<()> {
+        let mut rows = Vec::new();
+        if let Some((sha256, size, modified_at)) = self.source_file_provenance(progress)? {
+            rows.push(("plexos_file_sha256", sha256));
+            rows.push(("plexos_file_size_bytes", size.to_string()));
+            rows.push(("plexos_file_modified_at", modified_at));
+        }
+        rows.push(("convert_options", options.to_string()));
+        rows.push((
+            "host",
+            gethostname::gethostname().to_string_lossy().into_owned(),
+        ));
+
+        let tables_written: i64 = con.query_row(
+            "SELECT COUNT(*) FROM information_schema.tables
+             WHERE table_schema IN ('raw', 'data');",
+            [],
+            |row| row.get(0),
         )?;
+        rows.push(("tables_written", tables_written.to_string()));
+        rows.push(("rows_written", rows_written.to_string()));
+
+        let mut appender = con.appender_to_db("plexos2duckdb", "main")?;
+        for (key, value) in rows {
+            appender.append_row([key, value.as_str()])?;
+        }
+        for (key, value) in metadata {
+            appender.append_row([key.as_str(), value.as_str()])?;
+        }
+        appender.flush()?;
+        Ok(())
+    }
 
+    /// Append `step_timings` and `elapsed_seconds` to `main.plexos2duckdb`; the
+    /// last of `step_starts` is timed up to now.
+    fn record_step_timings(
+        &self,
+        con: &duckdb::Connection,
+        step_starts: &[(&'static str, std::time::Instant)],
+    ) -> Result<()> {
+        let now = std::time::Instant::now();
+        let mut step_timings = Vec::with_capacity(step_starts.len() + 1);
+        if let Some(seconds) = self.load_seconds {
+            step_timings.push(serde_json::json!({
+                "step": "Loading solution file",
+                "seconds": seconds,
+            }));
+        }
+        for (index, (step, started)) in step_starts.iter().enumerate() {
+            let finished = step_starts
+                .get(index + 1)
+                .map_or(now, |(_, finished)| *finished);
+            step_timings.push(serde_json::json!({
+                "step": step,
+                "seconds": finished.duration_since(*started).as_secs_f64(),
+            }));
+        }
+        let elapsed = self.load_seconds.unwrap_or_default()
+            + step_starts
+                .first()
+                .map_or(0.0, |(_, first)| now.duration_since(*first).as_secs_f64());
+
+        let mut appender = con.appender_to_db("plexos2duckdb", "main")?;
+        appender.append_row([
+            "step_timings",
+            serde_json::Value::from(step_timings).to_string().as_str(),
+        ])?;
+        appender.append_row(["elapsed_seconds", format!("{elapsed:.3}").as_str()])?;
+        appender.flush()?;
         Ok(())
     }
 
@@ -2225,6 +3858,7 @@ impl SolutionDataset {
             "Initializing DuckDB",
             "Configuring DuckDB session",
             "Creating raw schema",
+            "Selecting dimension rows",
             "Writing metadata",
             "Writing config",
             "Writing memberships",
@@ -2249,53 +3883,271 @@ impl SolutionDataset {
             "Writing time series data",
             "Creating processed views",
             "Creating report views",
+            "Recording provenance",
             "Persisting DuckDB database",
         ]
     }
 
+    fn parquet_progress_step_plan() -> &'static [&'static str] {
+        &[
+            "Initializing in-memory DuckDB",
+            "Creating raw schema",
+            "Selecting dimension rows",
+            "Writing metadata",
+            "Writing config",
+            "Writing memberships",
+            "Writing collections",
+            "Writing classes",
+            "Writing class groups",
+            "Writing categories",
+            "Writing bands",
+            "Writing models",
+            "Writing objects",
+            "Writing keys",
+            "Writing key indexes",
+            "Writing properties",
+            "Writing timeslices",
+            "Writing samples",
+            "Writing units",
+            "Writing memo objects",
+            "Writing custom columns",
+            "Writing attribute data",
+            "Writing attributes",
+            "Writing timestamp blocks",
+            "Exporting raw tables to Parquet",
+            "Writing time series data",
+            "Writing view definitions",
+            "Writing manifest",
+        ]
+    }
+
+    fn sink_progress_step_plan() -> &'static [&'static str] {
+        &[
+            "Selecting dimension rows",
+            "Writing metadata",
+            "Writing config",
+            "Writing memberships",
+            "Writing collections",
+            "Writing classes",
+            "Writing class groups",
+            "Writing categories",
+            "Writing bands",
+            "Writing models",
+            "Writing objects",
+            "Writing keys",
+            "Writing key indexes",
+            "Writing properties",
+            "Writing timeslices",
+            "Writing samples",
+            "Writing units",
+            "Writing memo objects",
+            "Writing custom columns",
+            "Writing attribute data",
+            "Writing attributes",
+            "Writing timestamp blocks",
+            "Writing time series data",
+            "Finalizing output",
+        ]
+    }
+
+    /// Raw dimension tables shared by every output format, in write order.
+    /// Labels must match the corresponding entries in the progress step plans.
+    fn raw_table_populate_steps() -> &'static [(&'static str, PopulateTableFn)] {
+        &[
+            ("Writing metadata", Self::populate_table_metadata),
+            ("Writing config", Self::populate_table_config),
+            ("Writing memberships", Self::populate_table_memberships),
+            ("Writing collections", Self::populate_table_collections),
+            ("Writing classes", Self::populate_table_classes),
+            ("Writing class groups", Self::populate_table_class_groups),
+            ("Writing categories", Self::populate_table_categories),
+            ("Writing bands", Self::populate_table_bands),
+            ("Writing models", Self::populate_table_models),
+            ("Writing objects", Self::populate_table_objects),
+            ("Writing keys", Self::populate_table_keys),
+            ("Writing key indexes", Self::populate_table_key_indexes),
+            ("Writing properties", Self::populate_table_properties),
+            ("Writing timeslices", Self::populate_table_timeslices),
+            ("Writing samples", Self::populate_table_samples),
+            ("Writing units", Self::populate_table_units),
+            ("Writing memo objects", Self::populate_table_memo_objects),
+            (
+                "Writing custom columns",
+                Self::populate_table_custom_columns,
+            ),
+            (
+                "Writing attribute data",
+                Self::populate_table_attribute_data,
+            ),
+            ("Writing attributes", Self::populate_table_attributes),
+            (
+                "Writing timestamp blocks",
+                Self::populate_table_timestamps_block,
+            ),
+        ]
+    }
+
+    /// Write the selected data tables. Returns the external Parquet files of
+    /// each table, if any, and the number of rows written.
     fn populate_table_data(
         &self,
         con: &mut duckdb::Connection,
         progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
-        data_write_threads: Option<usize>,
-        data_table_name_pattern: Option<&regex::Regex>,
-        db_path: &std::path::Path,
-        external_data_parquet_dir: Option<&std::path::Path>,
-    ) -> Result<()> {
+        write_options: &DataWriteOptions<'_>,
+        staging_parent: &std::path::Path,
+    ) -> Result<(
+        std::collections::BTreeMap<String, Vec<std::path::PathBuf>>,
+        u64,
+    )> {
+        let selection = write_options.selection;
         con.execute_batch("CREATE SCHEMA IF NOT EXISTS data;")?;
 
-        let plans = self.build_data_table_plans(data_table_name_pattern)?;
+        let mut plans = self.build_data_table_plans(selection)?;
         let total_tables = plans.len();
         if total_tables == 0 {
-            return Ok(());
+            return Ok(Default::default());
+        }
+        let mut rows_written = 0;
+        if let Some(downsample) = selection.downsample {
+            Self::report_duckdb_progress(progress, "Preparing downsampled timestamp blocks");
+            rows_written += self.downsample_data_table_plans(con, &mut plans, downsample)?;
         }
 
-        if external_data_parquet_dir.is_none() {
+        if write_options.external_parquet_dir.is_none() {
             for plan in &plans {
                 self.create_data_table(con, plan.table_name.as_str())?;
             }
         }
 
-        let staging_parent = Self::duckdb_staging_parent(db_path);
-        let external_data_parquet_dir = external_data_parquet_dir
-            .map(|path| Self::resolve_external_data_parquet_dir(db_path, path))
-            .transpose()?;
-        let materialized_period_data = self.materialize_compressed_period_data_for_plans(
-            &plans,
-            staging_parent.as_path(),
-            progress,
-        )?;
+        let materialized_period_data =
+            self.materialize_compressed_period_data_for_plans(&plans, staging_parent, progress)?;
+        Self::check_cancelled(selection.cancel)?;
 
-        let worker_count = Self::resolve_data_write_threads(total_tables, data_write_threads);
-        self.populate_table_data_uncompressed_parquet(
+        let (external_files, data_rows_written) = self.populate_table_data_uncompressed_parquet(
             con,
             plans,
-            worker_count,
-            staging_parent.as_path(),
-            external_data_parquet_dir.as_deref(),
+            write_options,
+            staging_parent,
             materialized_period_data.as_ref(),
             progress,
-        )
+        )?;
+        Ok((external_files, rows_written + data_rows_written))
+    }
+
+    /// Phases that have at least one selected `Interval` data table, which are
+    /// the phases that get a downsampled timestamp block.
+    fn downsampled_phases(&self, table_names: &[String], downsample: &Downsample) -> Vec<String> {
+        let mut phases = table_names
+            .iter()
+            .filter(|table_name| downsample.table_name(table_name).is_some())
+            .filter_map(|table_name| table_name.split("__").next())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        phases.sort();
+        phases.dedup();
+        phases
+    }
+
+    /// Point each `Interval` data table plan at its downsampled table and write
+    /// the matching timestamp blocks. Returns the number of block rows written.
+    fn downsample_data_table_plans(
+        &self,
+        con: &mut duckdb::Connection,
+        plans: &mut [DataTableWritePlan],
+        downsample: &Downsample,
+    ) -> Result<u64> {
+        con.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS main.plexos2duckdb_downsampled_tables (
+              table_name VARCHAR,
+              source_table VARCHAR,
+              period VARCHAR,
+              aggregation VARCHAR,
+              unit VARCHAR,
+            );
+            ",
+        )?;
+
+        let table_names = plans
+            .iter()
+            .map(|plan| plan.table_name.clone())
+            .collect::<Vec<_>>();
+        let mut phase_target_blocks = std::collections::HashMap::new();
+        let mut timestamp_rows = 0;
+        for phase_name in self.downsampled_phases(&table_names, downsample) {
+            let block_name = format!("{phase_name}__{}", downsample.block_suffix());
+            let mut target_blocks = std::collections::HashMap::new();
+            con.execute_batch(&format!(
+                "
+                CREATE TABLE raw.timestamp_block_{block_name} (
+                  interval_id BIGINT,
+                  datetime TIMESTAMP,
+                );
+                "
+            ))?;
+
+            let mut appender =
+                con.appender_to_db(&format!("timestamp_block_{block_name}"), "raw")?;
+            for phase in self
+                .phase
+                .get(&phase_name)
+                .into_iter()
+                .flat_map(|p| p.values())
+            {
+                let Ok(PeriodType::Interval(interval)) = self.interval(phase.interval_id()) else {
+                    continue;
+                };
+                let target_block_id = match downsample.period {
+                    DownsamplePeriod::Hour => interval.hour_id,
+                    DownsamplePeriod::Day => interval.day_id,
+                    DownsamplePeriod::Month => interval.month_id,
+                };
+                appender.append_row(duckdb::params![target_block_id, interval.datetime])?;
+                timestamp_rows += 1;
+                target_blocks
+                    .entry(phase.period_id())
+                    .or_insert(target_block_id);
+            }
+            appender.flush()?;
+            phase_target_blocks.insert(phase_name, Arc::new(target_blocks));
+        }
+
+        let mut appender = con.appender("plexos2duckdb_downsampled_tables")?;
+        for plan in plans.iter_mut() {
+            let Some(target_name) = downsample.table_name(&plan.table_name) else {
+                continue;
+            };
+            let phase_name = plan.table_name.split("__").next().unwrap_or_default();
+            let property_name = plan.table_name.rsplit("__").next().unwrap_or_default();
+            let unit = self
+                .table_units_mapping
+                .get(&plan.table_name)
+                .map(|(unit, _)| unit.clone())
+                .unwrap_or_default();
+            let aggregation = downsample
+                .aggregations
+                .get(property_name)
+                .copied()
+                .unwrap_or_else(|| DownsampleAggregation::for_unit(&unit));
+            let target_blocks = phase_target_blocks
+                .get(phase_name)
+                .cloned()
+                .unwrap_or_default();
+            appender.append_row(duckdb::params![
+                target_name,
+                plan.table_name,
+                downsample.period.name(),
+                aggregation.name(),
+                unit
+            ])?;
+            plan.downsample = Some(DataTableDownsample {
+                source_table_name: std::mem::replace(&mut plan.table_name, target_name),
+                aggregation,
+                target_blocks,
+            });
+        }
+        appender.flush()?;
+        Ok(timestamp_rows)
     }
 
     fn data_table_name_matches(
@@ -2308,21 +4160,176 @@ impl SolutionDataset {
         }
     }
 
-    fn build_data_table_plans(
+    /// Data tables that have at least one selected key with values to write,
+    /// sorted by name.
+    fn selected_data_tables(
         &self,
-        data_table_name_pattern: Option<&regex::Regex>,
-    ) -> Result<Vec<DataTableWritePlan>> {
-        let mut plans = Vec::with_capacity(self.table_key_index_mapping.len());
+        table_name_pattern: Option<&regex::Regex>,
+        key_filter: &DataKeyFilter,
+        time_window: &DataTimeWindow,
+    ) -> Result<Vec<(String, Vec<SelectedKey>)>> {
+        let mut tables = Vec::with_capacity(self.table_key_index_mapping.len());
         for (table_name, key_ids) in self.table_key_index_mapping.iter() {
-            if !Self::data_table_name_matches(table_name, data_table_name_pattern) {
+            if !Self::data_table_name_matches(table_name, table_name_pattern) {
                 continue;
             }
 
-            let mut estimated_values = 0u128;
+            let mut selected = Vec::with_capacity(key_ids.len());
             for key_id in key_ids.iter().copied() {
-                let length = self.key_index(key_id)?.length;
+                if !key_filter.is_empty() && !self.key_matches_filter(key_id, key_filter)? {
+                    continue;
+                }
+                let values = self.key_value_range(key_id, time_window)?;
+                if !values.is_empty() {
+                    selected.push(SelectedKey { key_id, values });
+                }
+            }
+            if !selected.is_empty() {
+                tables.push((table_name.clone(), selected));
+            }
+        }
+        tables.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(tables)
+    }
+
+    /// `(block_id, datetime)` pairs for a `phase__period` timestamp block, using
+    /// the same block numbering as the `processed.timestamp_block_*` views.
+    pub fn timestamp_block_datetimes(
+        &self,
+        block_name: &str,
+    ) -> Option<Vec<(i64, chrono::DateTime<chrono::Utc>)>> {
+        let values = self.timestamp_block.get(block_name)?;
+        if block_name.contains("Interval") {
+            let mut blocks = std::collections::BTreeMap::new();
+            for (datetime, block_id) in values {
+                blocks
+                    .entry(*block_id)
+                    .and_modify(|first: &mut chrono::DateTime<chrono::Utc>| {
+                        *first = (*first).min(*datetime)
+                    })
+                    .or_insert(*datetime);
+            }
+            Some(blocks.into_iter().collect())
+        } else {
+            Some(
+                values
+                    .iter()
+                    .zip(1_i64..)
+                    .map(|((datetime, _), block_id)| (block_id, *datetime))
+                    .collect(),
+            )
+        }
+    }
+
+    /// Values of a key that fall inside the time window. A value at index `i`
+    /// is stored with `block_id = i + period_offset + 1`.
+    fn key_value_range(
+        &self,
+        key_id: i64,
+        window: &DataTimeWindow,
+    ) -> Result<std::ops::Range<u64>> {
+        let ki = self.key_index(key_id)?;
+        if window.is_unbounded() {
+            return Ok(0..ki.length);
+        }
+
+        let key = self.key(key_id)?;
+        let block_name = format!(
+            "{}__{}",
+            self.phase_name(key.phase_id),
+            self.period_name(ki.period_type_id)
+        );
+        let Some(blocks) = self.timestamp_block_datetimes(&block_name) else {
+            return Ok(0..ki.length);
+        };
+        let mut selected_blocks = blocks
+            .into_iter()
+            .filter(|(_, datetime)| window.contains(*datetime))
+            .map(|(block_id, _)| block_id);
+        let Some(first_block) = selected_blocks.next() else {
+            return Ok(0..0);
+        };
+        let last_block = selected_blocks.next_back().unwrap_or(first_block);
+
+        let to_value_index =
+            |block_id: i64| -> i128 { i128::from(block_id) - i128::from(ki.period_offset) - 1 };
+        let length = i128::from(ki.length);
+        let start = to_value_index(first_block).clamp(0, length);
+        let end = (to_value_index(last_block) + 1).clamp(start, length);
+        Ok(u64::try_from(start)?..u64::try_from(end)?)
+    }
+
+    fn key_matches_filter(&self, key_id: i64, filter: &DataKeyFilter) -> Result<bool> {
+        let key = self.key(key_id)?;
+        let membership = self.membership(key.membership_id)?;
+        let object = self.object(membership.child_object_id)?;
+        let property = self.property(key.property_id)?;
+        let property_name = if key.is_summary {
+            property.summary_name()
+        } else {
+            property.property_name()
+        };
+        let category_name = self
+            .category
+            .get(&object.category_id)
+            .map(|category| category.name.as_str())
+            .unwrap_or_default();
+
+        if (!filter.samples.is_empty() && !filter.samples.contains(&key.sample_id))
+            || (!filter.bands.is_empty() && !filter.bands.contains(&key.band_id))
+        {
+            return Ok(false);
+        }
+        Ok(DataKeyFilter::matches_any(
+            &filter.classes,
+            &self.class(membership.child_class_id)?.name,
+        ) && DataKeyFilter::matches_any(
+            &filter.collections,
+            &self.collection(membership.collection_id)?.name,
+        ) && DataKeyFilter::matches_any(&filter.categories, category_name)
+            && DataKeyFilter::matches_any(&filter.objects, &object.name)
+            && DataKeyFilter::matches_any(&filter.properties, &property_name))
+    }
+
+    /// Keys, memberships and objects that selected data keys refer to, so the
+    /// `raw` tables only describe the filtered time series. `None` keeps every row.
+    fn dimension_trim(&self, selection: &DataSelection<'_>) -> Result<Option<DimensionTrim>> {
+        if !selection.key_filter.trim_dimensions {
+            return Ok(None);
+        }
+
+        let mut key_ids = std::collections::HashSet::new();
+        for (_, keys) in selection.tables {
+            key_ids.extend(keys.iter().map(|key| key.key_id));
+        }
+        let mut membership_ids = std::collections::HashSet::new();
+        for key_id in &key_ids {
+            membership_ids.insert(self.key(*key_id)?.membership_id);
+        }
+        let mut object_ids = std::collections::HashSet::new();
+        for membership_id in &membership_ids {
+            if let Some(membership) = self.membership.get(membership_id) {
+                object_ids.insert(membership.parent_object_id);
+                object_ids.insert(membership.child_object_id);
+            }
+        }
+        Ok(Some(DimensionTrim {
+            key_ids,
+            membership_ids,
+            object_ids,
+        }))
+    }
+
+    fn build_data_table_plans(
+        &self,
+        selection: &DataSelection<'_>,
+    ) -> Result<Vec<DataTableWritePlan>> {
+        let mut plans = Vec::with_capacity(selection.tables.len());
+        for (table_name, keys) in selection.tables {
+            let mut estimated_values = 0u128;
+            for key in keys {
                 estimated_values = estimated_values
-                    .checked_add(u128::from(length))
+                    .checked_add(u128::from(key.values.end - key.values.start))
                     .ok_or_else(|| {
                         eyre!(
                             "Estimated workload overflow for table '{}' while planning data writes",
@@ -2331,10 +4338,16 @@ impl SolutionDataset {
                     })?;
             }
 
+            let (key_ids, value_ranges) = keys
+                .iter()
+                .map(|key| (key.key_id, key.values.clone()))
+                .unzip();
             plans.push(DataTableWritePlan {
                 table_name: table_name.clone(),
-                key_ids: key_ids.clone(),
+                key_ids,
+                value_ranges,
                 estimated_values,
+                downsample: None,
             });
         }
 
@@ -2369,10 +4382,12 @@ impl SolutionDataset {
         for plan in plans {
             for key_id in plan.key_ids.iter().copied() {
                 let ki = self.key_index(key_id)?;
-                let period_data = self
-                    .period_data
-                    .get(&ki.period_type_id)
-                    .ok_or_else(|| eyre!("period type not found: {}", ki.period_type_id))?;
+                let period_data =
+                    self.period_data
+                        .get(&ki.period_type_id)
+                        .ok_or(Error::MissingPeriodData {
+                            period_type_id: ki.period_type_id,
+                        })?;
                 if period_data.is_compressed_zip_entry() {
                     period_type_ids.insert(ki.period_type_id);
                 }
@@ -2393,17 +4408,18 @@ impl SolutionDataset {
             return Ok(None);
         }
 
-        let materialized_dir = tempfile::Builder::new()
-            .prefix("plexos2duckdb-bin-")
-            .tempdir_in(staging_parent)?;
-        let mut files = std::collections::BTreeMap::new();
+        let mut materialized = MaterializedPeriodDataFiles::new(
+            tempfile::Builder::new()
+                .prefix("plexos2duckdb-bin-")
+                .tempdir_in(staging_parent)?,
+        );
         let total_periods = period_type_ids.len();
 
         for (idx, period_type_id) in period_type_ids.into_iter().enumerate() {
             let period_data = self
                 .period_data
                 .get(&period_type_id)
-                .ok_or_else(|| eyre!("period type not found: {}", period_type_id))?;
+                .ok_or(Error::MissingPeriodData { period_type_id })?;
             let entry = period_data.compressed_zip_entry().ok_or_else(|| {
                 eyre!(
                     "period type {} was expected to be compressed ZIP data",
@@ -2419,30 +4435,52 @@ impl SolutionDataset {
                     total_periods
                 ),
             );
-            let materialized_path = materialized_dir
-                .path()
-                .join(format!("t_data_{period_type_id}.BIN"));
-            entry.materialize_to(&materialized_path)?;
-            files.insert(period_type_id, std::fs::File::open(&materialized_path)?);
+            materialized.materialize(period_type_id, entry)?;
         }
 
-        Ok(Some(MaterializedPeriodDataFiles::new(
-            materialized_dir,
-            files,
-        )))
+        Ok(Some(materialized))
+    }
+
+    /// Extract the period data of `period_type_id` into `materialized` if it is
+    /// compressed in the ZIP.
+    fn materialize_compressed_period_data(
+        &self,
+        period_type_id: i64,
+        materialized: &mut Option<MaterializedPeriodDataFiles>,
+    ) -> Result<()> {
+        let Some(entry) = self
+            .period_data
+            .get(&period_type_id)
+            .and_then(PeriodData::compressed_zip_entry)
+        else {
+            return Ok(());
+        };
+        let files = match materialized {
+            Some(files) => files,
+            None => materialized.insert(MaterializedPeriodDataFiles::new(
+                tempfile::Builder::new()
+                    .prefix("plexos2duckdb-bin-")
+                    .tempdir()?,
+            )),
+        };
+        files.materialize(period_type_id, entry)
     }
 
     fn populate_table_data_uncompressed_parquet(
         &self,
         con: &mut duckdb::Connection,
         plans: Vec<DataTableWritePlan>,
-        worker_count: usize,
+        write_options: &DataWriteOptions<'_>,
         staging_parent: &std::path::Path,
-        external_data_parquet_dir: Option<&std::path::Path>,
         materialized_period_data: Option<&MaterializedPeriodDataFiles>,
         progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
-    ) -> Result<()> {
+    ) -> Result<(
+        std::collections::BTreeMap<String, Vec<std::path::PathBuf>>,
+        u64,
+    )> {
         let total_tables = plans.len();
+        let cancel = write_options.selection.cancel;
+        let worker_count = Self::resolve_data_write_threads(total_tables, write_options.threads);
         let worker_plans = Self::distribute_data_table_plans(plans, worker_count);
         Self::report_duckdb_progress(
             progress,
@@ -2453,135 +4491,150 @@ impl SolutionDataset {
         );
 
         let mut staging_dir = None;
-        let (parquet_root, external_layout) = if let Some(external_dir) = external_data_parquet_dir
-        {
+        let external_layout = write_options.external_layout();
+        let parquet_root = if let Some(external_dir) = write_options.external_parquet_dir {
             std::fs::create_dir_all(external_dir)?;
             let external_dir = external_dir.canonicalize()?;
             std::fs::create_dir_all(external_dir.join("data"))?;
-            (external_dir, true)
+            external_dir
         } else {
             let dir = tempfile::Builder::new()
                 .prefix("plexos2duckdb-data-parquet-")
                 .tempdir_in(staging_parent)?;
             let path = dir.path().to_path_buf();
             staging_dir = Some(dir);
-            (path, false)
+            path
         };
         let (tx, rx) = std::sync::mpsc::channel::<DataWriteWorkerEvent>();
-        let staged_files = std::thread::scope(|scope| -> Result<Vec<StagedDataFiles>> {
-            let mut handles = Vec::with_capacity(worker_plans.len());
-            for (worker_idx, worker_plan) in worker_plans.into_iter().enumerate() {
-                let worker_dir = if external_layout {
-                    parquet_root.join("data")
-                } else {
-                    parquet_root.join(format!("data_worker_{worker_idx}"))
-                };
-                let worker_tx = tx.clone();
-
-                handles.push(scope.spawn(move || -> Result<StagedDataFiles> {
-                    std::fs::create_dir_all(&worker_dir)?;
-                    let table_files = self.write_data_table_plans_to_parquet_files(
-                        worker_idx,
-                        worker_plan,
-                        &worker_dir,
-                        external_layout,
-                        materialized_period_data,
-                        &worker_tx,
-                    )?;
-                    Ok(StagedDataFiles::new(table_files))
-                }));
-            }
-            drop(tx);
-
-            let mut completed_tables = 0usize;
-            while completed_tables < total_tables {
-                let event = match rx.recv() {
-                    Ok(event) => event,
-                    Err(_) => {
-                        for (worker_idx, handle) in handles.into_iter().enumerate() {
-                            let result = handle
-                                .join()
-                                .map_err(|_| eyre!("Data worker {} panicked", worker_idx + 1))?;
-                            if let Err(err) = result {
-                                return Err(eyre!("Data worker {} failed: {err}", worker_idx + 1));
+        let (staged_files, rows_written) = std::thread::scope(
+            |scope| -> Result<(Vec<StagedDataFiles>, u64)> {
+                let mut handles = Vec::with_capacity(worker_plans.len());
+                for (worker_idx, worker_plan) in worker_plans.into_iter().enumerate() {
+                    let worker_dir = if external_layout.is_some() {
+                        parquet_root.join("data")
+                    } else {
+                        parquet_root.join(format!("data_worker_{worker_idx}"))
+                    };
+                    let worker_tx = tx.clone();
+
+                    handles.push(scope.spawn(move || -> Result<StagedDataFiles> {
+                        std::fs::create_dir_all(&worker_dir)?;
+                        let table_files = self.write_data_table_plans_to_parquet_files(
+                            worker_idx,
+                            worker_plan,
+                            &worker_dir,
+                            write_options,
+                            materialized_period_data,
+                            &worker_tx,
+                        )?;
+                        Ok(StagedDataFiles::new(table_files))
+                    }));
+                }
+                drop(tx);
+
+                let mut completed_tables = 0usize;
+                let mut rows_written = 0u64;
+                while completed_tables < total_tables {
+                    let event = match rx.recv() {
+                        Ok(event) => event,
+                        Err(_) => {
+                            for (worker_idx, handle) in handles.into_iter().enumerate() {
+                                let result = handle.join().map_err(|_| {
+                                    eyre!("Data worker {} panicked", worker_idx + 1)
+                                })?;
+                                if let Err(err) = result {
+                                    let msg =
+                                        format!("Data worker {} failed: {err}", worker_idx + 1);
+                                    return Err(err.wrap_err(msg));
+                                }
+                            }
+                            return Err(eyre!(
+                                "Worker progress channel closed before all tables completed ({}/{})",
+                                completed_tables,
+                                total_tables
+                            ));
+                        },
+                    };
+                    match event {
+                        DataWriteWorkerEvent::TableStarted {
+                            worker_id,
+                            index,
+                            total,
+                            table_name,
+                            keys,
+                        } => {
+                            if let Some(report) = progress.as_mut() {
+                                report(DuckdbProgress::Event(
+                                    ProgressEvent::DataWorkerTableStart {
+                                        worker_id,
+                                        index,
+                                        total,
+                                        table_name,
+                                        keys,
+                                    },
+                                ));
+                            }
+                        },
+                        DataWriteWorkerEvent::TableCompleted {
+                            worker_id,
+                            index,
+                            total,
+                            table_name,
+                            keys,
+                            rows,
+                        } => {
+                            rows_written += rows;
+                            if let Some(report) = progress.as_mut() {
+                                report(DuckdbProgress::Event(ProgressEvent::DataWorkerTableEnd {
+                                    worker_id,
+                                    index,
+                                    total,
+                                }));
                             }
-                        }
-                        return Err(eyre!(
-                            "Worker progress channel closed before all tables completed ({}/{})",
-                            completed_tables,
-                            total_tables
-                        ));
-                    },
-                };
-                match event {
-                    DataWriteWorkerEvent::TableStarted {
-                        worker_id,
-                        index,
-                        total,
-                        table_name,
-                        keys,
-                    } => {
-                        if let Some(report) = progress.as_mut() {
-                            report(DuckdbProgress::Event(ProgressEvent::DataWorkerTableStart {
-                                worker_id,
-                                index,
-                                total,
-                                table_name,
-                                keys,
-                            }));
-                        }
-                    },
-                    DataWriteWorkerEvent::TableCompleted {
-                        worker_id,
-                        index,
-                        total,
-                        table_name,
-                        keys,
-                    } => {
-                        if let Some(report) = progress.as_mut() {
-                            report(DuckdbProgress::Event(ProgressEvent::DataWorkerTableEnd {
-                                worker_id,
-                                index,
-                                total,
-                            }));
-                        }
 
-                        completed_tables += 1;
-                        if let Some(report) = progress.as_mut() {
-                            report(DuckdbProgress::Event(ProgressEvent::DataTableStart {
-                                index: completed_tables,
-                                total: total_tables,
-                                table_name,
-                                keys,
-                            }));
-                            report(DuckdbProgress::Event(ProgressEvent::DataTableEnd));
-                        }
-                    },
+                            completed_tables += 1;
+                            if let Some(report) = progress.as_mut() {
+                                report(DuckdbProgress::Event(ProgressEvent::DataTableStart {
+                                    index: completed_tables,
+                                    total: total_tables,
+                                    table_name,
+                                    keys,
+                                }));
+                                report(DuckdbProgress::Event(ProgressEvent::DataTableEnd));
+                            }
+                        },
+                    }
                 }
-            }
 
-            let mut staged_files = Vec::with_capacity(handles.len());
-            Self::report_duckdb_progress(progress, "Finalizing staged parquet files");
-            for (worker_idx, handle) in handles.into_iter().enumerate() {
-                let result = handle
-                    .join()
-                    .map_err(|_| eyre!("Data worker {} panicked", worker_idx + 1))?;
-                staged_files.push(
-                    result.map_err(|err| eyre!("Data worker {} failed: {err}", worker_idx + 1))?,
-                );
-            }
-            Ok(staged_files)
-        })?;
+                let mut staged_files = Vec::with_capacity(handles.len());
+                Self::report_duckdb_progress(progress, "Finalizing staged parquet files");
+                for (worker_idx, handle) in handles.into_iter().enumerate() {
+                    let result = handle
+                        .join()
+                        .map_err(|_| eyre!("Data worker {} panicked", worker_idx + 1))?;
+                    staged_files.push(result.map_err(|err| {
+                        let msg = format!("Data worker {} failed: {err}", worker_idx + 1);
+                        err.wrap_err(msg)
+                    })?);
+                }
+                Ok((staged_files, rows_written))
+            },
+        )?;
 
-        if external_layout {
+        let mut external_files = std::collections::BTreeMap::new();
+        if let Some(layout) = external_layout {
             Self::report_duckdb_progress(progress, "Creating external parquet data views");
             self.create_external_parquet_data_views(con, &parquet_root, &staged_files, progress)?;
+            if layout == ExternalDataParquetLayout::Hive {
+                self.create_external_parquet_hive_dataset_view(con)?;
+            }
+            external_files = Self::staged_files_by_table(&staged_files);
         } else {
             Self::report_duckdb_progress(progress, "Merging staged parquet files");
-            self.merge_staged_data_files(con, &staged_files, progress)?;
+            self.merge_staged_data_files(con, &staged_files, cancel, progress)?;
         }
         drop(staging_dir);
-        Ok(())
+        Ok((external_files, rows_written))
     }
 
     fn distribute_data_table_plans(
@@ -2612,14 +4665,16 @@ impl SolutionDataset {
         worker_idx: usize,
         worker_plan: Vec<DataTableWritePlan>,
         worker_dir: &std::path::Path,
-        external_layout: bool,
+        write_options: &DataWriteOptions<'_>,
         materialized_period_data: Option<&MaterializedPeriodDataFiles>,
         worker_tx: &std::sync::mpsc::Sender<DataWriteWorkerEvent>,
     ) -> Result<std::collections::BTreeMap<String, Vec<std::path::PathBuf>>> {
+        let cancel = write_options.selection.cancel;
         let worker_total = worker_plan.len();
         let mut table_files = std::collections::BTreeMap::<String, Vec<std::path::PathBuf>>::new();
 
         for (worker_table_idx, table_plan) in worker_plan.into_iter().enumerate() {
+            Self::check_cancelled(cancel)?;
             let worker_table_index = worker_table_idx + 1;
             let table_name = table_plan.table_name.clone();
             let keys = table_plan.key_ids.len();
@@ -2631,17 +4686,32 @@ impl SolutionDataset {
                 keys,
             });
 
-            let parquet_path = if external_layout {
-                let table_dir_name = Self::external_data_table_dir_name(&table_name);
-                let table_dir = worker_dir.join(&table_dir_name);
-                std::fs::create_dir_all(&table_dir)?;
-                table_dir.join(format!("{table_dir_name}.part-00001.parquet"))
-            } else {
-                worker_dir.join(format!("table_{worker_table_index:05}.parquet"))
+            let parquet_path = match write_options.external_layout() {
+                Some(ExternalDataParquetLayout::Table) => {
+                    let table_dir_name = Self::external_data_table_dir_name(&table_name);
+                    let table_dir = worker_dir.join(&table_dir_name);
+                    std::fs::create_dir_all(&table_dir)?;
+                    table_dir.join(format!("{table_dir_name}.part-00001.parquet"))
+                },
+                Some(ExternalDataParquetLayout::Hive) => {
+                    let table_dir =
+                        worker_dir.join(Self::external_data_hive_partition_dir(&table_name)?);
+                    std::fs::create_dir_all(&table_dir)?;
+                    table_dir.join("part-00001.parquet")
+                },
+                None => worker_dir.join(format!("table_{worker_table_index:05}.parquet")),
             };
-            let mut writer = Self::open_data_parquet_writer(&parquet_path)?;
-            self.write_data_table_to_parquet(&mut writer, &table_plan, materialized_period_data)?;
-            writer.close()?;
+            let mut writer = Self::open_data_parquet_writer(
+                &parquet_path,
+                self.data_parquet_key_value_metadata(&table_plan),
+            )?;
+            self.write_data_table_to_parquet(
+                &mut writer,
+                &table_plan,
+                materialized_period_data,
+                cancel,
+            )?;
+            let rows = u64::try_from(writer.close()?.file_metadata().num_rows())?;
 
             table_files
                 .entry(table_name.clone())
@@ -2654,6 +4724,7 @@ impl SolutionDataset {
                 total: worker_total,
                 table_name,
                 keys,
+                rows,
             });
         }
 
@@ -2665,41 +4736,67 @@ impl SolutionDataset {
         writer: &mut ArrowWriter<std::fs::File>,
         plan: &DataTableWritePlan,
         materialized_period_data: Option<&MaterializedPeriodDataFiles>,
+        cancel: Option<&std::sync::atomic::AtomicBool>,
     ) -> Result<()> {
-        for key_id in plan.key_ids.iter().copied() {
-            let ki = self.key_index(key_id)?;
-            let key = self.key(key_id)?;
+        for (key_id, values) in plan.key_ids.iter().copied().zip(&plan.value_ranges) {
+            let task = self.data_range_write_task(key_id, values)?;
+            self.write_data_range_task_to_parquet(
+                writer,
+                &task,
+                plan.downsample.as_ref(),
+                materialized_period_data,
+                cancel,
+            )?;
+        }
 
-            if ki.position % 8 != 0 {
-                return Err(eyre!(
-                    "BIN position misaligned for key_id {} (pos_bytes={})",
-                    key_id,
-                    ki.position
-                ));
-            }
+        Ok(())
+    }
 
-            let task = DataRangeWriteTask {
+    fn data_range_write_task(
+        &self,
+        key_id: i64,
+        values: &std::ops::Range<u64>,
+    ) -> Result<DataRangeWriteTask> {
+        let ki = self.key_index(key_id)?;
+        let key = self.key(key_id)?;
+
+        if ki.position % 8 != 0 {
+            return Err(Error::MisalignedPosition {
                 key_id,
-                sample_id: key.sample_id,
-                band_id: key.band_id,
-                membership_id: key.membership_id,
-                period_type_id: ki.period_type_id,
-                position: ki.position,
-                start_value_index: 0,
-                value_count: ki.length,
-                period_offset: ki.period_offset,
-            };
-            self.write_data_range_task_to_parquet(writer, &task, materialized_period_data)?;
+                offset: ki.position,
+            }
+            .into());
         }
 
-        Ok(())
+        Ok(DataRangeWriteTask {
+            key_id,
+            sample_id: key.sample_id,
+            band_id: key.band_id,
+            membership_id: key.membership_id,
+            period_type_id: ki.period_type_id,
+            position: values
+                .start
+                .checked_mul(8)
+                .and_then(|offset| ki.position.checked_add(offset))
+                .ok_or(Error::Overflow {
+                    quantity: "byte offset",
+                    key_id,
+                })?,
+            start_value_index: values.start,
+            value_count: values.end - values.start,
+            period_offset: ki.period_offset,
+        })
     }
 
-    fn open_data_parquet_writer(path: &std::path::Path) -> Result<ArrowWriter<std::fs::File>> {
+    fn open_data_parquet_writer(
+        path: &std::path::Path,
+        key_value_metadata: Vec<KeyValue>,
+    ) -> Result<ArrowWriter<std::fs::File>> {
         let file = std::fs::File::create(path)?;
         let writer_properties = WriterProperties::builder()
             .set_compression(Compression::UNCOMPRESSED)
             .set_max_row_group_row_count(Some(DATA_APPEND_BATCH_VALUES as usize))
+            .set_key_value_metadata(Some(key_value_metadata))
             .build();
         Ok(ArrowWriter::try_new(
             file,
@@ -2708,16 +4805,126 @@ impl SolutionDataset {
         )?)
     }
 
+    /// Footer metadata that keeps a data parquet file self-describing once it is
+    /// copied away from the database that references it.
+    fn data_parquet_key_value_metadata(&self, plan: &DataTableWritePlan) -> Vec<KeyValue> {
+        let table_name = plan.table_name.as_str();
+        let source_table_name = plan.downsample.as_ref().map_or(table_name, |downsample| {
+            downsample.source_table_name.as_str()
+        });
+        let mut metadata = vec![KeyValue::new(
+            "table_name".to_string(),
+            table_name.to_string(),
+        )];
+        let parts = table_name.split("__").collect::<Vec<_>>();
+        if let [phase, period, collection, property] = parts.as_slice() {
+            for (key, value) in [
+                ("phase", phase),
+                ("period", period),
+                ("collection", collection),
+                ("property", property),
+            ] {
+                metadata.push(KeyValue::new(key.to_string(), value.to_string()));
+            }
+        }
+        if let Some((unit, period_offset)) = self.table_units_mapping.get(source_table_name) {
+            metadata.push(KeyValue::new("unit".to_string(), unit.clone()));
+            metadata.push(KeyValue::new(
+                "period_offset".to_string(),
+                period_offset.to_string(),
+            ));
+        }
+        metadata.push(KeyValue::new(
+            "model_name".to_string(),
+            self.model_name.clone(),
+        ));
+        metadata.push(KeyValue::new(
+            "plexos_file".to_string(),
+            self.file.to_string_lossy().into_owned(),
+        ));
+        metadata.push(KeyValue::new(
+            "plexos2duckdb_version".to_string(),
+            utils::version().to_string(),
+        ));
+        metadata
+    }
+
     fn write_data_range_task_to_parquet(
         &self,
         writer: &mut ArrowWriter<std::fs::File>,
         task: &DataRangeWriteTask,
+        downsample: Option<&DataTableDownsample>,
         materialized_period_data: Option<&MaterializedPeriodDataFiles>,
+        cancel: Option<&std::sync::atomic::AtomicBool>,
     ) -> Result<()> {
+        let Some(downsample) = downsample else {
+            // Batches are at most `DATA_APPEND_BATCH_VALUES` values, so this
+            // checks for cancellation between chunks.
+            return self.write_data_range_task_batches(
+                task,
+                materialized_period_data,
+                |record_batch| {
+                    Self::check_cancelled(cancel)?;
+                    writer.write(&record_batch)?;
+                    Ok(())
+                },
+            );
+        };
+        let batch = self.downsampled_data_range_task_batch(
+            task,
+            downsample,
+            materialized_period_data,
+            cancel,
+        )?;
+        if batch.num_rows() > 0 {
+            writer.write(&batch)?;
+        }
+        Ok(())
+    }
+
+    /// Aggregates a key's values into one row per downsampled block. Intervals
+    /// without a `Period0` entry in the phase are dropped.
+    fn downsampled_data_range_task_batch(
+        &self,
+        task: &DataRangeWriteTask,
+        downsample: &DataTableDownsample,
+        materialized_period_data: Option<&MaterializedPeriodDataFiles>,
+        cancel: Option<&std::sync::atomic::AtomicBool>,
+    ) -> Result<RecordBatch> {
+        let mut blocks = std::collections::BTreeMap::<i64, DownsampleAccumulator>::new();
         self.write_data_range_task_batches(task, materialized_period_data, |record_batch| {
-            writer.write(&record_batch)?;
+            Self::check_cancelled(cancel)?;
+            let block_ids = record_batch.column(4).as_primitive::<Int64Type>();
+            let values = record_batch.column(5).as_primitive::<Float64Type>();
+            for (block_id, value) in block_ids.values().iter().zip(values.values()) {
+                let Some(target_block_id) = downsample.target_blocks.get(block_id) else {
+                    continue;
+                };
+                blocks
+                    .entry(*target_block_id)
+                    .and_modify(|block| block.add(*value))
+                    .or_insert_with(|| DownsampleAccumulator::new(*value));
+            }
             Ok(())
-        })
+        })?;
+
+        let rows = blocks.len();
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![task.key_id; rows])),
+            Arc::new(Int64Array::from(vec![task.sample_id; rows])),
+            Arc::new(Int64Array::from(vec![task.band_id; rows])),
+            Arc::new(Int64Array::from(vec![task.membership_id; rows])),
+            Arc::new(Int64Array::from_iter_values(blocks.keys().copied())),
+            Arc::new(Float64Array::from_iter_values(
+                blocks
+                    .values()
+                    .map(|block| block.finish(downsample.aggregation)),
+            )),
+        ];
+        Ok(RecordBatch::try_new(
+            DATA_RECORD_BATCH_SCHEMA.clone(),
+            columns,
+        )?)
     }
 
     fn write_data_range_task_batches(
@@ -2731,10 +4938,12 @@ impl SolutionDataset {
             return Self::write_data_range_task_batches_from_file(file, task, &mut write_batch);
         }
 
-        let period_data = self
-            .period_data
-            .get(&task.period_type_id)
-            .ok_or_else(|| eyre!("period type not found: {}", task.period_type_id))?;
+        let period_data =
+            self.period_data
+                .get(&task.period_type_id)
+                .ok_or(Error::MissingPeriodData {
+                    period_type_id: task.period_type_id,
+                })?;
 
         let mut chunk_buf = vec![0u8; (DATA_APPEND_BATCH_VALUES as usize) * 8];
         let mut value_offset = 0u64;
@@ -2742,23 +4951,25 @@ impl SolutionDataset {
         while value_offset < task.value_count {
             let chunk_values = (task.value_count - value_offset).min(DATA_APPEND_BATCH_VALUES);
             let chunk_bytes = Self::data_chunk_byte_len(task.key_id, chunk_values)?;
-            let offset_delta = value_offset
-                .checked_mul(8)
-                .ok_or_else(|| eyre!("Byte offset overflow for key_id {}", task.key_id))?;
+            let offset_delta = value_offset.checked_mul(8).ok_or(Error::Overflow {
+                quantity: "byte offset",
+                key_id: task.key_id,
+            })?;
             let chunk_offset = task
                 .position
                 .checked_add(offset_delta)
-                .ok_or_else(|| eyre!("Byte offset overflow for key_id {}", task.key_id))?;
+                .ok_or(Error::Overflow {
+                    quantity: "byte offset",
+                    key_id: task.key_id,
+                })?;
 
             period_data
                 .read_exact_at(chunk_offset, &mut chunk_buf[..chunk_bytes])
-                .map_err(|err| {
-                    eyre!(
-                        "Failed reading period data for key_id {} at byte offset {}: {}",
-                        task.key_id,
-                        chunk_offset,
-                        err
-                    )
+                .map_err(|source| Error::PeriodDataRead {
+                    key_id: task.key_id,
+                    offset: chunk_offset,
+                    materialized: false,
+                    source,
                 })?;
 
             Self::write_data_range_batch(
@@ -2769,7 +4980,10 @@ impl SolutionDataset {
             )?;
             value_offset = value_offset
                 .checked_add(chunk_values)
-                .ok_or_else(|| eyre!("Value index overflow for key_id {}", task.key_id))?;
+                .ok_or(Error::Overflow {
+                    quantity: "value index",
+                    key_id: task.key_id,
+                })?;
         }
 
         Ok(())
@@ -2786,22 +5000,24 @@ impl SolutionDataset {
         while value_offset < task.value_count {
             let chunk_values = (task.value_count - value_offset).min(DATA_APPEND_BATCH_VALUES);
             let chunk_bytes = Self::data_chunk_byte_len(task.key_id, chunk_values)?;
-            let offset_delta = value_offset
-                .checked_mul(8)
-                .ok_or_else(|| eyre!("Byte offset overflow for key_id {}", task.key_id))?;
+            let offset_delta = value_offset.checked_mul(8).ok_or(Error::Overflow {
+                quantity: "byte offset",
+                key_id: task.key_id,
+            })?;
             let chunk_offset = task
                 .position
                 .checked_add(offset_delta)
-                .ok_or_else(|| eyre!("Byte offset overflow for key_id {}", task.key_id))?;
+                .ok_or(Error::Overflow {
+                    quantity: "byte offset",
+                    key_id: task.key_id,
+                })?;
 
             Self::read_exact_at(file, chunk_offset, &mut chunk_buf[..chunk_bytes]).map_err(
-                |err| {
-                    eyre!(
-                        "Failed reading materialized period data for key_id {} at byte offset {}: {}",
-                        task.key_id,
-                        chunk_offset,
-                        err
-                    )
+                |source| Error::PeriodDataRead {
+                    key_id: task.key_id,
+                    offset: chunk_offset,
+                    materialized: true,
+                    source,
                 },
             )?;
 
@@ -2813,7 +5029,10 @@ impl SolutionDataset {
             )?;
             value_offset = value_offset
                 .checked_add(chunk_values)
-                .ok_or_else(|| eyre!("Value index overflow for key_id {}", task.key_id))?;
+                .ok_or(Error::Overflow {
+                    quantity: "value index",
+                    key_id: task.key_id,
+                })?;
         }
 
         Ok(())
@@ -2831,10 +5050,12 @@ impl SolutionDataset {
             band_id: task.band_id,
             membership_id: task.membership_id,
             period_offset: task.period_offset,
-            start_value_index: task
-                .start_value_index
-                .checked_add(value_offset)
-                .ok_or_else(|| eyre!("Value index overflow for key_id {}", task.key_id))?,
+            start_value_index: task.start_value_index.checked_add(value_offset).ok_or(
+                Error::Overflow {
+                    quantity: "value index",
+                    key_id: task.key_id,
+                },
+            )?,
         };
         write_batch(metadata.record_batch(bytes)?)?;
 
@@ -2842,25 +5063,32 @@ impl SolutionDataset {
     }
 
     fn data_chunk_byte_len(key_id: i64, chunk_values: u64) -> Result<usize> {
-        let chunk_bytes_u64 = chunk_values
-            .checked_mul(8)
-            .ok_or_else(|| eyre!("Chunk size overflow for key_id {}", key_id))?;
+        let chunk_bytes_u64 = chunk_values.checked_mul(8).ok_or(Error::Overflow {
+            quantity: "chunk size",
+            key_id,
+        })?;
         usize::try_from(chunk_bytes_u64)
-            .map_err(|_| eyre!("Chunk size exceeds usize for key_id {}", key_id))
+            .map_err(|_| Error::Overflow {
+                quantity: "chunk size",
+                key_id,
+            })
+            .map_err(Into::into)
     }
 
     fn merge_staged_data_files(
         &self,
         con: &mut duckdb::Connection,
         staged_files: &[StagedDataFiles],
+        cancel: Option<&std::sync::atomic::AtomicBool>,
         progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
     ) -> Result<()> {
         let target_catalog = Self::current_catalog_name(con)?;
-        let target_catalog_ident = Self::quote_ident(&target_catalog);
+        let target_catalog_ident = quote_ident(&target_catalog);
         let files_by_table = Self::staged_files_by_table(staged_files);
         let total_merges = files_by_table.len();
 
         for (table_idx, (table_name, files)) in files_by_table.into_iter().enumerate() {
+            Self::check_cancelled(cancel)?;
             let merge_index = table_idx + 1;
             if let Some(report) = progress.as_mut() {
                 report(DuckdbProgress::Event(ProgressEvent::DataMergeTableStart {
@@ -2870,7 +5098,7 @@ impl SolutionDataset {
                 }));
             }
 
-            let table_ident = Self::quote_ident(&table_name);
+            let table_ident = quote_ident(&table_name);
             let parquet_paths =
                 Self::sql_string_list(files.iter().map(|path| path.to_string_lossy().into_owned()));
             con.execute_batch(&format!(
@@ -2914,7 +5142,7 @@ impl SolutionDataset {
                 }));
             }
 
-            let table_ident = Self::quote_ident(&table_name);
+            let table_ident = quote_ident(&table_name);
             let parquet_paths = Self::external_parquet_path_expr_list(parquet_root, &files)?;
             con.execute_batch(&format!(
                 "CREATE OR REPLACE VIEW data.{table_ident} AS
@@ -2932,6 +5160,24 @@ impl SolutionDataset {
         Ok(())
     }
 
+    /// Combined view over every Hive-partitioned external parquet file so
+    /// consumers can filter on `phase`, `period`, `collection` and `property`
+    /// instead of picking individual data views.
+    fn create_external_parquet_hive_dataset_view(
+        &self,
+        con: &mut duckdb::Connection,
+    ) -> Result<()> {
+        con.execute_batch(
+            "CREATE OR REPLACE VIEW main.plexos2duckdb_external_data AS
+             SELECT * FROM read_parquet(
+               main.plexos2duckdb_external_data_root() || '/data/*/*/*/*/*.parquet',
+               hive_partitioning = true,
+               hive_types_autocast = false
+             );",
+        )?;
+        Ok(())
+    }
+
     fn staged_files_by_table(
         staged_files: &[StagedDataFiles],
     ) -> std::collections::BTreeMap<String, Vec<std::path::PathBuf>> {
@@ -2949,7 +5195,7 @@ impl SolutionDataset {
     }
 
     fn create_data_table(&self, con: &mut duckdb::Connection, table_name: &str) -> Result<()> {
-        let table_ident = Self::quote_ident(table_name);
+        let table_ident = quote_ident(table_name);
         con.execute_batch(&format!(
             r#"
             CREATE TABLE data.{table_ident} (
@@ -2965,10 +5211,6 @@ impl SolutionDataset {
         Ok(())
     }
 
-    fn quote_ident(identifier: &str) -> String {
-        format!("\"{}\"", identifier.replace('\"', "\"\""))
-    }
-
     fn sql_string_literal(value: &str) -> String {
         value.replace('\'', "''")
     }
@@ -3023,6 +5265,62 @@ impl SolutionDataset {
         }
     }
 
+    fn external_data_hive_partition_dir(table_name: &str) -> Result<std::path::PathBuf> {
+        let parts = table_name.split("__").collect::<Vec<_>>();
+        let [phase, period, collection, property] = parts.as_slice() else {
+            return Err(Error::InvalidDataTableName {
+                table_name: table_name.to_string(),
+            }
+            .into());
+        };
+
+        let mut dir = std::path::PathBuf::new();
+        for (column, value) in [
+            ("phase", phase),
+            ("period", period),
+            ("collection", collection),
+            ("property", property),
+        ] {
+            dir.push(format!("{column}={}", Self::hive_partition_value(value)));
+        }
+        Ok(dir)
+    }
+
+    /// Escape a Hive partition value as Hive does, e.g. `/` becomes `%2F`.
+    /// `<`, `>` and `|` are also escaped on Windows.
+    fn hive_partition_value(value: &str) -> String {
+        if value.is_empty() {
+            return "__HIVE_DEFAULT_PARTITION__".to_string();
+        }
+        let mut escaped = String::with_capacity(value.len());
+        for ch in value.chars() {
+            let escape = ch.is_ascii_control()
+                || matches!(
+                    ch,
+                    '"' | '#'
+                        | '%'
+                        | '\''
+                        | '*'
+                        | '/'
+                        | ':'
+                        | '='
+                        | '?'
+                        | '\\'
+                        | '{'
+                        | '['
+                        | ']'
+                        | '^'
+                )
+                || (cfg!(windows) && matches!(ch, '<' | '>' | '|'));
+            if escape {
+                escaped.push_str(&format!("%{:02X}", ch as u32));
+            } else {
+                escaped.push(ch);
+            }
+        }
+        escaped
+    }
+
     fn resolve_external_data_parquet_dir(
         db_path: &std::path::Path,
         parquet_dir: &std::path::Path,
@@ -3097,16 +5395,24 @@ impl SolutionDataset {
     ///   would break once the file is renamed to `db_path`'s stem.
     /// * `con` is dropped before the rename so the file handle is closed
     ///   and any WAL sidecar is finalized/removed by the checkpoint.
+    ///
+    /// `finish` runs on the written file, reopened before the rename, for
+    /// rows such as step timings that are only known once it is complete.
     fn persist_duckdb_database(
         con: duckdb::Connection,
         staging_dir: tempfile::TempDir,
         db_path: &std::path::Path,
+        finish: impl FnOnce(&duckdb::Connection) -> Result<()>,
     ) -> Result<()> {
         con.execute_batch("PRAGMA force_checkpoint;")?;
         con.execute_batch("CHECKPOINT;")?;
         drop(con);
 
         let staging_path = Self::staging_db_path(&staging_dir);
+        let con = duckdb::Connection::open(&staging_path)?;
+        finish(&con)?;
+        con.execute_batch("CHECKPOINT;")?;
+        drop(con);
         std::fs::rename(&staging_path, db_path).map_err(|err| {
             eyre!(
                 "failed to move staging DuckDB {} to {}: {}",
@@ -3129,7 +5435,7 @@ impl SolutionDataset {
         #[cfg(unix)]
         {
             use std::os::unix::fs::FileExt as _;
-            return file.read_exact_at(buf, offset);
+            file.read_exact_at(buf, offset)
         }
 
         #[cfg(windows)]
@@ -3163,667 +5469,673 @@ impl SolutionDataset {
 
     fn populate_table_config(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-            CREATE TABLE raw.config (
-                element VARCHAR,
-                value VARCHAR
-            );
-        ",
-        )?;
-
-        let mut appender = con.appender_to_db("config", "raw")?;
-
-        for (element, value) in &self.config {
-            let value_str = &value.clone().unwrap_or_default();
-            appender.append_row(&[element, value_str])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "config",
+            vec![
+                SinkColumn::new("element", SinkColumnType::Varchar),
+                SinkColumn::new("value", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.config.iter().map(|(element, value)| {
+                Ok(vec![
+                    element.clone().into(),
+                    value.clone().unwrap_or_default().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_memberships(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TYPE object_kind AS ENUM ('object', 'relation');
-              CREATE TABLE raw.memberships (
-                membership_id BIGINT PRIMARY KEY,
-                collection_id BIGINT,
-                collection VARCHAR,
-                child_id BIGINT,
-                child_name VARCHAR,
-                child_category VARCHAR,
-                child_category_class VARCHAR,
-                parent_id BIGINT,
-                parent_name VARCHAR,
-                parent_category VARCHAR,
-                parent_category_class VARCHAR,
-                child_class_id BIGINT,
-                child_class_name VARCHAR,
-                parent_class_id BIGINT,
-                parent_class_name VARCHAR,
-                kind object_kind,
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("memberships", "raw")?;
-
-        for membership in self.membership.values() {
-            let child = self.object(membership.child_object_id)?;
-            let parent = self.object(membership.parent_object_id)?;
-            let child_category = self.category(child.category_id)?;
-            let child_category_class = self.class(child_category.class_id)?;
-            let child_class = self.class(membership.child_class_id)?;
-            let parent_category = self.category(parent.category_id)?;
-            let parent_category_class = self.class(parent_category.class_id)?;
-            let parent_class = self.class(membership.parent_class_id)?;
-            let collection_name = self.collection_name(membership.collection_id)?;
-            let kind = if self.is_object(membership.collection_id)? {
-                "object"
-            } else {
-                "relation"
-            }
-            .to_string();
-
-            appender.append_row(duckdb::params![
-                membership.membership_id,
-                membership.collection_id,
-                collection_name,
-                child.object_id,
-                child.name,
-                child_category.name,
-                child_category_class.name,
-                parent.object_id,
-                parent.name,
-                parent_category.name,
-                parent_category_class.name,
-                child.class_id,
-                child_class.name,
-                parent.class_id,
-                parent_class.name,
-                kind,
-            ])?;
-        }
+        let table = SinkTable::raw(
+            "memberships",
+            vec![
+                SinkColumn::primary_key("membership_id", SinkColumnType::BigInt),
+                SinkColumn::new("collection_id", SinkColumnType::BigInt),
+                SinkColumn::new("collection", SinkColumnType::Varchar),
+                SinkColumn::new("child_id", SinkColumnType::BigInt),
+                SinkColumn::new("child_name", SinkColumnType::Varchar),
+                SinkColumn::new("child_category", SinkColumnType::Varchar),
+                SinkColumn::new("child_category_class", SinkColumnType::Varchar),
+                SinkColumn::new("parent_id", SinkColumnType::BigInt),
+                SinkColumn::new("parent_name", SinkColumnType::Varchar),
+                SinkColumn::new("parent_category", SinkColumnType::Varchar),
+                SinkColumn::new("parent_category_class", SinkColumnType::Varchar),
+                SinkColumn::new("child_class_id", SinkColumnType::BigInt),
+                SinkColumn::new("child_class_name", SinkColumnType::Varchar),
+                SinkColumn::new("parent_class_id", SinkColumnType::BigInt),
+                SinkColumn::new("parent_class_name", SinkColumnType::Varchar),
+                SinkColumn::new(
+                    "kind",
+                    SinkColumnType::Enum {
+                        name: "object_kind",
+                        values: &["object", "relation"],
+                    },
+                ),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
 
-        appender.flush()?;
+        let mut rows = self
+            .membership
+            .values()
+            .filter(|membership| DimensionTrim::keeps_membership(trim, membership.membership_id))
+            .map(|membership| {
+                let child = self.object(membership.child_object_id)?;
+                let parent = self.object(membership.parent_object_id)?;
+                let child_category = self.category(child.category_id)?;
+                let child_category_class = self.class(child_category.class_id)?;
+                let child_class = self.class(membership.child_class_id)?;
+                let parent_category = self.category(parent.category_id)?;
+                let parent_category_class = self.class(parent_category.class_id)?;
+                let parent_class = self.class(membership.parent_class_id)?;
+                let collection_name = self.collection_name(membership.collection_id)?;
+                let kind = if self.is_object(membership.collection_id)? {
+                    "object"
+                } else {
+                    "relation"
+                };
 
-        Ok(())
+                Ok(vec![
+                    membership.membership_id.into(),
+                    membership.collection_id.into(),
+                    collection_name.into(),
+                    child.object_id.into(),
+                    child.name.clone().into(),
+                    child_category.name.clone().into(),
+                    child_category_class.name.clone().into(),
+                    parent.object_id.into(),
+                    parent.name.clone().into(),
+                    parent_category.name.clone().into(),
+                    parent_category_class.name.clone().into(),
+                    child.class_id.into(),
+                    child_class.name.clone().into(),
+                    parent.class_id.into(),
+                    parent_class.name.clone().into(),
+                    kind.to_string().into(),
+                ])
+            });
+        sink.append_rows(&table, &mut rows)
     }
 
     fn populate_table_collections(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.collections (
-                collection_id BIGINT PRIMARY KEY,
-                parent_class_id BIGINT,
-                child_class_id BIGINT,
-                name VARCHAR,
-                complement_name VARCHAR
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("collections", "raw")?;
-
-        for collection in self.collection.values() {
-            appender.append_row(duckdb::params![
-                collection.collection_id,
-                collection.parent_class_id,
-                collection.child_class_id,
-                collection.name,
-                collection.complement_name
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "collections",
+            vec![
+                SinkColumn::primary_key("collection_id", SinkColumnType::BigInt),
+                SinkColumn::new("parent_class_id", SinkColumnType::BigInt),
+                SinkColumn::new("child_class_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("complement_name", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.collection.values().map(|collection| {
+                Ok(vec![
+                    collection.collection_id.into(),
+                    collection.parent_class_id.into(),
+                    collection.child_class_id.into(),
+                    collection.name.clone().into(),
+                    collection.complement_name.clone().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_classes(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.classes (
-                class_id BIGINT PRIMARY KEY,
-                name VARCHAR,
-                class_group_id BIGINT
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("classes", "raw")?;
-
-        for class in self.class.values() {
-            appender.append_row(duckdb::params![
-                class.class_id,
-                class.name,
-                class.class_group_id
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "classes",
+            vec![
+                SinkColumn::primary_key("class_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("class_group_id", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.class.values().map(|class| {
+                Ok(vec![
+                    class.class_id.into(),
+                    class.name.clone().into(),
+                    class.class_group_id.into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_class_groups(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.class_groups (
-                class_group_id BIGINT PRIMARY KEY,
-                name VARCHAR
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("class_groups", "raw")?;
-
-        for class_group in self.class_group.values() {
-            appender.append_row(duckdb::params![
-                class_group.class_group_id,
-                class_group.name
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "class_groups",
+            vec![
+                SinkColumn::primary_key("class_group_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.class_group.values().map(|class_group| {
+                Ok(vec![
+                    class_group.class_group_id.into(),
+                    class_group.name.clone().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_categories(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.categories (
-                category_id BIGINT PRIMARY KEY,
-                class_id BIGINT,
-                rank BIGINT,
-                name VARCHAR
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("categories", "raw")?;
-
-        for category in self.category.values() {
-            appender.append_row(duckdb::params![
-                category.category_id,
-                category.class_id,
-                category.rank,
-                category.name
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "categories",
+            vec![
+                SinkColumn::primary_key("category_id", SinkColumnType::BigInt),
+                SinkColumn::new("class_id", SinkColumnType::BigInt),
+                SinkColumn::new("rank", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.category.values().map(|category| {
+                Ok(vec![
+                    category.category_id.into(),
+                    category.class_id.into(),
+                    category.rank.into(),
+                    category.name.clone().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_bands(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.bands (
-                band_id BIGINT PRIMARY KEY,
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("bands", "raw")?;
-
-        for band in self.band.values() {
-            appender.append_row(duckdb::params![band])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "bands",
+            vec![SinkColumn::primary_key("band_id", SinkColumnType::BigInt)],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.band.values().map(|band| Ok(vec![(*band).into()])),
+        )
     }
 
     fn populate_table_models(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.models (
-                model_id BIGINT PRIMARY KEY,
-                name VARCHAR
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("models", "raw")?;
-
-        for model in self.model.values() {
-            appender.append_row(duckdb::params![model.model_id, model.name])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "models",
+            vec![
+                SinkColumn::primary_key("model_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self
+                .model
+                .values()
+                .map(|model| Ok(vec![model.model_id.into(), model.name.clone().into()])),
+        )
     }
 
     fn populate_table_objects(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.objects (
-                object_id BIGINT PRIMARY KEY,
-                class_id BIGINT,
-                name VARCHAR,
-                category_id BIGINT,
-                index BIGINT,
-                is_show BOOLEAN,
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("objects", "raw")?;
-
-        for object in self.object.values() {
-            appender.append_row(duckdb::params![
-                object.object_id,
-                object.class_id,
-                object.name,
-                object.category_id,
-                object.index,
-                object.show,
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "objects",
+            vec![
+                SinkColumn::primary_key("object_id", SinkColumnType::BigInt),
+                SinkColumn::new("class_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("category_id", SinkColumnType::BigInt),
+                SinkColumn::new("index", SinkColumnType::BigInt),
+                SinkColumn::new("is_show", SinkColumnType::Boolean),
+                SinkColumn::new("guid", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self
+                .object
+                .values()
+                .filter(|object| DimensionTrim::keeps_object(trim, object.object_id))
+                .map(|object| {
+                    Ok(vec![
+                        object.object_id.into(),
+                        object.class_id.into(),
+                        object.name.clone().into(),
+                        object.category_id.into(),
+                        object.index.into(),
+                        object.show.into(),
+                        object.guid.clone().into(),
+                    ])
+                }),
+        )
     }
 
     fn populate_table_keys(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.keys (
-                key_id BIGINT PRIMARY KEY,
-                membership_id BIGINT,
-                model_id BIGINT,
-                phase_id BIGINT,
-                property_id BIGINT,
-                is_summary BOOLEAN,
-                band_id BIGINT,
-                sample_id BIGINT,
-                timeslice_id BIGINT
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("keys", "raw")?;
-
-        for key in self.key.values() {
-            appender.append_row(duckdb::params![
-                key.key_id,
-                key.membership_id,
-                key.model_id,
-                key.phase_id,
-                key.property_id,
-                key.is_summary,
-                key.band_id,
-                key.sample_id,
-                key.timeslice_id
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "keys",
+            vec![
+                SinkColumn::primary_key("key_id", SinkColumnType::BigInt),
+                SinkColumn::new("membership_id", SinkColumnType::BigInt),
+                SinkColumn::new("model_id", SinkColumnType::BigInt),
+                SinkColumn::new("phase_id", SinkColumnType::BigInt),
+                SinkColumn::new("property_id", SinkColumnType::BigInt),
+                SinkColumn::new("is_summary", SinkColumnType::Boolean),
+                SinkColumn::new("band_id", SinkColumnType::BigInt),
+                SinkColumn::new("sample_id", SinkColumnType::BigInt),
+                SinkColumn::new("timeslice_id", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self
+                .key
+                .values()
+                .filter(|key| DimensionTrim::keeps_key(trim, key.key_id))
+                .map(|key| {
+                    Ok(vec![
+                        key.key_id.into(),
+                        key.membership_id.into(),
+                        key.model_id.into(),
+                        key.phase_id.into(),
+                        key.property_id.into(),
+                        key.is_summary.into(),
+                        key.band_id.into(),
+                        key.sample_id.into(),
+                        key.timeslice_id.into(),
+                    ])
+                }),
+        )
     }
 
     fn populate_table_key_indexes(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.key_indexes (
-                key_id BIGINT PRIMARY KEY,
-                period_type_id BIGINT,
-                position UBIGINT,
-                length UBIGINT,
-                period_offset BIGINT,
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("key_indexes", "raw")?;
-
-        for key_index in self.key_index.values() {
-            appender.append_row(duckdb::params![
-                key_index.key_id,
-                key_index.period_type_id,
-                key_index.position,
-                key_index.length,
-                key_index.period_offset
-            ])?;
-        }
-
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "key_indexes",
+            vec![
+                SinkColumn::primary_key("key_id", SinkColumnType::BigInt),
+                SinkColumn::new("period_type_id", SinkColumnType::BigInt),
+                SinkColumn::new("position", SinkColumnType::UBigInt),
+                SinkColumn::new("length", SinkColumnType::UBigInt),
+                SinkColumn::new("period_offset", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self
+                .key_index
+                .values()
+                .filter(|key_index| DimensionTrim::keeps_key(trim, key_index.key_id))
+                .map(|key_index| {
+                    Ok(vec![
+                        key_index.key_id.into(),
+                        key_index.period_type_id.into(),
+                        key_index.position.into(),
+                        key_index.length.into(),
+                        key_index.period_offset.into(),
+                    ])
+                }),
+        )
     }
 
     fn populate_table_properties(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
-    ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.properties (
-                property_id BIGINT PRIMARY KEY,
-                name VARCHAR,
-                summary_name VARCHAR,
-                enum_id BIGINT,
-                unit_id BIGINT,
-                summary_unit_id BIGINT,
-                is_multi_band BOOLEAN,
-                is_period BOOLEAN,
-                is_summary BOOLEAN,
-                collection_id BIGINT,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("properties", "raw")?;
-        for (_, property) in self.property.iter() {
-            appender.append_row(duckdb::params![
-                property.property_id,
-                property.name,
-                property.summary_name,
-                property.enum_id,
-                property.unit_id,
-                property.summary_unit_id,
-                property.is_multi_band,
-                property.is_period,
-                property.is_summary,
-                property.collection_id,
-            ])?;
-        }
-        appender.flush()?;
-
-        Ok(())
-    }
-
-    fn populate_table_timeslices(
-        &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.timeslices (
-                timeslice_id BIGINT PRIMARY KEY,
-                timeslice_name VARCHAR,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("timeslices", "raw")?;
-        for (_, timeslice) in self.timeslice.iter() {
-            appender.append_row(duckdb::params![timeslice.timeslice_id, timeslice.name])?;
-        }
-        appender.flush()?;
+        let table = SinkTable::raw(
+            "properties",
+            vec![
+                SinkColumn::primary_key("property_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("summary_name", SinkColumnType::Varchar),
+                SinkColumn::new("enum_id", SinkColumnType::BigInt),
+                SinkColumn::new("unit_id", SinkColumnType::BigInt),
+                SinkColumn::new("summary_unit_id", SinkColumnType::BigInt),
+                SinkColumn::new("is_multi_band", SinkColumnType::Boolean),
+                SinkColumn::new("is_period", SinkColumnType::Boolean),
+                SinkColumn::new("is_summary", SinkColumnType::Boolean),
+                SinkColumn::new("collection_id", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.property.values().map(|property| {
+                Ok(vec![
+                    property.property_id.into(),
+                    property.name.clone().into(),
+                    property.summary_name.clone().into(),
+                    property.enum_id.into(),
+                    property.unit_id.into(),
+                    property.summary_unit_id.into(),
+                    property.is_multi_band.into(),
+                    property.is_period.into(),
+                    property.is_summary.into(),
+                    property.collection_id.into(),
+                ])
+            }),
+        )
+    }
 
-        Ok(())
+    fn populate_table_timeslices(
+        &self,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
+    ) -> Result<()> {
+        let table = SinkTable::raw(
+            "timeslices",
+            vec![
+                SinkColumn::primary_key("timeslice_id", SinkColumnType::BigInt),
+                SinkColumn::new("timeslice_name", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.timeslice.values().map(|timeslice| {
+                Ok(vec![
+                    timeslice.timeslice_id.into(),
+                    timeslice.name.clone().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_samples(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.samples (
-                sample_id BIGINT PRIMARY KEY,
-                sample_name VARCHAR,
-                sample_phase_id BIGINT,
-                sample_weight DOUBLE,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("samples", "raw")?;
-        for (_, sample) in self.sample.iter() {
-            let sample_weight = self
-                .sample_weight(sample.sample_id)
-                .cloned()
-                .unwrap_or_else(|_| SampleWeight {
-                    sample_id: sample.sample_id,
-                    phase_id: 0,
-                    weight: 0.0,
-                });
-
-            appender.append_row(duckdb::params![
-                sample.sample_id,
-                sample.name,
-                sample_weight.phase_id,
-                sample_weight.weight
-            ])?;
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "samples",
+            vec![
+                SinkColumn::primary_key("sample_id", SinkColumnType::BigInt),
+                SinkColumn::new("sample_name", SinkColumnType::Varchar),
+                SinkColumn::new("sample_phase_id", SinkColumnType::BigInt),
+                SinkColumn::new("sample_weight", SinkColumnType::Double),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.sample.values().map(|sample| {
+                let sample_weight =
+                    self.sample_weight(sample.sample_id)
+                        .cloned()
+                        .unwrap_or(SampleWeight {
+                            sample_id: sample.sample_id,
+                            phase_id: 0,
+                            weight: 0.0,
+                        });
+
+                Ok(vec![
+                    sample.sample_id.into(),
+                    sample.name.clone().into(),
+                    sample_weight.phase_id.into(),
+                    sample_weight.weight.into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_units(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.units (
-                unit_id BIGINT PRIMARY KEY,
-                unit_name VARCHAR,
-                lang_id BIGINT,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("units", "raw")?;
-        for (_, unit) in self.unit.iter() {
-            appender.append_row(duckdb::params![unit.id, unit.value, unit.lang_id])?;
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "units",
+            vec![
+                SinkColumn::primary_key("unit_id", SinkColumnType::BigInt),
+                SinkColumn::new("unit_name", SinkColumnType::Varchar),
+                SinkColumn::new("lang_id", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.unit.values().map(|unit| {
+                Ok(vec![
+                    unit.id.into(),
+                    unit.name.clone().into(),
+                    unit.lang_id.into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_memo_objects(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.memo_objects (
-                object_id BIGINT,
-                column_id BIGINT,
-                value VARCHAR,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("memo_objects", "raw")?;
-        for memo in &self.memo_object {
-            appender.append_row(duckdb::params![memo.object_id, memo.column_id, memo.value])?;
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "memo_objects",
+            vec![
+                SinkColumn::new("object_id", SinkColumnType::BigInt),
+                SinkColumn::new("column_id", SinkColumnType::BigInt),
+                SinkColumn::new("value", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self
+                .memo_object
+                .iter()
+                .filter(|memo| DimensionTrim::keeps_object(trim, memo.object_id))
+                .map(|memo| {
+                    Ok(vec![
+                        memo.object_id.into(),
+                        memo.column_id.into(),
+                        memo.value.clone().into(),
+                    ])
+                }),
+        )
     }
 
     fn populate_table_custom_columns(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.custom_columns (
-                column_id BIGINT PRIMARY KEY,
-                name VARCHAR,
-                position BIGINT,
-                class_id BIGINT,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("custom_columns", "raw")?;
-        for (_, column) in self.custom_column.iter() {
-            appender.append_row(duckdb::params![
-                column.column_id,
-                column.name,
-                column.position,
-                column.class_id
-            ])?;
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "custom_columns",
+            vec![
+                SinkColumn::primary_key("column_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("position", SinkColumnType::BigInt),
+                SinkColumn::new("class_id", SinkColumnType::BigInt),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.custom_column.values().map(|column| {
+                Ok(vec![
+                    column.column_id.into(),
+                    column.name.clone().into(),
+                    column.position.into(),
+                    column.class_id.into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_attribute_data(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.attribute_data (
-                object_id BIGINT,
-                attribute_id BIGINT,
-                value VARCHAR,
-              );
-              ",
-        )?;
-        let mut appender = con.appender_to_db("attribute_data", "raw")?;
-        for (_, data) in self.attribute_data.iter() {
-            if let Some(object_id) = data.object_id {
-                appender.append_row(duckdb::params![object_id, data.attribute_id, data.value])?;
-            }
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "attribute_data",
+            vec![
+                SinkColumn::new("object_id", SinkColumnType::BigInt),
+                SinkColumn::new("attribute_id", SinkColumnType::BigInt),
+                SinkColumn::new("value", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.attribute_data.values().filter_map(|data| {
+                let object_id = data.object_id?;
+                DimensionTrim::keeps_object(trim, object_id).then(|| {
+                    Ok(vec![
+                        object_id.into(),
+                        data.attribute_id.into(),
+                        data.value.into(),
+                    ])
+                })
+            }),
+        )
     }
 
     fn populate_table_attributes(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch(
-            "
-              CREATE TABLE raw.attributes (
-                attribute_id BIGINT PRIMARY KEY,
-                name VARCHAR,
-                lang_id BIGINT,
-                class_id BIGINT,
-                description VARCHAR,
-              );
-              ",
-        )?;
-
-        let mut appender = con.appender_to_db("attributes", "raw")?;
-        for (_, attribute) in self.attribute.iter() {
-            appender.append_row(duckdb::params![
-                attribute.attribute_id,
-                attribute.name,
-                attribute.lang_id,
-                attribute.class_id,
-                attribute.description
-            ])?;
-        }
-        appender.flush()?;
-
-        Ok(())
+        let table = SinkTable::raw(
+            "attributes",
+            vec![
+                SinkColumn::primary_key("attribute_id", SinkColumnType::BigInt),
+                SinkColumn::new("name", SinkColumnType::Varchar),
+                SinkColumn::new("lang_id", SinkColumnType::BigInt),
+                SinkColumn::new("class_id", SinkColumnType::BigInt),
+                SinkColumn::new("description", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+        sink.append_rows(
+            &table,
+            &mut self.attribute.values().map(|attribute| {
+                Ok(vec![
+                    attribute.attribute_id.into(),
+                    attribute.name.clone().into(),
+                    attribute.lang_id.into(),
+                    attribute.class_id.into(),
+                    attribute.description.clone().into(),
+                ])
+            }),
+        )
     }
 
     fn populate_table_metadata(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
-        con.execute_batch("CREATE TABLE main.plexos2duckdb (\n  key TEXT,\n  value TEXT\n);")?;
-
-        let mut appender = con.appender("plexos2duckdb")?;
-        appender.append_row(duckdb::params!["plexos2duckdb_version", utils::version()])?;
-        appender.append_row(duckdb::params![
-            "duckdb_file_created_at",
-            chrono::Utc::now().to_string()
-        ])?;
-        appender.append_row(duckdb::params!["plexos_file", self.file.to_str()])?;
-        appender.append_row(duckdb::params!["model_name", self.model_name])?;
-
+        let table = SinkTable::new(
+            "main",
+            "plexos2duckdb",
+            vec![
+                SinkColumn::new("key", SinkColumnType::Varchar),
+                SinkColumn::new("value", SinkColumnType::Varchar),
+            ],
+        );
+        sink.create_dimension_table(&table)?;
+
+        let mut rows = vec![
+            ("plexos2duckdb_version", Some(utils::version().to_string())),
+            ("schema_version", Some(SCHEMA_VERSION.to_string())),
+            (
+                "duckdb_file_created_at",
+                Some(chrono::Utc::now().to_string()),
+            ),
+            ("plexos_file", self.file.to_str().map(str::to_string)),
+            ("model_name", Some(self.model_name.clone())),
+        ];
         if let Some(log) = self.simulation_log.as_ref() {
-            appender.append_row(duckdb::params!["simulation_log", log])?;
+            rows.push(("simulation_log", Some(log.clone())));
         }
         if let Some(run_stats) = self.run_stats.as_ref() {
-            appender.append_row(duckdb::params!["run_stats", run_stats])?;
+            rows.push(("run_stats", Some(run_stats.clone())));
         }
-        appender.flush()?;
-        Ok(())
+        sink.append_rows(
+            &table,
+            &mut rows
+                .into_iter()
+                .map(|(key, value)| Ok(vec![key.to_string().into(), value.into()])),
+        )
     }
 
     fn populate_table_timestamps_block(
         &self,
-        con: &mut duckdb::Connection,
-        _progress: &mut Option<&mut dyn FnMut(DuckdbProgress)>,
+        sink: &mut dyn OutputSink,
+        _trim: Option<&DimensionTrim>,
     ) -> Result<()> {
         for (name, values) in self.timestamp_block.iter() {
-            con.execute_batch(&format!(
-                "
-                  CREATE TABLE raw.timestamp_block_{name} (
-                    interval_id BIGINT,
-                    datetime TIMESTAMP,
-                  );
-                ",
-            ))?;
-
-            let mut appender = con.appender_to_db(&format!("timestamp_block_{name}"), "raw")?;
-            for (value, interval_id) in values.iter() {
-                appender.append_row(duckdb::params![interval_id, value])?;
-            }
-            appender.flush()?;
+            let table = SinkTable::raw(
+                format!("timestamp_block_{name}"),
+                vec![
+                    SinkColumn::new("interval_id", SinkColumnType::BigInt),
+                    SinkColumn::new("datetime", SinkColumnType::Timestamp),
+                ],
+            );
+            sink.create_dimension_table(&table)?;
+            sink.append_rows(
+                &table,
+                &mut values.iter().map(|(value, interval_id)| {
+                    Ok(vec![
+                        (*interval_id).into(),
+                        duckdb::types::Value::Timestamp(
+                            duckdb::types::TimeUnit::Microsecond,
+                            value.timestamp_micros(),
+                        ),
+                    ])
+                }),
+            )?;
         }
 
         Ok(())
@@ -3832,149 +6144,239 @@ impl SolutionDataset {
     fn create_report_views(
         &self,
         con: &mut duckdb::Connection,
-        data_table_name_pattern: Option<&regex::Regex>,
+        selection: &DataSelection<'_>,
     ) -> Result<()> {
-        con.execute_batch("CREATE SCHEMA IF NOT EXISTS report;")?;
+        con.execute_batch(&self.report_views_sql(selection, ViewDialect::DuckDb)?)?;
+        Ok(())
+    }
 
-        for table_name in self.table_key_index_mapping.keys() {
-            if !Self::data_table_name_matches(table_name, data_table_name_pattern) {
-                continue;
-            }
+    fn report_views_sql(
+        &self,
+        selection: &DataSelection<'_>,
+        dialect: ViewDialect,
+    ) -> Result<String> {
+        let mut tables = Vec::new();
+        for (source_table_name, _) in selection.tables {
+            let downsampled_table_name = selection
+                .downsample
+                .and_then(|downsample| downsample.table_name(source_table_name));
+            let has_timestamps = downsampled_table_name.is_some()
+                || self
+                    .timestamp_block
+                    .contains_key(sink::timestamp_block_name(source_table_name).as_str());
+            tables.push((
+                downsampled_table_name.unwrap_or_else(|| source_table_name.clone()),
+                has_timestamps,
+            ));
+        }
+        Self::report_views_sql_for_tables(&tables, dialect)
+    }
 
-            let phase_name = table_name
-                .split("__")
-                .next()
-                .ok_or_else(|| eyre!("Phase name not found"))?;
-            let period_name = table_name
-                .split("__")
-                .nth(1)
-                .ok_or_else(|| eyre!("Period name not found"))?;
-            let property_name = table_name
-                .split("__")
-                .nth(3)
-                .ok_or_else(|| eyre!("Property name not found"))?;
+    /// `report` views over `(data table, has timestamp block)` pairs.
+    fn report_views_sql_for_tables(
+        tables: &[(String, bool)],
+        dialect: ViewDialect,
+    ) -> Result<String> {
+        let mut sql = dialect.create_schema("report");
+
+        for (table_name, has_timestamps) in tables {
+            let table_name = table_name.as_str();
+            let phase_name =
+                table_name
+                    .split("__")
+                    .next()
+                    .ok_or_else(|| Error::InvalidDataTableName {
+                        table_name: table_name.to_string(),
+                    })?;
+            let period_name =
+                table_name
+                    .split("__")
+                    .nth(1)
+                    .ok_or_else(|| Error::InvalidDataTableName {
+                        table_name: table_name.to_string(),
+                    })?;
+            let property_name =
+                table_name
+                    .split("__")
+                    .nth(3)
+                    .ok_or_else(|| Error::InvalidDataTableName {
+                        table_name: table_name.to_string(),
+                    })?;
             let timestamp_block_name = format!("{phase_name}__{period_name}");
-            let (timestamp_select, timestamp_join, timestamp_order) = if self
-                .timestamp_block
-                .contains_key(timestamp_block_name.as_str())
-            {
+            let (timestamp_select, timestamp_join, timestamp_order) = if *has_timestamps {
                 (
                     "p.datetime AS timestamp,
                   p.interval_length AS interval_length,"
                         .to_string(),
                     format!(
-                        "LEFT JOIN processed.timestamp_block_{timestamp_block_name} p ON d.block_id = p.block_id"
+                        "LEFT JOIN {} p ON d.block_id = p.block_id{}",
+                        dialect.table(
+                            "processed",
+                            &format!("timestamp_block_{timestamp_block_name}")
+                        ),
+                        dialect.scenario_join("d.scenario", "p.scenario")
                     ),
                     "p.datetime",
                 )
             } else {
                 (
-                    "NULL::TIMESTAMP AS timestamp,
-                  NULL::BIGINT AS interval_length,"
+                    "CAST(NULL AS TIMESTAMP) AS timestamp,
+                  CAST(NULL AS BIGINT) AS interval_length,"
                         .to_string(),
                     String::new(),
                     "d.block_id",
                 )
             };
-            con.execute_batch(&format!(
+            sql.push_str(&format!(
                 "
-                CREATE VIEW report.\"{table_name}\" AS SELECT
-                  d.band_id AS band,
+                CREATE VIEW {report_view} AS SELECT
+                  {scenario}d.band_id AS band,
                   s.sample_name,
                   m.child_name AS name,
                   m.child_category AS category,
                   {timestamp_select}
                   d.value AS \"{property_name}\",
-                  pr.unit AS unit,
+                  pr.unit AS unit
                   FROM
-                    data.\"{table_name}\" d
-                    LEFT JOIN raw.samples s ON d.sample_id = s.sample_id
-                    LEFT JOIN processed.memberships m ON d.membership_id = m.membership_id
+                    {data_table} d
+                    LEFT JOIN {samples} s ON d.sample_id = s.sample_id{sample_scenario}
+                    LEFT JOIN {memberships} m ON d.membership_id = m.membership_id{membership_scenario}
                     {timestamp_join}
-                    LEFT JOIN raw.keys k ON d.key_id = k.key_id
-                    LEFT JOIN processed.properties pr ON k.property_id = pr.property_id AND k.is_summary = pr.is_summary
+                    LEFT JOIN {keys} k ON d.key_id = k.key_id{key_scenario}
+                    LEFT JOIN {properties} pr ON k.property_id = pr.property_id AND k.is_summary = pr.is_summary
                   ORDER BY
-                    d.band_id,
+                    {scenario}d.band_id,
                     s.sample_id,
                     m.membership_id,
                     {timestamp_order}
                   ;
                   ",
-            ))?;
+                scenario = dialect.scenario_select("d.scenario"),
+                sample_scenario = dialect.scenario_join("d.scenario", "s.scenario"),
+                membership_scenario = dialect.scenario_join("d.scenario", "m.scenario"),
+                key_scenario = dialect.scenario_join("d.scenario", "k.scenario"),
+                report_view = dialect.table("report", table_name),
+                data_table = dialect.table("data", table_name),
+                samples = dialect.table("raw", "samples"),
+                memberships = dialect.table("processed", "memberships"),
+                keys = dialect.table("raw", "keys"),
+                properties = dialect.table("processed", "properties"),
+            ));
         }
 
+        Ok(sql)
+    }
+
+    fn create_processed_views(
+        &self,
+        con: &mut duckdb::Connection,
+        selection: &DataSelection<'_>,
+    ) -> Result<()> {
+        con.execute_batch(&self.processed_views_sql(selection, ViewDialect::DuckDb)?)?;
         Ok(())
     }
 
-    fn create_processed_views(&self, con: &mut duckdb::Connection) -> Result<()> {
-        con.execute_batch("CREATE SCHEMA IF NOT EXISTS processed;")?;
+    fn processed_views_sql(
+        &self,
+        selection: &DataSelection<'_>,
+        dialect: ViewDialect,
+    ) -> Result<String> {
+        let mut timestamp_block_names = self.timestamp_block.keys().cloned().collect::<Vec<_>>();
+        if let Some(downsample) = selection.downsample {
+            let table_names = selection
+                .tables
+                .iter()
+                .map(|(table_name, _)| table_name.clone())
+                .collect::<Vec<_>>();
+            for phase_name in self.downsampled_phases(&table_names, downsample) {
+                timestamp_block_names.push(format!("{phase_name}__{}", downsample.block_suffix()));
+            }
+        }
+        Ok(Self::processed_views_sql_for_blocks(
+            timestamp_block_names,
+            dialect,
+        ))
+    }
 
-        for (name, _) in self.timestamp_block.iter() {
+    /// `processed` views over the `raw` dimension tables and the named
+    /// timestamp blocks.
+    fn processed_views_sql_for_blocks(
+        mut timestamp_block_names: Vec<String>,
+        dialect: ViewDialect,
+    ) -> String {
+        let mut sql = dialect.create_schema("processed");
+        timestamp_block_names.sort();
+        for name in timestamp_block_names {
+            let view = dialect.table("processed", &format!("timestamp_block_{name}"));
+            let table = dialect.table("raw", &format!("timestamp_block_{name}"));
             if name.contains("Interval") {
-                con.execute_batch(&format!(
+                sql.push_str(&format!(
                     "
-                    CREATE VIEW processed.timestamp_block_{name} AS
+                    CREATE VIEW {view} AS
                       SELECT
-                          interval_id AS block_id,
+                          {scenario}interval_id AS block_id,
                           MIN(datetime) AS datetime,
                           COUNT(*) AS interval_length
                       FROM
-                          raw.timestamp_block_{name}
+                          {table}
                       GROUP BY
-                          interval_id;
+                          {scenario}interval_id;
                     ",
-                ))?;
+                    scenario = dialect.scenario_select("scenario"),
+                ));
             } else {
-                con.execute_batch(&format!(
+                sql.push_str(&format!(
                     "
-                    CREATE VIEW processed.timestamp_block_{name} AS
+                    CREATE VIEW {view} AS
                       SELECT
-                          ROW_NUMBER() OVER () AS block_id,
+                          {scenario}{row_number} AS block_id,
                           datetime,
-                          1 AS interval_length,
+                          1 AS interval_length
                       FROM
-                          raw.timestamp_block_{name};
+                          {table};
                     ",
-                ))?;
+                    scenario = dialect.scenario_select("scenario"),
+                    row_number = dialect.insertion_row_number(),
+                ));
             }
         }
 
-        con.execute_batch(
+        sql.push_str(&format!(
             "
 
-        CREATE VIEW processed.classes AS
+        CREATE VIEW {processed_classes} AS
           SELECT
             c.class_id,
             c.name AS class,
             cg.name AS class_group
-          FROM raw.classes c
-          LEFT JOIN raw.class_groups cg
+          FROM {classes} c
+          LEFT JOIN {class_groups} cg
             ON c.class_group_id = cg.class_group_id;
 
-        CREATE VIEW processed.objects AS
+        CREATE VIEW {processed_objects} AS
           SELECT
-            o.object_id AS id,
+            {object_scenario}o.object_id AS id,
             o.name AS name,
             cat.name AS category,
             c.class_group AS class_group,
             c.class AS class
-          FROM raw.objects o
-          JOIN processed.classes c
+          FROM {objects} o
+          JOIN {processed_classes} c
             ON o.class_id = c.class_id
-          JOIN raw.categories cat
-            ON o.category_id = cat.category_id;
+          JOIN {categories} cat
+            ON o.category_id = cat.category_id{category_scenario};
 
-        CREATE VIEW processed.properties AS
+        CREATE VIEW {processed_properties} AS
             SELECT
               p.property_id,
               false AS is_summary,
               c.name AS collection,
               p.name AS property,
-              u.unit_name AS unit,
-            FROM raw.properties p
-            LEFT JOIN raw.collections c
+              u.unit_name AS unit
+            FROM {properties} p
+            LEFT JOIN {collections} c
               ON p.collection_id = c.collection_id
-            LEFT JOIN raw.units u
+            LEFT JOIN {units} u
               ON p.unit_id = u.unit_id
           UNION ALL
             SELECT
@@ -3982,16 +6384,16 @@ impl SolutionDataset {
               true AS is_summary,
               c.name AS collection,
               p.summary_name AS property,
-              u.unit_name AS unit,
-            FROM raw.properties p
-            LEFT JOIN raw.collections c
+              u.unit_name AS unit
+            FROM {properties} p
+            LEFT JOIN {collections} c
               ON p.collection_id = c.collection_id
-            LEFT JOIN raw.units u
+            LEFT JOIN {units} u
               ON p.summary_unit_id = u.unit_id;
 
-        CREATE VIEW processed.memberships AS
+        CREATE VIEW {processed_memberships} AS
           SELECT
-            m.membership_id membership_id,
+            {membership_scenario}m.membership_id membership_id,
             m.parent_id parent_id,
             m.child_id child_id,
             c.name collection,
@@ -4003,25 +6405,42 @@ impl SolutionDataset {
             ch.class child_class,
             ch.class_group child_group,
             ch.category child_category,
-            m.kind kind,
-          FROM raw.memberships m
-          JOIN raw.collections c
+            m.kind kind
+          FROM {memberships} m
+          JOIN {collections} c
             ON c.collection_id = m.collection_id
-          JOIN processed.objects p
-            ON p.id = m.parent_id
-          JOIN processed.objects ch
-            ON ch.id = m.child_id
+          JOIN {processed_objects} p
+            ON p.id = m.parent_id{parent_scenario}
+          JOIN {processed_objects} ch
+            ON ch.id = m.child_id{child_scenario};
           ",
-        )?;
-
-        Ok(())
+            object_scenario = dialect.scenario_select("o.scenario"),
+            category_scenario = dialect.scenario_join("o.scenario", "cat.scenario"),
+            membership_scenario = dialect.scenario_select("m.scenario"),
+            parent_scenario = dialect.scenario_join("m.scenario", "p.scenario"),
+            child_scenario = dialect.scenario_join("m.scenario", "ch.scenario"),
+            processed_classes = dialect.table("processed", "classes"),
+            processed_objects = dialect.table("processed", "objects"),
+            processed_properties = dialect.table("processed", "properties"),
+            processed_memberships = dialect.table("processed", "memberships"),
+            classes = dialect.table("raw", "classes"),
+            class_groups = dialect.table("raw", "class_groups"),
+            objects = dialect.table("raw", "objects"),
+            categories = dialect.table("raw", "categories"),
+            properties = dialect.table("raw", "properties"),
+            collections = dialect.table("raw", "collections"),
+            units = dialect.table("raw", "units"),
+            memberships = dialect.table("raw", "memberships"),
+        ));
+
+        sql
     }
 
     fn collection_name(&self, collection_id: i64) -> Result<String> {
-        let collection = self
-            .collection
-            .get(&collection_id)
-            .ok_or(eyre!("Collection not found for {collection_id}"))?;
+        let collection = self.collection.get(&collection_id).ok_or(Error::NotFound {
+            entity: "collection",
+            id: collection_id,
+        })?;
         let collection_name = collection.name.clone();
 
         let prefix = if let Some(name) = collection.complement_name.as_deref() {
@@ -4029,7 +6448,10 @@ impl SolutionDataset {
         } else {
             self.class
                 .get(&collection.parent_class_id)
-                .ok_or_else(|| eyre!("Parent class not found"))?
+                .ok_or(Error::NotFound {
+                    entity: "class",
+                    id: collection.parent_class_id,
+                })?
                 .name
                 .as_str()
         };
@@ -4037,22 +6459,36 @@ impl SolutionDataset {
         Ok(format!("{prefix}_{collection_name}"))
     }
 
-    fn object(&self, object_id: i64) -> Result<&Object> {
+    /// Object by id.
+    pub fn object(&self, object_id: i64) -> Result<&Object> {
         self.object
             .get(&object_id)
-            .ok_or_else(|| eyre!("Object not found for {object_id}"))
+            .ok_or(Error::NotFound {
+                entity: "object",
+                id: object_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn category(&self, category_id: i64) -> Result<&Category> {
+    /// Category by id.
+    pub fn category(&self, category_id: i64) -> Result<&Category> {
         self.category
             .get(&category_id)
-            .ok_or_else(|| eyre!("Category not found for {category_id}"))
+            .ok_or(Error::NotFound {
+                entity: "category",
+                id: category_id,
+            })
+            .map_err(Into::into)
     }
 
     fn attribute(&self, attribute_id: i64) -> Result<&Attribute> {
         self.attribute
             .get(&attribute_id)
-            .ok_or_else(|| eyre!("Attribute not found for {attribute_id}"))
+            .ok_or(Error::NotFound {
+                entity: "attribute",
+                id: attribute_id,
+            })
+            .map_err(Into::into)
     }
 
     fn object_name(&self, object_id: i64) -> Result<String> {
@@ -4063,67 +6499,114 @@ impl SolutionDataset {
         Ok(format!("{}_{}_{}", class.name, category.name, object.name))
     }
 
-    fn membership(&self, membership_id: i64) -> Result<&Membership> {
+    /// Membership by id.
+    pub fn membership(&self, membership_id: i64) -> Result<&Membership> {
         self.membership
             .get(&membership_id)
-            .ok_or_else(|| eyre!("Membership not found for {membership_id}"))
+            .ok_or(Error::NotFound {
+                entity: "membership",
+                id: membership_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn class(&self, class_id: i64) -> Result<&Class> {
+    /// Class by id.
+    pub fn class(&self, class_id: i64) -> Result<&Class> {
         self.class
             .get(&class_id)
-            .ok_or_else(|| eyre!("Class not found for {class_id}"))
+            .ok_or(Error::NotFound {
+                entity: "class",
+                id: class_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn collection(&self, collection_id: i64) -> Result<&Collection> {
+    /// Collection by id.
+    pub fn collection(&self, collection_id: i64) -> Result<&Collection> {
         self.collection
             .get(&collection_id)
-            .ok_or_else(|| eyre!("Collection not found for {collection_id}"))
+            .ok_or(Error::NotFound {
+                entity: "collection",
+                id: collection_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn property(&self, property_id: i64) -> Result<&Property> {
+    /// Property by id.
+    pub fn property(&self, property_id: i64) -> Result<&Property> {
         self.property
             .get(&property_id)
-            .ok_or_else(|| eyre!("Property not found for {property_id}"))
+            .ok_or(Error::NotFound {
+                entity: "property",
+                id: property_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn unit(&self, unit_id: i64) -> Result<&Unit> {
+    /// Unit by id.
+    pub fn unit(&self, unit_id: i64) -> Result<&Unit> {
         self.unit
             .get(&unit_id)
-            .ok_or_else(|| eyre!("Unit not found for {unit_id}"))
+            .ok_or(Error::NotFound {
+                entity: "unit",
+                id: unit_id,
+            })
+            .map_err(Into::into)
     }
 
     fn band(&self, band_id: i64) -> Result<&Band> {
         self.band
             .get(&band_id)
-            .ok_or_else(|| eyre!("Band not found for {band_id}"))
+            .ok_or(Error::NotFound {
+                entity: "band",
+                id: band_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn sample(&self, sample_id: i64) -> Result<&Sample> {
+    /// Sample by id.
+    pub fn sample(&self, sample_id: i64) -> Result<&Sample> {
         self.sample
             .get(&sample_id)
-            .ok_or_else(|| eyre!("Sample not found for {sample_id}"))
+            .ok_or(Error::NotFound {
+                entity: "sample",
+                id: sample_id,
+            })
+            .map_err(Into::into)
     }
 
     fn sample_weight(&self, sample_id: i64) -> Result<&SampleWeight> {
         self.sample_weight
             .get(&sample_id)
-            .ok_or_else(|| eyre!("Sample weight not found for {sample_id}"))
+            .ok_or(Error::NotFound {
+                entity: "sample weight",
+                id: sample_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn timeslice(&self, timeslice_id: i64) -> Result<&Timeslice> {
+    /// Timeslice by id.
+    pub fn timeslice(&self, timeslice_id: i64) -> Result<&Timeslice> {
         self.timeslice
             .get(&timeslice_id)
-            .ok_or_else(|| eyre!("Timeslice not found for {timeslice_id}"))
+            .ok_or(Error::NotFound {
+                entity: "timeslice",
+                id: timeslice_id,
+            })
+            .map_err(Into::into)
     }
 
-    fn key_index(&self, key_id: i64) -> Result<&KeyIndex> {
+    /// Location of a key's values in its period data file.
+    pub fn key_index(&self, key_id: i64) -> Result<&KeyIndex> {
         self.key_index
             .get(&key_id)
-            .ok_or_else(|| eyre!("Key index not found for {key_id}"))
+            .ok_or(Error::MissingKeyIndex { key_id })
+            .map_err(Into::into)
     }
 
-    fn membership_name(&self, membership_id: i64) -> Result<String> {
+    /// `collection_parentclass_childclass_parent_child` name of a membership, as
+    /// used for the `membership_name` columns of the processed views.
+    pub fn membership_name(&self, membership_id: i64) -> Result<String> {
         let membership = self.membership(membership_id)?;
         let collection_name = self.collection_name(membership.collection_id)?;
         let child_class = self.class(membership.child_class_id)?;
@@ -4143,100 +6626,451 @@ impl SolutionDataset {
     fn interval(&self, interval_id: i64) -> Result<PeriodType> {
         self.period
             .get("interval")
-            .ok_or_else(|| eyre!("Interval not found"))?
-            .get(&interval_id)
+            .and_then(|periods| periods.get(&interval_id))
             .cloned()
-            .ok_or_else(|| eyre!("Interval not found for {interval_id}"))
+            .ok_or(Error::NotFound {
+                entity: "interval",
+                id: interval_id,
+            })
+            .map_err(Into::into)
     }
 
     fn day(&self, day_id: i64) -> Result<PeriodType> {
         self.period
             .get("day")
-            .ok_or_else(|| eyre!("Day not found"))?
-            .get(&day_id)
+            .and_then(|periods| periods.get(&day_id))
+            .cloned()
+            .ok_or(Error::NotFound {
+                entity: "day",
+                id: day_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn week(&self, week_id: i64) -> Result<PeriodType> {
+        self.period
+            .get("week")
+            .and_then(|periods| periods.get(&week_id))
+            .cloned()
+            .ok_or(Error::NotFound {
+                entity: "week",
+                id: week_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn month(&self, month_id: i64) -> Result<PeriodType> {
+        self.period
+            .get("month")
+            .and_then(|periods| periods.get(&month_id))
+            .cloned()
+            .ok_or(Error::NotFound {
+                entity: "month",
+                id: month_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn year(&self, fiscal_year_id: i64) -> Result<PeriodType> {
+        self.period
+            .get("year")
+            .and_then(|periods| periods.get(&fiscal_year_id))
+            .cloned()
+            .ok_or(Error::NotFound {
+                entity: "year",
+                id: fiscal_year_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn hour(&self, day_id: i64) -> Result<PeriodType> {
+        self.period
+            .get("hour")
+            .and_then(|periods| periods.get(&day_id))
+            .cloned()
+            .ok_or(Error::NotFound {
+                entity: "hour",
+                id: day_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn quarter(&self, quarter_id: i64) -> Result<PeriodType> {
+        self.period
+            .get("quarter")
+            .and_then(|periods| periods.get(&quarter_id))
             .cloned()
-            .ok_or_else(|| eyre!("Day not found for {day_id}"))
+            .ok_or(Error::NotFound {
+                entity: "quarter",
+                id: quarter_id,
+            })
+            .map_err(Into::into)
+    }
+
+    fn phase_name(&self, phase_id: i64) -> &str {
+        match phase_id {
+            1 => "LT",
+            2 => "PASA",
+            3 => "MT",
+            4 => "ST",
+            _ => "Unknown",
+        }
+    }
+
+    fn period_name(&self, period_id: i64) -> &str {
+        match period_id {
+            0 => "Interval",
+            1 => "Day",
+            2 => "Week",
+            3 => "Month",
+            4 => "Year",
+            6 => "Hour",
+            7 => "Quarter",
+            _ => "Unknown",
+        }
+    }
+
+    fn is_object(&self, collection_id: i64) -> Result<bool> {
+        let collection = self.collection(collection_id)?;
+        let class = self.class(collection.parent_class_id)?;
+        Ok(class.name == "System")
+    }
+
+    /// Key by id.
+    pub fn key(&self, key_id: i64) -> Result<&Key> {
+        self.key
+            .get(&key_id)
+            .ok_or(Error::MissingKey { key_id })
+            .map_err(Into::into)
+    }
+
+    /// Name of the model the solution belongs to.
+    pub fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    /// Models in file order.
+    pub fn models(&self) -> impl Iterator<Item = &Model> {
+        self.model.values()
+    }
+
+    /// Classes in file order.
+    pub fn classes(&self) -> impl Iterator<Item = &Class> {
+        self.class.values()
+    }
+
+    /// Class with the given name, e.g. `Generator`.
+    pub fn class_by_name(&self, name: &str) -> Option<&Class> {
+        self.class.values().find(|class| class.name == name)
+    }
+
+    /// Categories in file order.
+    pub fn categories(&self) -> impl Iterator<Item = &Category> {
+        self.category.values()
+    }
+
+    /// Objects in file order.
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.object.values()
+    }
+
+    /// Objects of one class.
+    pub fn objects_by_class(&self, class_id: i64) -> impl Iterator<Item = &Object> {
+        self.object
+            .values()
+            .filter(move |object| object.class_id == class_id)
+    }
+
+    /// Collections in file order. Collection names are only unique together
+    /// with their parent class.
+    pub fn collections(&self) -> impl Iterator<Item = &Collection> {
+        self.collection.values()
+    }
+
+    /// Memberships of one collection.
+    pub fn memberships_by_collection(
+        &self,
+        collection_id: i64,
+    ) -> impl Iterator<Item = &Membership> {
+        self.membership
+            .values()
+            .filter(move |membership| membership.collection_id == collection_id)
+    }
+
+    /// Properties in file order.
+    pub fn properties(&self) -> impl Iterator<Item = &Property> {
+        self.property.values()
+    }
+
+    /// Unit of a property, using the summary unit for summary keys.
+    pub fn property_unit(&self, property_id: i64, summary: bool) -> Result<&Unit> {
+        let property = self.property(property_id)?;
+        let unit_id = if summary {
+            property.summary_unit_id
+        } else {
+            property.unit_id
+        };
+        self.unit(unit_id)
+    }
+
+    /// Units in file order.
+    pub fn units(&self) -> impl Iterator<Item = &Unit> {
+        self.unit.values()
+    }
+
+    /// Samples in file order.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.sample.values()
     }
 
-    fn week(&self, week_id: i64) -> Result<PeriodType> {
-        self.period
-            .get("week")
-            .ok_or_else(|| eyre!("Week not found"))?
-            .get(&week_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Week not found for {week_id}"))
+    /// Names of the `phase__period__collection__property` data tables, sorted.
+    pub fn data_table_names(&self) -> Vec<&str> {
+        let mut names = self
+            .table_key_index_mapping
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
     }
 
-    fn month(&self, month_id: i64) -> Result<PeriodType> {
-        self.period
-            .get("month")
-            .ok_or_else(|| eyre!("Month not found"))?
-            .get(&month_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Month not found for {month_id}"))
+    /// Keys stored in a data table, in key index order.
+    pub fn data_table_keys(&self, table_name: &str) -> Result<Vec<&Key>> {
+        let key_ids = self
+            .table_key_index_mapping
+            .get(table_name)
+            .ok_or_else(|| Error::MissingDataTable {
+                table_name: table_name.to_string(),
+            })?;
+        key_ids
+            .iter()
+            .map(|key_id| {
+                self.key
+                    .get(key_id)
+                    .ok_or(Error::MissingKey { key_id: *key_id })
+                    .map_err(Into::into)
+            })
+            .collect()
     }
 
-    fn year(&self, fiscal_year_id: i64) -> Result<PeriodType> {
-        self.period
-            .get("year")
-            .ok_or_else(|| eyre!("Year not found"))?
-            .get(&fiscal_year_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Year not found for {fiscal_year_id}"))
+    /// Unit name of a data table.
+    pub fn data_table_unit(&self, table_name: &str) -> Option<&str> {
+        self.table_units_mapping
+            .get(table_name)
+            .map(|(unit, _)| unit.as_str())
     }
 
-    fn hour(&self, day_id: i64) -> Result<PeriodType> {
-        self.period
-            .get("hour")
-            .ok_or_else(|| eyre!("Hour not found"))?
-            .get(&day_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Hour not found for {day_id}"))
+    /// Values of the keys picked by `selector`, a data table name or a
+    /// `(collection, property, object)` tuple, read without going through DuckDB.
+    pub fn series<'s>(&self, selector: impl Into<SeriesSelector<'s>>) -> Result<Series<'_>> {
+        let key_ids = match selector.into() {
+            SeriesSelector::Table(table_name) => self
+                .table_key_index_mapping
+                .get(table_name)
+                .cloned()
+                .ok_or_else(|| Error::MissingDataTable {
+                    table_name: table_name.to_string(),
+                })?,
+            SeriesSelector::Object {
+                collection,
+                property,
+                object,
+            } => {
+                let mut key_ids = Vec::new();
+                for table_name in self.data_table_names() {
+                    for key_id in self.table_key_index_mapping[table_name].iter().copied() {
+                        let key = self.key(key_id)?;
+                        let membership = self.membership(key.membership_id)?;
+                        let key_property = self.property(key.property_id)?;
+                        let property_name = if key.is_summary {
+                            key_property.summary_name()
+                        } else {
+                            key_property.property_name()
+                        };
+                        if property_name == property
+                            && self.collection(membership.collection_id)?.name == collection
+                            && self.object(membership.child_object_id)?.name == object
+                        {
+                            key_ids.push(key_id);
+                        }
+                    }
+                }
+                if key_ids.is_empty() {
+                    return Err(Error::MissingSeries {
+                        collection: collection.to_string(),
+                        property: property.to_string(),
+                        object: object.to_string(),
+                    }
+                    .into());
+                }
+                key_ids
+            },
+        };
+
+        Ok(Series {
+            dataset: self,
+            key_ids: key_ids.into_iter(),
+            current: None,
+            materialized_period_data: None,
+            block_datetimes: Default::default(),
+            values: Default::default(),
+        })
     }
 
-    fn quarter(&self, quarter_id: i64) -> Result<PeriodType> {
-        self.period
-            .get("quarter")
-            .ok_or_else(|| eyre!("Quarter not found"))?
-            .get(&quarter_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Quarter not found for {quarter_id}"))
+    /// Arrow reader over every value of a data table, read straight from the
+    /// period data.
+    pub fn data_table_reader(&self, table_name: &str) -> Result<DataTableReader<'_>> {
+        self.data_table_reader_for(DatasetRef::Borrowed(self), table_name)
     }
 
-    fn phase_name(&self, phase_id: i64) -> &str {
-        match phase_id {
-            1 => "LT",
-            2 => "PASA",
-            3 => "MT",
-            4 => "ST",
-            _ => "Unknown",
-        }
+    /// [`Self::data_table_reader`] for a shared dataset. The reader holds its own
+    /// `Arc`, so it is `'static` and can be handed to another thread or runtime.
+    pub fn shared_data_table_reader(
+        self: &Arc<Self>,
+        table_name: &str,
+    ) -> Result<DataTableReader<'static>> {
+        self.data_table_reader_for(DatasetRef::Shared(self.clone()), table_name)
     }
 
-    fn period_name(&self, period_id: i64) -> &str {
-        match period_id {
-            0 => "Interval",
-            1 => "Day",
-            2 => "Week",
-            3 => "Month",
-            4 => "Year",
-            6 => "Hour",
-            7 => "Quarter",
-            _ => "Unknown",
+    fn data_table_reader_for<'a>(
+        &self,
+        dataset: DatasetRef<'a>,
+        table_name: &str,
+    ) -> Result<DataTableReader<'a>> {
+        let key_ids = self
+            .table_key_index_mapping
+            .get(table_name)
+            .ok_or_else(|| Error::MissingDataTable {
+                table_name: table_name.to_string(),
+            })?;
+        let mut tasks = std::collections::VecDeque::with_capacity(key_ids.len());
+        for key_id in key_ids.iter().copied() {
+            let values = 0..self.key_index(key_id)?.length;
+            tasks.push_back(self.data_range_write_task(key_id, &values)?);
         }
+
+        Ok(DataTableReader {
+            dataset,
+            table_name: table_name.to_string(),
+            tasks,
+            materialized_period_data: None,
+            block_datetimes: None,
+        })
     }
 
-    fn is_object(&self, collection_id: i64) -> Result<bool> {
-        let collection = self.collection(collection_id)?;
-        let class = self.class(collection.parent_class_id)?;
-        Ok(class.name == "System")
+    /// Appends the report view dimension columns to a batch of one key.
+    fn data_batch_with_dimensions(
+        &self,
+        task: &DataRangeWriteTask,
+        batch: RecordBatch,
+        block_datetimes: &std::collections::HashMap<i64, chrono::DateTime<chrono::Utc>>,
+    ) -> Result<RecordBatch> {
+        let rows = batch.num_rows();
+        let sample_name = self
+            .sample
+            .get(&task.sample_id)
+            .and_then(|sample| sample.name.clone());
+        let child_object = self
+            .membership
+            .get(&task.membership_id)
+            .and_then(|membership| self.object.get(&membership.child_object_id));
+        let name = child_object.map(|object| object.name.clone());
+        let category = child_object
+            .and_then(|object| self.category.get(&object.category_id))
+            .map(|category| category.name.clone());
+        let unit = self
+            .key
+            .get(&task.key_id)
+            .and_then(|key| self.property_unit(key.property_id, key.is_summary).ok())
+            .map(|unit| unit.name.clone());
+
+        let first_block_id = i64::try_from(task.start_value_index)?
+            .checked_add(task.period_offset)
+            .and_then(|v| v.checked_add(1))
+            .ok_or(Error::Overflow {
+                quantity: "block_id",
+                key_id: task.key_id,
+            })?;
+        let timestamps = (first_block_id..)
+            .take(rows)
+            .map(|block_id| {
+                block_datetimes
+                    .get(&block_id)
+                    .map(|datetime| datetime.timestamp_micros())
+            })
+            .collect::<Vec<_>>();
+
+        let mut columns = batch.columns().to_vec();
+        columns.extend([
+            Arc::new(StringArray::from(vec![sample_name; rows])) as ArrayRef,
+            Arc::new(StringArray::from(vec![name; rows])),
+            Arc::new(StringArray::from(vec![category; rows])),
+            Arc::new(TimestampMicrosecondArray::from(timestamps).with_timezone("UTC")),
+            Arc::new(StringArray::from(vec![unit; rows])),
+        ]);
+        Ok(RecordBatch::try_new(
+            DATA_DIMENSIONS_RECORD_BATCH_SCHEMA.clone(),
+            columns,
+        )?)
     }
 
-    fn key(&self, key_id: i64) -> Result<Key> {
-        self.key
-            .get(&key_id)
-            .cloned()
-            .ok_or_else(|| eyre!("Key with {} not found", key_id))
+    /// Key count and row estimate of every data table, from the XML alone.
+    pub fn data_table_estimates(&self) -> Result<Vec<DataTableEstimate>> {
+        let key_filter = DataKeyFilter::default();
+        let tables = self.selected_data_tables(None, &key_filter, &DataTimeWindow::default())?;
+        let selection = DataSelection {
+            tables: &tables,
+            table_name_pattern: None,
+            key_filter: &key_filter,
+            time_window: DataTimeWindow::default(),
+            downsample: None,
+            cancel: None,
+        };
+        Ok(self
+            .build_data_table_plans(&selection)?
+            .into_iter()
+            .map(|plan| DataTableEstimate {
+                table_name: plan.table_name,
+                keys: plan.key_ids.len(),
+                estimated_rows: plan.estimated_values,
+            })
+            .collect())
+    }
+
+    /// BIN files holding the period data, by period type.
+    pub fn period_data_files(&self) -> Result<Vec<PeriodDataFile>> {
+        self.period_data
+            .iter()
+            .map(|(period_type_id, data)| {
+                let (storage, size_bytes, stored_size_bytes) = match data {
+                    PeriodData::File(file) => {
+                        let len = file.metadata()?.len();
+                        ("file", len, len)
+                    },
+                    PeriodData::ZipEntry(entry) => ("stored", entry.data_len, entry.data_len),
+                    PeriodData::CompressedZipEntry(entry) => {
+                        ("deflated", entry.data_len, entry.compressed_len)
+                    },
+                };
+                Ok(PeriodDataFile {
+                    period_type_id: *period_type_id,
+                    storage,
+                    size_bytes,
+                    stored_size_bytes,
+                })
+            })
+            .collect()
+    }
+
+    /// Names of the `phase__period` timestamp blocks, sorted.
+    pub fn timestamp_block_names(&self) -> Vec<&str> {
+        let mut names = self
+            .timestamp_block
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
     }
 
     pub fn print_summary(&self) {
@@ -4267,6 +7101,42 @@ impl SolutionDataset {
     }
 }
 
+/// Helper function to get text from a child element, returns any type T that implements FromStr
+fn get_child<T: std::str::FromStr>(node: &Node, tag_name: &str) -> Result<T>
+where
+    T::Err: std::fmt::Debug,
+{
+    let text = node
+        .children()
+        .find(|n| n.has_tag_name(tag_name))
+        .and_then(|n| n.text())
+        .ok_or_else(|| Error::MissingXmlElement {
+            tag: tag_name.to_string(),
+            element: node.tag_name().name().to_string(),
+        })?;
+    text.parse::<T>().map_err(|_| {
+        Error::InvalidXmlValue {
+            tag: tag_name.to_string(),
+            element: node.tag_name().name().to_string(),
+            value: text.to_string(),
+        }
+        .into()
+    })
+}
+
+fn parse_datetime_to_utc(input: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    // Try parsing with timezone first
+    if let Ok(dt_with_tz) = chrono::DateTime::parse_from_rfc3339(input) {
+        Ok(dt_with_tz.with_timezone(&chrono::Utc))
+    } else {
+        let naive = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S")?;
+        Ok(chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
+            naive,
+            chrono::Utc,
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4283,7 +7153,7 @@ mod tests {
               INSERT INTO numbers VALUES (1), (2), (3);
             ",
         )?;
-        SolutionDataset::persist_duckdb_database(con, stage_dir, &db_path)?;
+        SolutionDataset::persist_duckdb_database(con, stage_dir, &db_path, |_| Ok(()))?;
 
         Ok((output_dir, db_path))
     }
@@ -4348,11 +7218,8 @@ mod tests {
         ]
     }
 
-    fn write_file_period_data_database(
-        threads: usize,
-    ) -> Result<(tempfile::TempDir, std::path::PathBuf)> {
-        let output_dir = tempfile::TempDir::new()?;
-        let bin_path = output_dir.path().join("t_data_0.BIN");
+    fn file_period_dataset(output_dir: &std::path::Path) -> Result<SolutionDataset> {
+        let bin_path = output_dir.join("t_data_0.BIN");
         let mut bin_file = std::fs::File::create(&bin_path)?;
 
         let mut key = indexmap::IndexMap::new();
@@ -4416,8 +7283,10 @@ mod tests {
         }
         timestamp_block.insert("ST__Interval".to_string(), timestamps);
 
-        let dataset = SolutionDataset {
-            file: output_dir.path().join("source.xml"),
+        std::fs::write(output_dir.join("source.xml"), "")?;
+
+        Ok(SolutionDataset {
+            file: output_dir.join("source.xml"),
             model_name: "file-period-data".to_string(),
             key,
             key_index,
@@ -4428,7 +7297,20 @@ mod tests {
             timestamp_block,
             table_key_index_mapping,
             ..Default::default()
-        };
+        })
+    }
+
+    /// [`file_period_dataset`] in a new temporary directory.
+    pub(crate) fn file_period_fixture() -> Result<(tempfile::TempDir, SolutionDataset)> {
+        let output_dir = tempfile::TempDir::new()?;
+        let dataset = file_period_dataset(output_dir.path())?;
+        Ok((output_dir, dataset))
+    }
+
+    fn write_file_period_data_database(
+        threads: usize,
+    ) -> Result<(tempfile::TempDir, std::path::PathBuf)> {
+        let (output_dir, dataset) = file_period_fixture()?;
 
         let db_path = output_dir
             .path()
@@ -4451,7 +7333,7 @@ mod tests {
             let band_id = 300 + i64::try_from(table_idx)?;
             let membership_id = 400 + i64::try_from(table_idx)?;
             let period_offset = i64::try_from(table_idx)? * 10;
-            let table_ident = SolutionDataset::quote_ident(table_name);
+            let table_ident = quote_ident(table_name);
             let mut stmt = con.prepare(&format!(
                 "SELECT key_id, sample_id, band_id, membership_id, block_id, value
                  FROM data.{table_ident}
@@ -4484,6 +7366,178 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn duckdb_output_records_provenance_in_metadata_table() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        std::fs::write(output_dir.path().join("source.xml"), "abc")?;
+        let db_path = output_dir.path().join("provenance.duckdb");
+        dataset
+            .to_duckdb(&db_path)
+            .with_data_write_threads(2)
+            .with_data_table_name_pattern(regex::Regex::new("MetricA")?)
+            .with_metadata("team", "planning")
+            .run()?;
+
+        let con = duckdb::Connection::open(&db_path)?;
+        let metadata = con
+            .prepare("SELECT key, value FROM main.plexos2duckdb;")?
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<std::collections::HashMap<_, _>, _>>()?;
+        assert_eq!(
+            metadata["plexos_file_sha256"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(metadata["plexos_file_size_bytes"], "3");
+        assert!(!metadata["host"].is_empty());
+        assert_eq!(metadata["team"], "planning");
+
+        let options: serde_json::Value = serde_json::from_str(&metadata["convert_options"])?;
+        assert_eq!(options["data_write_threads"], 2);
+        assert_eq!(options["table_name_pattern"], "MetricA");
+        assert_eq!(
+            options["external_data_parquet_dir"],
+            serde_json::Value::Null
+        );
+
+        let step_timings: serde_json::Value = serde_json::from_str(&metadata["step_timings"])?;
+        let steps = step_timings
+            .as_array()
+            .ok_or_else(|| eyre!("step timings must be an array"))?
+            .iter()
+            .map(|step| step["step"].as_str().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(steps, SolutionDataset::duckdb_progress_step_plan());
+
+        let data_rows: i64 = con.query_row(
+            "SELECT COUNT(*) FROM data.ST__Interval__FileFixture__MetricA;",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(data_rows, 3);
+        let tables = con
+            .prepare(
+                "SELECT table_schema, table_name FROM information_schema.tables
+                 WHERE table_schema IN ('raw', 'data');",
+            )?
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut rows = 0i64;
+        for (schema, table_name) in &tables {
+            rows += con.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {}.{};",
+                    quote_ident(schema),
+                    quote_ident(table_name)
+                ),
+                [],
+                |row| row.get::<_, i64>(0),
+            )?;
+        }
+        assert_eq!(metadata["tables_written"], tables.len().to_string());
+        assert_eq!(metadata["rows_written"], rows.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_rejects_keys_written_by_plexos2duckdb() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        let db_path = output_dir.path().join("reserved.duckdb");
+        let err = dataset
+            .to_duckdb(&db_path)
+            .with_metadata("schema_version", "99")
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("schema_version"));
+        assert!(!db_path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn data_table_estimates_and_period_data_files_need_no_period_reads() -> Result<()> {
+        let (_output_dir, dataset) = file_period_fixture()?;
+
+        let estimates = dataset.data_table_estimates()?;
+        assert_eq!(
+            estimates
+                .iter()
+                .map(|table| (table.table_name.as_str(), table.keys, table.estimated_rows))
+                .collect::<Vec<_>>(),
+            file_period_table_specs()
+                .iter()
+                .map(|(table_name, _)| (*table_name, 1, 3))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            dataset.period_data_files()?,
+            vec![PeriodDataFile {
+                period_type_id: 0,
+                storage: "file",
+                size_bytes: 96,
+                stored_size_bytes: 96,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_upgrades_older_database_in_place() -> Result<()> {
+        let (_output_dir, db_path) = write_file_period_data_database(1)?;
+        let mut con = duckdb::Connection::open(&db_path)?;
+        assert_eq!(database_schema_version(&con)?, SCHEMA_VERSION);
+
+        // Shape of a database written before schema versions were recorded.
+        con.execute_batch(
+            "DELETE FROM main.plexos2duckdb WHERE key = 'schema_version';
+             ALTER TABLE raw.units DROP COLUMN lang_id;
+             ALTER TABLE raw.objects DROP COLUMN guid;
+             DROP TABLE raw.custom_columns;
+             DROP VIEW report.ST__Interval__FileFixture__MetricA;",
+        )?;
+        assert_eq!(database_schema_version(&con)?, 1);
+
+        let summary = migrate_database(&mut con)?;
+        assert_eq!(summary.from_version, 1);
+        assert_eq!(summary.to_version, SCHEMA_VERSION);
+        assert_eq!(
+            summary.created_tables,
+            vec!["raw.custom_columns".to_string()]
+        );
+        assert_eq!(
+            summary.added_columns,
+            vec![
+                "raw.objects.guid".to_string(),
+                "raw.units.lang_id".to_string()
+            ]
+        );
+        assert_eq!(database_schema_version(&con)?, SCHEMA_VERSION);
+
+        let report_rows: i64 = con.query_row(
+            "SELECT COUNT(*) FROM report.ST__Interval__FileFixture__MetricA;",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(report_rows, 3);
+        let views: i64 = con.query_row(
+            "SELECT COUNT(*) FROM information_schema.tables
+             WHERE table_schema IN ('processed', 'report') AND table_type = 'VIEW';",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(usize::try_from(views)?, summary.recreated_views);
+
+        assert_eq!(migrate_database(&mut con)?.recreated_views, 0);
+        con.execute_batch(&format!(
+            "UPDATE main.plexos2duckdb SET value = '{}' WHERE key = 'schema_version';",
+            SCHEMA_VERSION + 1
+        ))?;
+        assert!(migrate_database(&mut con).is_err());
+        Ok(())
+    }
+
     #[test]
     fn persist_database_renames_staging_file_into_place() -> Result<()> {
         let (_output_dir, db_path) = build_small_database()?;
@@ -4502,6 +7556,329 @@ mod tests {
         assert_file_period_data(&db_path)
     }
 
+    #[test]
+    fn hive_partition_values_keep_spaces_and_escape_reserved_characters() {
+        assert_eq!(
+            SolutionDataset::hive_partition_value("Units Generating"),
+            "Units Generating"
+        );
+        assert_eq!(
+            SolutionDataset::hive_partition_value("a/b=c%d?"),
+            "a%2Fb%3Dc%25d%3F"
+        );
+        assert_eq!(
+            SolutionDataset::hive_partition_value("Émissions\t"),
+            "Émissions%09"
+        );
+        assert_eq!(
+            SolutionDataset::hive_partition_value(""),
+            "__HIVE_DEFAULT_PARTITION__"
+        );
+    }
+
+    #[test]
+    fn external_hive_layout_partitions_parquet_files_by_table_name_parts() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        let db_path = output_dir.path().join("hive.duckdb");
+        let external_dir = output_dir.path().join("external");
+        dataset
+            .to_duckdb(&db_path)
+            .with_external_data_parquet_dir(&external_dir)
+            .with_external_data_parquet_layout(ExternalDataParquetLayout::Hive)
+            .run()?;
+
+        for (table_name, _) in file_period_table_specs() {
+            let property = table_name
+                .rsplit("__")
+                .next()
+                .expect("table name has parts");
+            let parquet_path = external_dir
+                .join("data")
+                .join("phase=ST")
+                .join("period=Interval")
+                .join("collection=FileFixture")
+                .join(format!("property={property}"))
+                .join("part-00001.parquet");
+            assert!(
+                parquet_path.is_file(),
+                "expected hive partitioned parquet file at {}",
+                parquet_path.display()
+            );
+        }
+
+        assert_file_period_data(&db_path)?;
+
+        let con = duckdb::Connection::open(&db_path)?;
+        let mut stmt = con.prepare(
+            "SELECT property, COUNT(*)
+             FROM main.plexos2duckdb_external_data
+             WHERE phase = 'ST' AND period = 'Interval' AND collection = 'FileFixture'
+             GROUP BY property
+             ORDER BY property;",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                ("MetricA".to_string(), 3),
+                ("MetricB".to_string(), 3),
+                ("MetricC".to_string(), 3),
+                ("MetricD".to_string(), 3),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parquet_output_writes_tables_manifest_and_views_without_duckdb_file() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        let parquet_dir = output_dir.path().join("dataset");
+        dataset
+            .to_duckdb(&parquet_dir)
+            .with_output_format(OutputFormat::Parquet)
+            .run()?;
+
+        for entry in std::fs::read_dir(output_dir.path())? {
+            let path = entry?.path();
+            assert_ne!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("duckdb"),
+                "unexpected DuckDB file {}",
+                path.display()
+            );
+        }
+        assert!(parquet_dir.join("plexos2duckdb.parquet").is_file());
+        assert!(parquet_dir.join("raw").join("objects.parquet").is_file());
+        assert!(
+            parquet_dir
+                .join("raw")
+                .join("timestamp_block_ST__Interval.parquet")
+                .is_file()
+        );
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(parquet_dir.join("manifest.json"))?)?;
+        assert_eq!(manifest["model_name"], "file-period-data");
+        assert_eq!(manifest["views_sql"], "views.sql");
+        let data_files = manifest["tables"]
+            .as_array()
+            .ok_or_else(|| eyre!("manifest tables must be an array"))?
+            .iter()
+            .filter(|table| table["schema"] == "data")
+            .map(|table| table["files"][0].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            data_files,
+            file_period_table_specs()
+                .iter()
+                .map(|(table_name, _)| format!("data/{table_name}/{table_name}.part-00001.parquet"))
+                .collect::<Vec<_>>()
+        );
+
+        let con = duckdb::Connection::open_in_memory()?;
+        con.execute_batch(&format!(
+            "SET file_search_path = '{}';",
+            SolutionDataset::sql_string_literal(&SolutionDataset::path_string(&parquet_dir))
+        ))?;
+        con.execute_batch(&std::fs::read_to_string(parquet_dir.join("views.sql"))?)?;
+        for (table_name, expected_values) in file_period_table_specs() {
+            let table_ident = quote_ident(table_name);
+            let values = con
+                .prepare(&format!(
+                    "SELECT value FROM data.{table_ident} ORDER BY block_id;"
+                ))?
+                .query_map([], |row| row.get::<_, f64>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            assert_eq!(values, expected_values);
+
+            let report_rows: i64 = con.query_row(
+                &format!("SELECT COUNT(*) FROM report.{table_ident};"),
+                [],
+                |row| row.get(0),
+            )?;
+            assert_eq!(report_rows, 3);
+        }
+        let model_name: String = con.query_row(
+            "SELECT value FROM main.plexos2duckdb WHERE key = 'model_name';",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(model_name, "file-period-data");
+        Ok(())
+    }
+
+    #[test]
+    fn sqlite_output_writes_raw_and_data_tables_with_views() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        let sqlite_path = output_dir.path().join("dataset.sqlite");
+        dataset
+            .to_duckdb(&sqlite_path)
+            .with_output_format(OutputFormat::Sqlite)
+            .run()?;
+
+        let con = rusqlite::Connection::open(&sqlite_path)?;
+        let keys: i64 = con.query_row("SELECT COUNT(*) FROM raw_keys;", [], |row| row.get(0))?;
+        assert_eq!(keys, 4);
+        for (table_name, expected_values) in file_period_table_specs() {
+            let values = con
+                .prepare(&format!(
+                    "SELECT value FROM {} ORDER BY block_id;",
+                    quote_ident(&format!("data_{table_name}"))
+                ))?
+                .query_map([], |row| row.get::<_, f64>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            assert_eq!(values, expected_values);
+
+            let report_rows: i64 = con.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {};",
+                    quote_ident(&format!("report_{table_name}"))
+                ),
+                [],
+                |row| row.get(0),
+            )?;
+            assert_eq!(report_rows, 3);
+        }
+        let (blocks, first_datetime): (i64, String) = con.query_row(
+            "SELECT COUNT(*), MIN(datetime) FROM processed_timestamp_block_ST__Interval;",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(blocks, 40);
+        assert_eq!(first_datetime, "2020-01-01 00:00:00");
+        let model_name: String = con.query_row(
+            "SELECT value FROM plexos2duckdb WHERE key = 'model_name';",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(model_name, "file-period-data");
+
+        assert!(
+            dataset
+                .to_duckdb(output_dir.path().join("downsampled.sqlite"))
+                .with_output_format(OutputFormat::Sqlite)
+                .with_downsample(Downsample::new(DownsamplePeriod::Day))
+                .run()
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn arrow_ipc_output_writes_compressed_files_and_manifest() -> Result<()> {
+        use duckdb::arrow::array::Array as _;
+
+        let (output_dir, dataset) = file_period_fixture()?;
+        let arrow_dir = output_dir.path().join("dataset");
+        dataset
+            .to_duckdb(&arrow_dir)
+            .with_output_format(OutputFormat::ArrowIpc)
+            .with_arrow_ipc_compression(ArrowIpcCompression::Zstd)
+            .run()?;
+
+        let read_file = |path: std::path::PathBuf| -> Result<Vec<RecordBatch>> {
+            let reader = arrow_ipc::reader::FileReader::try_new(std::fs::File::open(path)?, None)?;
+            Ok(reader.collect::<std::result::Result<Vec<_>, _>>()?)
+        };
+        let keys = read_file(arrow_dir.join("raw").join("keys.arrow"))?;
+        assert_eq!(keys.iter().map(RecordBatch::num_rows).sum::<usize>(), 4);
+        for (table_name, expected_values) in file_period_table_specs() {
+            let mut values = Vec::new();
+            for batch in read_file(arrow_dir.join("data").join(format!("{table_name}.arrow")))? {
+                let column = batch
+                    .column_by_name("value")
+                    .and_then(|column| column.as_any().downcast_ref::<Float64Array>())
+                    .ok_or_else(|| eyre!("value column must be Float64"))?;
+                values.extend(column.values().iter().copied());
+            }
+            assert_eq!(values, expected_values);
+        }
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(arrow_dir.join("manifest.json"))?)?;
+        assert_eq!(manifest["compression"], "zstd");
+        let tables = manifest["tables"]
+            .as_array()
+            .ok_or_else(|| eyre!("manifest tables must be an array"))?;
+        assert_eq!(tables[0]["file"], "plexos2duckdb.arrow");
+        let data_table = tables
+            .iter()
+            .find(|table| table["name"] == "ST__Interval__FileFixture__MetricB")
+            .ok_or_else(|| eyre!("data table missing from manifest"))?;
+        assert_eq!(
+            data_table["file"],
+            "data/ST__Interval__FileFixture__MetricB.arrow"
+        );
+        assert_eq!(data_table["rows"], 3);
+        assert_eq!(
+            data_table["timestamps"]["file"],
+            "raw/timestamp_block_ST__Interval.arrow"
+        );
+        assert_eq!(data_table["timestamps"]["block_id"], "interval_id");
+        Ok(())
+    }
+
+    #[test]
+    fn external_parquet_files_carry_table_semantics_in_footer_metadata() -> Result<()> {
+        let (output_dir, mut dataset) = file_period_fixture()?;
+        for (table_idx, (table_name, _)) in file_period_table_specs().into_iter().enumerate() {
+            dataset.table_units_mapping.insert(
+                table_name.to_string(),
+                ("MW".to_string(), i64::try_from(table_idx)? * 10),
+            );
+        }
+        let db_path = output_dir.path().join("footer.duckdb");
+        let external_dir = output_dir.path().join("external");
+        dataset
+            .to_duckdb(&db_path)
+            .with_external_data_parquet_dir(&external_dir)
+            .run()?;
+
+        let table_name = "ST__Interval__FileFixture__MetricB";
+        let parquet_path = external_dir
+            .join("data")
+            .join(table_name)
+            .join(format!("{table_name}.part-00001.parquet"));
+        let reader =
+            parquet::file::reader::SerializedFileReader::new(std::fs::File::open(&parquet_path)?)?;
+        let metadata = parquet::file::reader::FileReader::metadata(&reader)
+            .file_metadata()
+            .key_value_metadata()
+            .ok_or_else(|| eyre!("expected key-value metadata in {}", parquet_path.display()))?
+            .iter()
+            .filter_map(|kv| kv.value.clone().map(|value| (kv.key.clone(), value)))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let source_file = output_dir
+            .path()
+            .join("source.xml")
+            .to_string_lossy()
+            .into_owned();
+        for (key, expected) in [
+            ("table_name", table_name),
+            ("phase", "ST"),
+            ("period", "Interval"),
+            ("collection", "FileFixture"),
+            ("property", "MetricB"),
+            ("unit", "MW"),
+            ("period_offset", "10"),
+            ("model_name", "file-period-data"),
+            ("plexos_file", source_file.as_str()),
+            ("plexos2duckdb_version", utils::version()),
+        ] {
+            assert_eq!(
+                metadata.get(key).map(String::as_str),
+                Some(expected),
+                "{key}"
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn read_zip_archive_records_deflated_bin_entries_without_materializing() -> Result<()> {
         let temp_dir = tempfile::TempDir::new()?;
@@ -4523,7 +7900,7 @@ mod tests {
         zip_writer.finish()?;
 
         let (_xml_content, period_data) =
-            SolutionDataset::read_zip_archive(&zip_path, "", &mut None)?;
+            SolutionDataset::read_zip_archive(&zip_path, "", None, &mut None)?;
         let data = period_data
             .get(&0)
             .ok_or_else(|| eyre!("expected period data for digit 0"))?;
@@ -4536,6 +7913,663 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn downsampled_batches_aggregate_each_key_while_writing() -> Result<()> {
+        let (_output_dir, dataset) = file_period_fixture()?;
+        let task = dataset.data_range_write_task(100, &(0..3))?;
+        let rows = |aggregation| -> Result<Vec<(i64, f64)>> {
+            let downsample = DataTableDownsample {
+                source_table_name: "ST__Interval__FileFixture__MetricA".to_string(),
+                aggregation,
+                target_blocks: Arc::new([(1, 1), (2, 1), (3, 2)].into_iter().collect()),
+            };
+            let batch =
+                dataset.downsampled_data_range_task_batch(&task, &downsample, None, None)?;
+            assert_eq!(batch.schema(), DATA_RECORD_BATCH_SCHEMA.clone());
+            let block_ids = batch.column(4).as_primitive::<Int64Type>();
+            let values = batch.column(5).as_primitive::<Float64Type>();
+            Ok(block_ids
+                .values()
+                .iter()
+                .copied()
+                .zip(values.values().iter().copied())
+                .collect())
+        };
+        assert_eq!(rows(DownsampleAggregation::Sum)?, vec![(1, 3.5), (2, 3.75)]);
+        assert_eq!(
+            rows(DownsampleAggregation::Mean)?,
+            vec![(1, 1.75), (2, 3.75)]
+        );
+        assert_eq!(rows(DownsampleAggregation::Min)?, vec![(1, 1.0), (2, 3.75)]);
+        Ok(())
+    }
+
+    #[test]
+    fn downsample_aggregates_interval_tables_by_period0_day() -> Result<()> {
+        let (output_dir, mut dataset) = file_period_fixture()?;
+        let base_time = chrono::DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z")?
+            .with_timezone(&chrono::Utc);
+        let mut intervals = indexmap::IndexMap::new();
+        let mut phase = indexmap::IndexMap::new();
+        for interval_id in 1_i64..=40 {
+            intervals.insert(
+                interval_id,
+                PeriodType::Interval(Period0 {
+                    interval_id,
+                    period_of_day: (interval_id - 1) % 24 + 1,
+                    hour_id: interval_id,
+                    day_id: (interval_id - 1) / 24 + 1,
+                    week_id: 1,
+                    month_id: 1,
+                    quarter_id: None,
+                    fiscal_year_id: 1,
+                    datetime: base_time + chrono::Duration::hours(interval_id - 1),
+                }),
+            );
+            phase.insert(
+                interval_id,
+                PhaseType::ST(Phase {
+                    interval_id,
+                    period_id: interval_id,
+                }),
+            );
+        }
+        dataset.period.insert("interval".to_string(), intervals);
+        dataset.phase.insert("ST".to_string(), phase);
+
+        let mut downsample = Downsample::new(DownsamplePeriod::Day);
+        downsample
+            .aggregations
+            .insert("MetricA".to_string(), DownsampleAggregation::Sum);
+        let db_path = output_dir.path().join("downsampled.duckdb");
+        dataset
+            .to_duckdb(&db_path)
+            .with_downsample(downsample)
+            .run()?;
+
+        let con = duckdb::Connection::open(&db_path)?;
+        let mut stmt = con.prepare(
+            "SELECT table_name, source_table, period, aggregation
+             FROM main.plexos2duckdb_downsampled_tables ORDER BY table_name;",
+        )?;
+        let catalog = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(catalog.len(), 4);
+        assert_eq!(
+            catalog[0],
+            (
+                "ST__Interval_to_Day__FileFixture__MetricA".to_string(),
+                "ST__Interval__FileFixture__MetricA".to_string(),
+                "Day".to_string(),
+                "sum".to_string(),
+            )
+        );
+        assert_eq!(catalog[3].3, "mean");
+
+        let interval_tables: i64 = con.query_row(
+            "SELECT COUNT(*) FROM duckdb_tables()
+             WHERE schema_name = 'data' AND table_name LIKE 'ST\\_\\_Interval\\_\\_%' ESCAPE '\\';",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(interval_tables, 0, "interval tables should be replaced");
+
+        let (block_id, value): (i64, f64) = con.query_row(
+            "SELECT block_id, value FROM data.ST__Interval_to_Day__FileFixture__MetricA;",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!((block_id, value), (1, 7.25));
+
+        let (value, interval_length): (f64, i64) = con.query_row(
+            "SELECT MetricD, interval_length FROM report.ST__Interval_to_Day__FileFixture__MetricD;",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert!((value - (30.0 + 31.5 + 32.75) / 3.0).abs() < 1e-9);
+        assert_eq!(interval_length, 16, "day 2 holds intervals 25 to 40");
+        drop(con);
+
+        let external_db_path = output_dir.path().join("downsampled-external.duckdb");
+        dataset
+            .to_duckdb(&external_db_path)
+            .with_downsample(Downsample::new(DownsamplePeriod::Day))
+            .with_external_data_parquet_dir(output_dir.path().join("external"))
+            .run()?;
+        let con = duckdb::Connection::open(&external_db_path)?;
+        let (block_id, value): (i64, f64) = con.query_row(
+            "SELECT block_id, value FROM data.ST__Interval_to_Day__FileFixture__MetricB;",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(block_id, 1);
+        assert!((value - (10.0 + 11.5 + 12.75) / 3.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn time_window_writes_only_values_inside_the_window() -> Result<()> {
+        let (output_dir, dataset) = file_period_fixture()?;
+        let base_time = chrono::DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z")?
+            .with_timezone(&chrono::Utc);
+        let window = DataTimeWindow {
+            from: Some(base_time + chrono::Duration::hours(1)),
+            to: Some(base_time + chrono::Duration::hours(12)),
+        };
+
+        let db_path = output_dir.path().join("time_window.duckdb");
+        dataset
+            .to_duckdb(&db_path)
+            .with_data_time_window(window)
+            .run()?;
+
+        let con = duckdb::Connection::open(&db_path)?;
+        let mut stmt = con.prepare(
+            "SELECT table_name FROM duckdb_tables() WHERE schema_name = 'data' ORDER BY table_name;",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(
+            tables,
+            vec![
+                "ST__Interval__FileFixture__MetricA".to_string(),
+                "ST__Interval__FileFixture__MetricB".to_string(),
+            ]
+        );
+
+        for (table_name, expected) in [
+            (
+                "ST__Interval__FileFixture__MetricA",
+                vec![(2, 2.5), (3, 3.75)],
+            ),
+            (
+                "ST__Interval__FileFixture__MetricB",
+                vec![(11, 10.0), (12, 11.5)],
+            ),
+        ] {
+            let mut stmt = con.prepare(&format!(
+                "SELECT block_id, value FROM data.{} ORDER BY block_id;",
+                quote_ident(table_name)
+            ))?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            assert_eq!(rows, expected, "unexpected windowed rows in {table_name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cancel_token_stops_conversion_and_removes_partial_output() -> Result<()> {
+        let output_dir = tempfile::TempDir::new()?;
+        let list_dir = || -> Result<Vec<std::path::PathBuf>> {
+            let mut entries = std::fs::read_dir(output_dir.path())?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            entries.sort();
+            Ok(entries)
+        };
+        let token = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let dataset = file_period_dataset(output_dir.path())?.with_cancel_token(token.clone());
+        let before = list_dir()?;
+
+        let is_cancelled = |report: &color_eyre::Report| {
+            report
+                .chain()
+                .any(|err| matches!(err.downcast_ref::<Error>(), Some(Error::Cancelled)))
+        };
+
+        let db_path = output_dir.path().join("cancelled.duckdb");
+        let mut cancel_on_first_table = |event: ProgressEvent| {
+            if matches!(event, ProgressEvent::DataWorkerTableStart { .. }) {
+                token.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+        };
+        let err = dataset
+            .to_duckdb(&db_path)
+            .with_external_data_parquet_dir("cancelled-data")
+            .with_events(&mut cancel_on_first_table)
+            .run()
+            .expect_err("conversion should be cancelled while writing data");
+        assert!(is_cancelled(&err), "unexpected error: {err:#}");
+        assert_eq!(list_dir()?, before);
+
+        let err = dataset
+            .to_duckdb(output_dir.path().join("cancelled-parquet"))
+            .with_output_format(OutputFormat::Parquet)
+            .run()
+            .expect_err("conversion should be cancelled before it starts");
+        assert!(is_cancelled(&err), "unexpected error: {err:#}");
+        assert_eq!(list_dir()?, before);
+
+        token.store(false, std::sync::atomic::Ordering::Relaxed);
+        dataset.to_duckdb(&db_path).run()?;
+        assert!(db_path.exists());
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        tables: Vec<String>,
+        rows: std::collections::BTreeMap<String, usize>,
+        data_values: std::collections::BTreeMap<String, Vec<f64>>,
+        finalized: bool,
+    }
+
+    impl OutputSink for RecordingSink {
+        fn create_dimension_table(&mut self, table: &SinkTable) -> Result<()> {
+            self.tables.push(format!("{}.{}", table.schema, table.name));
+            Ok(())
+        }
+
+        fn append_rows(&mut self, table: &SinkTable, rows: &mut SinkRows<'_>) -> Result<()> {
+            let mut count = 0;
+            for row in rows {
+                assert_eq!(row?.len(), table.columns.len(), "{}", table.name);
+                count += 1;
+            }
+            self.rows
+                .insert(format!("{}.{}", table.schema, table.name), count);
+            Ok(())
+        }
+
+        fn write_data_batch(&mut self, table_name: &str, batch: &RecordBatch) -> Result<()> {
+            let values = batch
+                .column_by_name("value")
+                .and_then(|column| column.as_any().downcast_ref::<Float64Array>())
+                .ok_or_else(|| eyre!("data batch has no value column"))?;
+            self.data_values
+                .entry(table_name.to_string())
+                .or_default()
+                .extend(values.values().iter().copied());
+            Ok(())
+        }
+
+        fn finalize(&mut self) -> Result<()> {
+            self.finalized = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn output_sink_receives_dimension_tables_and_data_batches() -> Result<()> {
+        let (_output_dir, dataset) = file_period_fixture()?;
+
+        let mut sink = RecordingSink::default();
+        dataset.to_sink(&mut sink).run()?;
+        assert!(sink.finalized);
+        assert_eq!(
+            sink.tables.first().map(String::as_str),
+            Some("main.plexos2duckdb")
+        );
+        assert!(
+            sink.tables
+                .iter()
+                .any(|table| table == "raw.timestamp_block_ST__Interval")
+        );
+        assert_eq!(sink.rows.get("raw.keys"), Some(&4));
+        assert_eq!(sink.rows.get("raw.key_indexes"), Some(&4));
+        assert_eq!(sink.data_values.len(), 4);
+        for (table_name, values) in file_period_table_specs() {
+            assert_eq!(sink.data_values[table_name], values);
+        }
+
+        let con = duckdb::Connection::open_in_memory()?;
+        let mut sink = DuckdbSink::new(&con);
+        dataset.to_sink(&mut sink).run()?;
+        let keys: i64 = con.query_row("SELECT COUNT(*) FROM raw.keys", [], |row| row.get(0))?;
+        assert_eq!(keys, 4);
+        let total: f64 = con.query_row(
+            "SELECT SUM(value) FROM data.ST__Interval__FileFixture__MetricB",
+            [],
+            |row| row.get(0),
+        )?;
+        assert!((total - 34.25).abs() < 1e-9);
+        Ok(())
+    }
+
+    /// `file_period_dataset` with a collection, classes, categories, objects,
+    /// memberships and properties behind its keys.
+    fn dimension_fixture_dataset(output_dir: &std::path::Path) -> Result<SolutionDataset> {
+        let mut dataset = file_period_dataset(output_dir)?;
+        dataset.collection.insert(
+            700,
+            Collection {
+                collection_id: 700,
+                name: "FileFixture".to_string(),
+                ..Default::default()
+            },
+        );
+        for (class_id, name) in [(800, "Generator"), (801, "Battery")] {
+            dataset.class.insert(
+                class_id,
+                Class {
+                    class_id,
+                    name: name.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        for (category_id, name) in [(900, "Coal"), (901, "Gas")] {
+            dataset.category.insert(
+                category_id,
+                Category {
+                    category_id,
+                    name: name.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        for (idx, (table_name, _)) in file_period_table_specs().into_iter().enumerate() {
+            let offset = i64::try_from(idx)?;
+            let class_id = if idx < 2 { 800 } else { 801 };
+            dataset.membership.insert(
+                400 + offset,
+                Membership {
+                    membership_id: 400 + offset,
+                    child_class_id: class_id,
+                    collection_id: 700,
+                    child_object_id: 600 + offset,
+                    ..Default::default()
+                },
+            );
+            dataset.object.insert(
+                600 + offset,
+                Object {
+                    object_id: 600 + offset,
+                    name: format!("Unit {idx}"),
+                    class_id,
+                    category_id: 900 + offset % 2,
+                    ..Default::default()
+                },
+            );
+            dataset.property.insert(
+                500 + offset,
+                Property {
+                    property_id: 500 + offset,
+                    name: table_name
+                        .rsplit("__")
+                        .next()
+                        .expect("table name has a property")
+                        .to_string(),
+                    collection_id: 700,
+                    ..Default::default()
+                },
+            );
+        }
+
+        Ok(dataset)
+    }
+
+    #[test]
+    fn key_filters_select_keys_by_class_category_object_and_property() -> Result<()> {
+        let output_dir = tempfile::TempDir::new()?;
+        let dataset = dimension_fixture_dataset(output_dir.path())?;
+
+        let selected_tables = |filter: &DataKeyFilter| -> Result<Vec<String>> {
+            let tables = dataset.selected_data_tables(None, filter, &DataTimeWindow::default())?;
+            let selection = DataSelection {
+                tables: &tables,
+                table_name_pattern: None,
+                key_filter: filter,
+                time_window: DataTimeWindow::default(),
+                downsample: None,
+                cancel: None,
+            };
+            Ok(dataset
+                .build_data_table_plans(&selection)?
+                .into_iter()
+                .map(|plan| plan.table_name)
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect())
+        };
+
+        let filter = DataKeyFilter {
+            classes: vec![DataKeyFilter::pattern("Generator")?],
+            ..Default::default()
+        };
+        assert_eq!(
+            selected_tables(&filter)?,
+            vec![
+                "ST__Interval__FileFixture__MetricA".to_string(),
+                "ST__Interval__FileFixture__MetricB".to_string(),
+            ]
+        );
+
+        // Regex patterns match the whole name, like globs
+        assert!(!DataKeyFilter::pattern("re:Metric")?.is_match("MetricB"));
+        assert!(DataKeyFilter::pattern("re:Metric.")?.is_match("MetricB"));
+
+        let filter = DataKeyFilter {
+            categories: vec![DataKeyFilter::pattern("Coal")?],
+            properties: vec![DataKeyFilter::pattern("re:Metric[BC]")?],
+            ..Default::default()
+        };
+        assert_eq!(
+            selected_tables(&filter)?,
+            vec!["ST__Interval__FileFixture__MetricC".to_string()]
+        );
+
+        let filter = DataKeyFilter {
+            classes: vec![DataKeyFilter::pattern("*")?],
+            samples: vec![201, 202],
+            bands: vec![302, 303],
+            ..Default::default()
+        };
+        assert_eq!(
+            selected_tables(&filter)?,
+            vec!["ST__Interval__FileFixture__MetricC".to_string()]
+        );
+
+        let filter = DataKeyFilter {
+            objects: vec![DataKeyFilter::pattern("Unit ?")?],
+            collections: vec![DataKeyFilter::pattern("Other*")?],
+            ..Default::default()
+        };
+        assert!(selected_tables(&filter)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn accessors_expose_objects_memberships_properties_keys_and_blocks() -> Result<()> {
+        let output_dir = tempfile::TempDir::new()?;
+        let mut dataset = dimension_fixture_dataset(output_dir.path())?;
+        dataset.unit.insert(
+            1,
+            Unit {
+                id: 1,
+                name: "MW".to_string(),
+                ..Default::default()
+            },
+        );
+        dataset.unit.insert(
+            2,
+            Unit {
+                id: 2,
+                name: "GWh".to_string(),
+                ..Default::default()
+            },
+        );
+        if let Some(property) = dataset.property.get_mut(&500) {
+            property.unit_id = 1;
+            property.summary_unit_id = 2;
+        }
+
+        let generator = dataset
+            .class_by_name("Generator")
+            .ok_or_else(|| eyre!("expected Generator class"))?;
+        let generators = dataset
+            .objects_by_class(generator.class_id)
+            .map(|object| object.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(generators, vec!["Unit 0", "Unit 1"]);
+        assert!(dataset.class_by_name("Line").is_none());
+
+        let members = dataset
+            .memberships_by_collection(700)
+            .map(|membership| membership.child_object_id)
+            .collect::<Vec<_>>();
+        assert_eq!(members, vec![600, 601, 602, 603]);
+        assert_eq!(dataset.memberships_by_collection(701).count(), 0);
+
+        assert_eq!(dataset.property_unit(500, false)?.name, "MW");
+        assert_eq!(dataset.property_unit(500, true)?.name, "GWh");
+        assert!(dataset.property_unit(501, false).is_err());
+
+        assert_eq!(
+            dataset.data_table_names(),
+            vec![
+                "ST__Interval__FileFixture__MetricA",
+                "ST__Interval__FileFixture__MetricB",
+                "ST__Interval__FileFixture__MetricC",
+                "ST__Interval__FileFixture__MetricD",
+            ]
+        );
+        let keys = dataset.data_table_keys("ST__Interval__FileFixture__MetricB")?;
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].key_id, 101);
+        assert_eq!(dataset.key_index(keys[0].key_id)?.length, 3);
+        let err = dataset
+            .data_table_keys("ST__Interval__Missing__Metric")
+            .expect_err("missing data table");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingDataTable { table_name }) if table_name == "ST__Interval__Missing__Metric"
+        ));
+        let err = dataset.key_index(999).expect_err("missing key index");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::MissingKeyIndex { key_id: 999 })
+        ));
+        let err = dataset.object(999).expect_err("missing object");
+        assert_eq!(err.to_string(), "object not found for 999");
+        let err = DataKeyFilter::pattern("re:(").expect_err("invalid regex");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidOptions { .. })
+        ));
+
+        assert_eq!(dataset.timestamp_block_names(), vec!["ST__Interval"]);
+        let blocks = dataset
+            .timestamp_block_datetimes("ST__Interval")
+            .ok_or_else(|| eyre!("expected ST__Interval block"))?;
+        assert_eq!(blocks.len(), 40);
+        assert_eq!(blocks[0].0, 1);
+        assert_eq!(blocks[0].1.to_rfc3339(), "2020-01-01T00:00:00+00:00");
+        Ok(())
+    }
+
+    #[test]
+    fn series_streams_values_with_datetimes_from_period_data() -> Result<()> {
+        let output_dir = tempfile::TempDir::new()?;
+        let dataset = dimension_fixture_dataset(output_dir.path())?;
+
+        let values = dataset
+            .series("ST__Interval__FileFixture__MetricB")?
+            .collect::<Result<Vec<_>>>()?;
+        let base_time = chrono::DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z")?
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            values,
+            [(11, 10.0), (12, 11.5), (13, 12.75)]
+                .into_iter()
+                .map(|(block_id, value)| SeriesValue {
+                    key_id: 101,
+                    band_id: 301,
+                    sample_id: 201,
+                    block_id,
+                    datetime: Some(base_time + chrono::Duration::hours(block_id - 1)),
+                    value,
+                })
+                .collect::<Vec<_>>()
+        );
+
+        let values = dataset
+            .series(("FileFixture", "MetricC", "Unit 2"))?
+            .map(|value| value.map(|value| value.value))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(values, vec![20.0, 21.5, 22.75]);
+
+        assert!(
+            dataset
+                .series(("FileFixture", "MetricC", "Unit 0"))
+                .is_err()
+        );
+        assert!(dataset.series("ST__Interval__Missing__Metric").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn data_table_reader_yields_batches_with_optional_dimensions() -> Result<()> {
+        use duckdb::arrow::array::Array as _;
+
+        let output_dir = tempfile::TempDir::new()?;
+        let mut dataset = dimension_fixture_dataset(output_dir.path())?;
+        dataset.sample.insert(
+            200,
+            Sample {
+                sample_id: 200,
+                name: Some("Mean".to_string()),
+            },
+        );
+
+        let reader = dataset.data_table_reader("ST__Interval__FileFixture__MetricA")?;
+        assert_eq!(reader.schema(), DATA_RECORD_BATCH_SCHEMA.clone());
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(batches.len(), 1);
+        let values = batches[0]
+            .column(5)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| eyre!("expected Float64 values"))?;
+        assert_eq!(values.values().to_vec(), vec![1.0, 2.5, 3.75]);
+
+        let reader = dataset
+            .data_table_reader("ST__Interval__FileFixture__MetricA")?
+            .with_dimensions();
+        let schema = reader.schema();
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>()?;
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), schema);
+        let column = |name: &str| -> Result<StringArray> {
+            Ok(batch
+                .column_by_name(name)
+                .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+                .ok_or_else(|| eyre!("expected string column {name}"))?
+                .clone())
+        };
+        assert_eq!(column("sample_name")?.value(0), "Mean");
+        assert_eq!(column("name")?.value(2), "Unit 0");
+        assert_eq!(column("category")?.value(1), "Coal");
+        assert!(column("unit")?.is_null(0));
+        let timestamps = batch
+            .column_by_name("timestamp")
+            .and_then(|column| column.as_any().downcast_ref::<TimestampMicrosecondArray>())
+            .ok_or_else(|| eyre!("expected timestamp column"))?;
+        assert_eq!(
+            timestamps.value(1),
+            chrono::DateTime::parse_from_rfc3339("2020-01-01T01:00:00Z")?.timestamp_micros()
+        );
+
+        assert!(
+            dataset
+                .data_table_reader("ST__Interval__Missing__Metric")
+                .is_err()
+        );
+        Ok(())
+    }
+
     #[test]
     fn materializes_only_compressed_period_data_used_by_plans() -> Result<()> {
         let temp_dir = tempfile::TempDir::new()?;
@@ -4562,7 +8596,7 @@ mod tests {
         zip_writer.finish()?;
 
         let (_xml_content, period_data) =
-            SolutionDataset::read_zip_archive(&zip_path, "", &mut None)?;
+            SolutionDataset::read_zip_archive(&zip_path, "", None, &mut None)?;
         let mut key_index = indexmap::IndexMap::new();
         key_index.insert(
             10,
@@ -4579,10 +8613,13 @@ mod tests {
             period_data,
             ..Default::default()
         };
+        #[allow(clippy::single_range_in_vec_init)]
         let plans = vec![DataTableWritePlan {
             table_name: "selected_table".to_string(),
             key_ids: vec![10],
+            value_ranges: vec![0..2],
             estimated_values: 2,
+            downsample: None,
         }];
         let mut progress: Option<&mut dyn FnMut(DuckdbProgress)> = None;
 
@@ -4609,6 +8646,109 @@ mod tests {
         Ok(())
     }
 
+    /// Dataset over a ZIP with deflated `t_data_0.BIN` and `t_data_1.BIN`; the
+    /// two keys of `ST__Interval__Zip__Metric` read `[1, 2]` and `[3, 4]` from
+    /// `t_data_1.BIN`.
+    fn compressed_period_dataset(output_dir: &std::path::Path) -> Result<SolutionDataset> {
+        let zip_path = output_dir.join("Model_Base_Solution.zip");
+        let mut zip_writer = zip::ZipWriter::new(std::fs::File::create(&zip_path)?);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip_writer.start_file("Model Base Solution.xml", options)?;
+        zip_writer.write_all(b"<root />")?;
+        for (name, values) in [
+            ("t_data_0.BIN", &[0.0_f64, 0.0][..]),
+            ("t_data_1.BIN", &[1.0, 2.0, 3.0, 4.0]),
+        ] {
+            zip_writer.start_file(name, options)?;
+            for value in values {
+                zip_writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        zip_writer.finish()?;
+
+        let (_xml_content, period_data) =
+            SolutionDataset::read_zip_archive(&zip_path, "", None, &mut None)?;
+        let mut key = indexmap::IndexMap::new();
+        let mut key_index = indexmap::IndexMap::new();
+        for (key_id, position) in [(10, 0), (11, 16)] {
+            key.insert(
+                key_id,
+                Key {
+                    key_id,
+                    ..Default::default()
+                },
+            );
+            key_index.insert(
+                key_id,
+                KeyIndex {
+                    key_id,
+                    period_type_id: 1,
+                    length: 2,
+                    position,
+                    period_offset: 0,
+                },
+            );
+        }
+        Ok(SolutionDataset {
+            key,
+            key_index,
+            period_data,
+            table_key_index_mapping: [("ST__Interval__Zip__Metric".to_string(), vec![10, 11])]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn series_reads_compressed_period_data() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let dataset = compressed_period_dataset(temp_dir.path())?;
+
+        let mut series = dataset.series("ST__Interval__Zip__Metric")?;
+        assert!(series.materialized_period_data.is_none());
+        let values = series
+            .by_ref()
+            .map(|value| value.map(|value| (value.key_id, value.block_id, value.value)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            values,
+            vec![(10, 1, 1.0), (10, 2, 2.0), (11, 1, 3.0), (11, 2, 4.0)]
+        );
+        let materialized = series
+            .materialized_period_data
+            .as_ref()
+            .ok_or_else(|| eyre!("expected t_data_1.BIN to be extracted"))?;
+        assert!(materialized.get(0).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn data_table_reader_extracts_compressed_bins_lazily_and_combines_keys() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let dataset = compressed_period_dataset(temp_dir.path())?;
+
+        let mut reader = dataset.data_table_reader("ST__Interval__Zip__Metric")?;
+        assert!(reader.materialized_period_data.is_none());
+        let batch = reader.next().ok_or_else(|| eyre!("expected a batch"))??;
+        let materialized = reader
+            .materialized_period_data
+            .as_ref()
+            .ok_or_else(|| eyre!("expected t_data_1.BIN to be extracted"))?;
+        assert!(materialized.get(0).is_none());
+        assert!(materialized.get(1).is_some());
+
+        let values = batch
+            .column(5)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| eyre!("expected Float64 values"))?;
+        assert_eq!(values.values().to_vec(), vec![1.0, 2.0, 3.0, 4.0]);
+        assert!(reader.next().is_none());
+        Ok(())
+    }
+
     #[test]
     fn read_zip_archive_uses_zip_offsets_for_stored_bin_entries() -> Result<()> {
         let temp_dir = tempfile::TempDir::new()?;
@@ -4638,7 +8778,7 @@ mod tests {
         zip_writer.finish()?;
 
         let (_xml_content, period_data) =
-            SolutionDataset::read_zip_archive(&zip_path, "", &mut None)?;
+            SolutionDataset::read_zip_archive(&zip_path, "", None, &mut None)?;
         let data = period_data
             .get(&0)
             .ok_or_else(|| eyre!("expected period data for digit 0"))?;
@@ -4656,30 +8796,3 @@ mod tests {
         Ok(())
     }
 }
-
-/// Helper function to get text from a child element, returns any type T that implements FromStr
-fn get_child<T: std::str::FromStr>(node: &Node, tag_name: &str) -> Result<T>
-where
-    T::Err: std::fmt::Debug,
-{
-    node.children()
-        .find(|n| n.has_tag_name(tag_name))
-        .and_then(|n| n.text())
-        .map(|s| s.to_string())
-        .ok_or_else(|| eyre!("Missing {} element: {:?}", tag_name, node))?
-        .parse::<T>()
-        .map_err(|_| eyre!("Invalid value for {}: {:?}", tag_name, node))
-}
-
-fn parse_datetime_to_utc(input: &str) -> Result<chrono::DateTime<chrono::Utc>> {
-    // Try parsing with timezone first
-    if let Ok(dt_with_tz) = chrono::DateTime::parse_from_rfc3339(input) {
-        Ok(dt_with_tz.with_timezone(&chrono::Utc))
-    } else {
-        let naive = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S")?;
-        Ok(chrono::DateTime::<chrono::Utc>::from_naive_utc_and_offset(
-            naive,
-            chrono::Utc,
-        ))
-    }
-}
@@ -1,14 +1,12 @@
-mod cli;
-
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use owo_colors::OwoColorize;
-
-use crate::cli::Cli;
+use plexos2duckdb::cli::{self, Cli};
 
 fn main() {
-    let cli = Cli::parse();
-    if let Err(err) = cli::run(cli) {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Err(err) = cli::run(cli, &matches) {
         eprintln!("{} {}", "Error:".red().bold(), err);
-        std::process::exit(1);
+        std::process::exit(cli::exit_code(&err));
     }
 }
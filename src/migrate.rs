@@ -0,0 +1,231 @@
+//! Schema versions of generated databases and in-place upgrades between them.
+//! Databases without a `schema_version` key are version 1.
+
+use color_eyre::{Result, eyre::eyre};
+use duckdb::arrow::record_batch::RecordBatch;
+
+use crate::{DuckdbSink, OutputSink, SinkRows, SinkTable, SolutionDataset, ViewDialect};
+
+/// Version of the table and view layout written by this build. Version 3 added
+/// the `guid` column to `raw.objects`.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Changes made by [`migrate_database`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationSummary {
+    pub from_version: u32,
+    pub to_version: u32,
+    /// `schema.table` of each table that was missing and created empty
+    pub created_tables: Vec<String>,
+    /// `schema.table.column` of each column that was missing and added as NULL
+    pub added_columns: Vec<String>,
+    /// Number of `processed` and `report` views recreated
+    pub recreated_views: usize,
+}
+
+/// Schema version recorded in `main.plexos2duckdb`, or 1 when the key is missing.
+pub fn database_schema_version(con: &duckdb::Connection) -> Result<u32> {
+    let value = con
+        .prepare("SELECT value FROM main.plexos2duckdb WHERE key = 'schema_version';")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .next()
+        .transpose()?;
+    match value {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|err| eyre!("Invalid schema_version {value:?} in main.plexos2duckdb: {err}")),
+        None => Ok(1),
+    }
+}
+
+/// Fail for databases written by a newer plexos2duckdb than this one.
+pub fn check_schema_version(version: u32) -> Result<()> {
+    if version > SCHEMA_VERSION {
+        return Err(eyre!(
+            "Database has schema version {version}, but this plexos2duckdb supports up to {SCHEMA_VERSION}; upgrade plexos2duckdb to read it"
+        ));
+    }
+    Ok(())
+}
+
+/// Upgrade a database written by an older build to [`SCHEMA_VERSION`] in one
+/// transaction. Merged databases are rejected; migrate their inputs instead.
+pub fn migrate_database(con: &mut duckdb::Connection) -> Result<MigrationSummary> {
+    let from_version = database_schema_version(con)?;
+    check_schema_version(from_version)?;
+    let mut summary = MigrationSummary {
+        from_version,
+        to_version: SCHEMA_VERSION,
+        ..Default::default()
+    };
+    if from_version == SCHEMA_VERSION {
+        return Ok(summary);
+    }
+    if catalog_tables(con, "main")?
+        .iter()
+        .any(|(name, _)| name == "scenarios")
+    {
+        return Err(eyre!(
+            "Merged databases cannot be migrated; migrate each input and run `merge` again"
+        ));
+    }
+
+    let tx = con.transaction()?;
+    upgrade_dimension_tables(&tx, &mut summary)?;
+    summary.recreated_views = recreate_views(&tx)?;
+
+    tx.execute_batch("DELETE FROM main.plexos2duckdb WHERE key = 'schema_version';")?;
+    let mut appender = tx.appender_to_db("plexos2duckdb", "main")?;
+    appender.append_row(["schema_version", SCHEMA_VERSION.to_string().as_str()])?;
+    appender.append_row(["schema_migrated_from", from_version.to_string().as_str()])?;
+    appender.append_row([
+        "schema_migrated_at",
+        chrono::Utc::now().to_string().as_str(),
+    ])?;
+    appender.append_row([
+        "schema_migrated_by",
+        crate::utils::version().to_string().as_str(),
+    ])?;
+    appender.flush()?;
+    drop(appender);
+    tx.commit()?;
+    con.execute_batch("CHECKPOINT;")?;
+    Ok(summary)
+}
+
+/// `(table, table_type)` of every table and view in `schema`.
+fn catalog_tables(con: &duckdb::Connection, schema: &str) -> Result<Vec<(String, String)>> {
+    Ok(con
+        .prepare(
+            "SELECT table_name, table_type FROM information_schema.tables
+             WHERE table_schema = ? ORDER BY table_name;",
+        )?
+        .query_map([schema], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+/// Records the tables a conversion creates, without their rows.
+#[derive(Default)]
+struct ShapeSink {
+    tables: Vec<SinkTable>,
+}
+
+impl OutputSink for ShapeSink {
+    fn create_dimension_table(&mut self, table: &SinkTable) -> Result<()> {
+        self.tables.push(table.clone());
+        Ok(())
+    }
+
+    fn append_rows(&mut self, _table: &SinkTable, _rows: &mut SinkRows<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_data_batch(&mut self, _table_name: &str, _batch: &RecordBatch) -> Result<()> {
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Add the dimension tables and columns of the current layout that the
+/// database lacks. Timestamp blocks depend on the solution and are left alone.
+fn upgrade_dimension_tables(
+    con: &duckdb::Connection,
+    summary: &mut MigrationSummary,
+) -> Result<()> {
+    let dataset = SolutionDataset::default();
+    let mut shapes = ShapeSink::default();
+    for (_, populate) in SolutionDataset::raw_table_populate_steps() {
+        populate(&dataset, &mut shapes, None)?;
+    }
+
+    let mut sink = DuckdbSink::new(con);
+    for table in &shapes.tables {
+        let columns = con
+            .prepare(
+                "SELECT column_name FROM information_schema.columns
+                 WHERE table_schema = ? AND table_name = ?;",
+            )?
+            .query_map([table.schema, table.name.as_str()], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<std::result::Result<std::collections::HashSet<_>, _>>()?;
+        if columns.is_empty() {
+            sink.create_dimension_table(table)?;
+            summary
+                .created_tables
+                .push(format!("{}.{}", table.schema, table.name));
+            continue;
+        }
+        for column in &table.columns {
+            if columns.contains(column.name) {
+                continue;
+            }
+            let column_type = match column.column_type {
+                crate::SinkColumnType::Enum { name, values } => {
+                    let exists: i64 = con.query_row(
+                        "SELECT COUNT(*) FROM duckdb_types() WHERE type_name = ?;",
+                        [name],
+                        |row| row.get(0),
+                    )?;
+                    if exists == 0 {
+                        con.execute_batch(&crate::sink::create_enum_type_sql(name, values))?;
+                    }
+                    name
+                },
+                other => other.duckdb_type(),
+            };
+            con.execute_batch(&format!(
+                "ALTER TABLE {}.{} ADD COLUMN {} {column_type};",
+                crate::sink::quote_ident(table.schema),
+                crate::sink::quote_ident(&table.name),
+                crate::sink::quote_ident(column.name),
+            ))?;
+            summary
+                .added_columns
+                .push(format!("{}.{}.{}", table.schema, table.name, column.name));
+        }
+    }
+    Ok(())
+}
+
+/// Replace the `processed` and `report` views with the current definitions,
+/// built from the `raw` timestamp blocks and the `data` tables in `con`.
+fn recreate_views(con: &duckdb::Connection) -> Result<usize> {
+    for schema in ["report", "processed"] {
+        for (view, table_type) in catalog_tables(con, schema)? {
+            if table_type == "VIEW" {
+                con.execute_batch(&format!(
+                    "DROP VIEW {schema}.{};",
+                    crate::sink::quote_ident(&view)
+                ))?;
+            }
+        }
+    }
+
+    let timestamp_blocks = catalog_tables(con, "raw")?
+        .into_iter()
+        .filter_map(|(table, _)| table.strip_prefix("timestamp_block_").map(str::to_string))
+        .collect::<Vec<_>>();
+    let data_tables = catalog_tables(con, "data")?
+        .into_iter()
+        .map(|(table, _)| {
+            let has_timestamps =
+                timestamp_blocks.contains(&crate::sink::timestamp_block_name(&table));
+            (table, has_timestamps)
+        })
+        .collect::<Vec<_>>();
+
+    con.execute_batch(&SolutionDataset::processed_views_sql_for_blocks(
+        timestamp_blocks.clone(),
+        ViewDialect::DuckDb,
+    ))?;
+    con.execute_batch(&SolutionDataset::report_views_sql_for_tables(
+        &data_tables,
+        ViewDialect::DuckDb,
+    )?)?;
+    Ok(catalog_tables(con, "processed")?.len() + catalog_tables(con, "report")?.len())
+}
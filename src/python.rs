@@ -0,0 +1,474 @@
+//! Python extension module `plexos2duckdb._plexos2duckdb`, built with the
+//! `python` feature. The `plexos2duckdb` Python package wraps it; see
+//! `python/plexos2duckdb/api.py`.
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+
+use duckdb::arrow::ffi_stream::FFI_ArrowArrayStream;
+use owo_colors::OwoColorize;
+use pyo3::{
+    exceptions::{PyKeyboardInterrupt, PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyCapsule, PyDict},
+};
+
+use crate::cli;
+
+pyo3::create_exception!(
+    _plexos2duckdb,
+    PlexosError,
+    PyRuntimeError,
+    "Raised when loading or converting a PLEXOS solution fails."
+);
+
+fn to_py_err(err: color_eyre::Report) -> PyErr {
+    match err
+        .chain()
+        .find_map(|err| err.downcast_ref::<crate::Error>())
+    {
+        Some(crate::Error::Cancelled) => PyKeyboardInterrupt::new_err(err.to_string()),
+        _ => PlexosError::new_err(format!("{err:#}")),
+    }
+}
+
+/// Python callables for progress messages and events. Callbacks run with the
+/// interpreter attached; an exception or a pending signal (Ctrl-C) sets the
+/// cancel token so the conversion stops and the exception is raised instead.
+struct Callbacks {
+    on_progress: Option<Py<PyAny>>,
+    on_event: Option<Py<PyAny>>,
+    cancel: Arc<AtomicBool>,
+    error: Mutex<Option<PyErr>>,
+}
+
+impl Callbacks {
+    fn new(on_progress: Option<Py<PyAny>>, on_event: Option<Py<PyAny>>) -> Self {
+        Self {
+            on_progress,
+            on_event,
+            cancel: Arc::new(AtomicBool::new(false)),
+            error: Mutex::new(None),
+        }
+    }
+
+    fn call(&self, f: impl FnOnce(Python<'_>) -> PyResult<()>) {
+        if self.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        Python::attach(|py| {
+            if let Err(err) = py.check_signals().and_then(|()| f(py)) {
+                *self.error.lock().unwrap_or_else(|err| err.into_inner()) = Some(err);
+                self.cancel.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    fn progress(&self, msg: &str) {
+        self.call(|py| {
+            if let Some(on_progress) = &self.on_progress {
+                on_progress.bind(py).call1((msg,))?;
+            }
+            Ok(())
+        });
+    }
+
+    fn event(&self, event: crate::ProgressEvent) {
+        self.call(|py| {
+            if let Some(on_event) = &self.on_event {
+                on_event.bind(py).call1((event_dict(py, event)?,))?;
+            }
+            Ok(())
+        });
+    }
+
+    fn finish<T>(self, result: color_eyre::Result<T>) -> PyResult<T> {
+        let error = self
+            .error
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner());
+        match error {
+            Some(err) => Err(err),
+            None => result.map_err(to_py_err),
+        }
+    }
+}
+
+/// Progress event as a dict, keyed like the `convert --format-diagnostics json`
+/// events.
+fn event_dict(py: Python<'_>, event: crate::ProgressEvent) -> PyResult<Bound<'_, PyDict>> {
+    let dict = PyDict::new(py);
+    match event {
+        crate::ProgressEvent::DataTableStart {
+            index,
+            total,
+            table_name,
+            keys,
+        } => {
+            dict.set_item("event", "data_table_start")?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+            dict.set_item("table_name", table_name)?;
+            dict.set_item("keys", keys)?;
+        },
+        crate::ProgressEvent::DataTableEnd => {
+            dict.set_item("event", "data_table_end")?;
+        },
+        crate::ProgressEvent::DataWorkerTableStart {
+            worker_id,
+            index,
+            total,
+            table_name,
+            keys,
+        } => {
+            dict.set_item("event", "data_worker_table_start")?;
+            dict.set_item("worker_id", worker_id)?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+            dict.set_item("table_name", table_name)?;
+            dict.set_item("keys", keys)?;
+        },
+        crate::ProgressEvent::DataWorkerTableEnd {
+            worker_id,
+            index,
+            total,
+        } => {
+            dict.set_item("event", "data_worker_table_end")?;
+            dict.set_item("worker_id", worker_id)?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+        },
+        crate::ProgressEvent::DataMergeTableStart {
+            index,
+            total,
+            table_name,
+        } => {
+            dict.set_item("event", "data_merge_table_start")?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+            dict.set_item("table_name", table_name)?;
+        },
+        crate::ProgressEvent::DataMergeTableEnd { index, total } => {
+            dict.set_item("event", "data_merge_table_end")?;
+            dict.set_item("index", index)?;
+            dict.set_item("total", total)?;
+        },
+    }
+    Ok(dict)
+}
+
+/// A loaded PLEXOS solution.
+#[pyclass(
+    name = "SolutionDataset",
+    module = "plexos2duckdb._plexos2duckdb",
+    frozen
+)]
+struct PySolutionDataset {
+    inner: Arc<crate::SolutionDataset>,
+}
+
+/// Keyword arguments shared by `SolutionDataset.from_zip` and `from_xml`.
+struct LoadOptions {
+    model_name: Option<String>,
+    simulation_log: Option<String>,
+    run_stats: Option<String>,
+    on_progress: Option<Py<PyAny>>,
+}
+
+impl PySolutionDataset {
+    fn load(
+        py: Python<'_>,
+        path: &std::path::Path,
+        zip: bool,
+        options: LoadOptions,
+    ) -> PyResult<Self> {
+        let LoadOptions {
+            model_name,
+            simulation_log,
+            run_stats,
+            on_progress,
+        } = options;
+        let callbacks = Callbacks::new(on_progress, None);
+        let result = py.detach(|| {
+            let dataset = crate::SolutionDataset::default()
+                .with_cancel_token(callbacks.cancel.clone())
+                .with_model_name(model_name.unwrap_or_default());
+            let mut report = |msg: &str| callbacks.progress(msg);
+            let dataset = if zip {
+                dataset.with_zip_file_with_progress(path, &mut report)?
+            } else {
+                dataset.with_xml_file_with_progress(path, &mut report)?
+            };
+            Ok(dataset
+                .with_simulation_log(simulation_log.unwrap_or_default())
+                .with_run_stats(run_stats.unwrap_or_default()))
+        });
+        let dataset = callbacks.finish(result)?;
+        Ok(Self {
+            inner: Arc::new(dataset),
+        })
+    }
+}
+
+#[pymethods]
+impl PySolutionDataset {
+    /// Load a PLEXOS solution ZIP. `simulation_log` and `run_stats` are the
+    /// contents of the model log and `runstats.json`, stored with the metadata.
+    #[staticmethod]
+    #[pyo3(signature = (
+        path,
+        *,
+        model_name=None,
+        simulation_log=None,
+        run_stats=None,
+        on_progress=None
+    ))]
+    fn from_zip(
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        model_name: Option<String>,
+        simulation_log: Option<String>,
+        run_stats: Option<String>,
+        on_progress: Option<Py<PyAny>>,
+    ) -> PyResult<Self> {
+        let options = LoadOptions {
+            model_name,
+            simulation_log,
+            run_stats,
+            on_progress,
+        };
+        Self::load(py, &path, true, options)
+    }
+
+    /// Load a PLEXOS solution XML file, without period data.
+    #[staticmethod]
+    #[pyo3(signature = (
+        path,
+        *,
+        model_name=None,
+        simulation_log=None,
+        run_stats=None,
+        on_progress=None
+    ))]
+    fn from_xml(
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        model_name: Option<String>,
+        simulation_log: Option<String>,
+        run_stats: Option<String>,
+        on_progress: Option<Py<PyAny>>,
+    ) -> PyResult<Self> {
+        let options = LoadOptions {
+            model_name,
+            simulation_log,
+            run_stats,
+            on_progress,
+        };
+        Self::load(py, &path, false, options)
+    }
+
+    #[getter]
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    /// Names of the data tables, sorted.
+    fn data_table_names(&self) -> Vec<String> {
+        self.inner
+            .data_table_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Unit of a data table, or `None` when the table has no unit.
+    fn data_table_unit(&self, table_name: &str) -> Option<String> {
+        self.inner.data_table_unit(table_name).map(str::to_string)
+    }
+
+    /// Arrow stream over every value of a data table. Pass it to
+    /// `pyarrow.RecordBatchReader.from_stream`, `duckdb.from_arrow` or any
+    /// other consumer of the Arrow PyCapsule interface.
+    #[pyo3(signature = (table_name, *, dimensions=false))]
+    fn data_table_reader(&self, table_name: &str, dimensions: bool) -> PyResult<DataTableStream> {
+        let mut reader = self
+            .inner
+            .shared_data_table_reader(table_name)
+            .map_err(to_py_err)?;
+        if dimensions {
+            reader = reader.with_dimensions();
+        }
+        Ok(DataTableStream {
+            reader: Mutex::new(Some(reader)),
+        })
+    }
+
+    /// Convert to DuckDB, or to `output_format` "parquet", "sqlite" or "arrow".
+    /// `on_progress` receives status messages and `on_event` progress dicts.
+    #[pyo3(signature = (
+        path,
+        *,
+        n_threads=None,
+        table_name_pattern=None,
+        external_data_parquet_dir=None,
+        output_format="duckdb",
+        on_progress=None,
+        on_event=None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn to_duckdb(
+        &self,
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        n_threads: Option<usize>,
+        table_name_pattern: Option<&str>,
+        external_data_parquet_dir: Option<std::path::PathBuf>,
+        output_format: &str,
+        on_progress: Option<Py<PyAny>>,
+        on_event: Option<Py<PyAny>>,
+    ) -> PyResult<()> {
+        let output_format = match output_format {
+            "duckdb" => crate::OutputFormat::DuckDb,
+            "parquet" => crate::OutputFormat::Parquet,
+            "sqlite" => crate::OutputFormat::Sqlite,
+            "arrow" => crate::OutputFormat::ArrowIpc,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "output_format must be 'duckdb', 'parquet', 'sqlite' or 'arrow', got {other:?}"
+                )));
+            },
+        };
+        let table_name_pattern = table_name_pattern
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+        let callbacks = Callbacks::new(on_progress, on_event);
+        let result = py.detach(|| {
+            let mut report = |msg: &str| callbacks.progress(msg);
+            let mut events = |event: crate::ProgressEvent| callbacks.event(event);
+            let mut builder = self
+                .inner
+                .to_duckdb(&path)
+                .with_output_format(output_format)
+                .with_cancel_token(callbacks.cancel.clone())
+                .with_progress(&mut report)
+                .with_events(&mut events);
+            if let Some(threads) = n_threads {
+                builder = builder.with_data_write_threads(threads);
+            }
+            if let Some(pattern) = table_name_pattern {
+                builder = builder.with_data_table_name_pattern(pattern);
+            }
+            if let Some(dir) = external_data_parquet_dir {
+                builder = builder.with_external_data_parquet_dir(dir);
+            }
+            builder.run()
+        });
+        callbacks.finish(result)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SolutionDataset(model_name={:?}, data_tables={})",
+            self.inner.model_name(),
+            self.inner.data_table_names().len()
+        )
+    }
+}
+
+/// One-shot Arrow stream returned by `SolutionDataset.data_table_reader`.
+#[pyclass(module = "plexos2duckdb._plexos2duckdb", frozen)]
+struct DataTableStream {
+    reader: Mutex<Option<crate::DataTableReader<'static>>>,
+}
+
+#[pymethods]
+impl DataTableStream {
+    #[pyo3(signature = (requested_schema=None))]
+    fn __arrow_c_stream__<'py>(
+        &self,
+        py: Python<'py>,
+        requested_schema: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyCapsule>> {
+        // The batches have a fixed schema; `requested_schema` is a hint that
+        // producers may ignore.
+        let _ = requested_schema;
+        let reader = self
+            .reader
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+            .ok_or_else(|| PlexosError::new_err("The Arrow stream has already been consumed"))?;
+        let stream = FFI_ArrowArrayStream::new(Box::new(reader));
+        PyCapsule::new(py, stream, Some(c"arrow_array_stream".to_owned()))
+    }
+}
+
+/// Solution file for `input`: the file itself, or the only `.zip` file in a
+/// directory. Same rules as `plexos2duckdb convert --input`.
+#[pyfunction]
+fn resolve_solution_file(input: std::path::PathBuf) -> PyResult<std::path::PathBuf> {
+    cli::resolve_input_path(&input).map_err(to_py_err)
+}
+
+/// Model name from a `Model <name> Solution.zip` or `.xml` file name.
+#[pyfunction]
+fn model_name(input: std::path::PathBuf) -> PyResult<String> {
+    cli::model_name(&input).map_err(to_py_err)
+}
+
+/// DuckDB output path for `input`, defaulting to `input` with a `.duckdb`
+/// extension. With `force`, an existing file or conversion output folder is
+/// removed, as with `convert --force`.
+#[pyfunction]
+#[pyo3(signature = (input, output=None, force=false))]
+fn prepare_output_path(
+    input: std::path::PathBuf,
+    output: Option<std::path::PathBuf>,
+    force: bool,
+) -> PyResult<std::path::PathBuf> {
+    let path = cli::default_output_path(&input, output, cli::ConvertFormat::Duckdb);
+    cli::remove_existing_output(&path, force, "Pass `force=True`").map_err(to_py_err)?;
+    Ok(path)
+}
+
+/// Run the command line interface with `argv` and return its exit status.
+/// Backs the `plexos2duckdb` console script of the Python package.
+#[pyfunction]
+fn main(py: Python<'_>, argv: Vec<String>) -> i32 {
+    use clap::{CommandFactory as _, FromArgMatches as _};
+
+    let parsed = cli::Cli::command()
+        .try_get_matches_from(argv)
+        .and_then(|matches| cli::Cli::from_arg_matches(&matches).map(|cli| (cli, matches)));
+    let (cli, matches) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let _ = err.print();
+            return err.exit_code();
+        },
+    };
+    py.detach(|| match cli::run(cli, &matches) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("{} {}", "Error:".red().bold(), err);
+            cli::exit_code(&err)
+        },
+    })
+}
+
+#[pymodule]
+fn _plexos2duckdb(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", crate::utils::version())?;
+    m.add("PlexosError", m.py().get_type::<PlexosError>())?;
+    m.add_class::<PySolutionDataset>()?;
+    m.add_class::<DataTableStream>()?;
+    m.add_function(wrap_pyfunction!(resolve_solution_file, m)?)?;
+    m.add_function(wrap_pyfunction!(model_name, m)?)?;
+    m.add_function(wrap_pyfunction!(prepare_output_path, m)?)?;
+    m.add_function(wrap_pyfunction!(main, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,779 @@
+//! Output sinks: where a conversion writes its `raw` and `data` tables. Other
+//! backends implement [`OutputSink`] and are passed to `SolutionDataset::to_sink`.
+
+use color_eyre::{Result, eyre::eyre};
+use duckdb::{
+    arrow::{
+        array::{
+            Array, ArrayRef, BooleanArray, DictionaryArray, Float64Array, Int64Array, StringArray,
+            TimestampMicrosecondArray, UInt64Array,
+        },
+        datatypes::{DataType, Field, Int8Type, Schema, TimeUnit},
+        record_batch::RecordBatch,
+    },
+    types::Value,
+};
+
+// Rows per SQLite transaction when writing data tables.
+const SQLITE_TRANSACTION_ROWS: usize = 500_000;
+
+/// Column type of a dimension table, named after the DuckDB type it maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkColumnType {
+    BigInt,
+    UBigInt,
+    Double,
+    Boolean,
+    Varchar,
+    Timestamp,
+    /// Named enum type, e.g. `object_kind` with `object` and `relation`.
+    Enum {
+        name: &'static str,
+        values: &'static [&'static str],
+    },
+}
+
+impl SinkColumnType {
+    /// DuckDB type of a `data` table column.
+    pub fn for_data_type(data_type: &DataType) -> Option<Self> {
+        match data_type {
+            DataType::Int64 => Some(Self::BigInt),
+            DataType::UInt64 => Some(Self::UBigInt),
+            DataType::Float64 => Some(Self::Double),
+            DataType::Boolean => Some(Self::Boolean),
+            DataType::Utf8 => Some(Self::Varchar),
+            DataType::Timestamp(..) => Some(Self::Timestamp),
+            _ => None,
+        }
+    }
+
+    fn sqlite_type(self) -> &'static str {
+        match self {
+            Self::BigInt | Self::UBigInt | Self::Boolean => "INTEGER",
+            Self::Double => "REAL",
+            Self::Varchar | Self::Timestamp | Self::Enum { .. } => "TEXT",
+        }
+    }
+
+    pub(crate) fn duckdb_type(self) -> &'static str {
+        match self {
+            Self::BigInt => "BIGINT",
+            Self::UBigInt => "UBIGINT",
+            Self::Double => "DOUBLE",
+            Self::Boolean => "BOOLEAN",
+            Self::Varchar => "VARCHAR",
+            Self::Timestamp => "TIMESTAMP",
+            Self::Enum { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkColumn {
+    pub name: &'static str,
+    pub column_type: SinkColumnType,
+    pub primary_key: bool,
+}
+
+impl SinkColumn {
+    pub fn new(name: &'static str, column_type: SinkColumnType) -> Self {
+        Self {
+            name,
+            column_type,
+            primary_key: false,
+        }
+    }
+
+    pub fn primary_key(name: &'static str, column_type: SinkColumnType) -> Self {
+        Self {
+            primary_key: true,
+            ..Self::new(name, column_type)
+        }
+    }
+}
+
+/// A dimension table such as `raw.objects` or `main.plexos2duckdb`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkTable {
+    pub schema: &'static str,
+    pub name: String,
+    pub columns: Vec<SinkColumn>,
+}
+
+impl SinkTable {
+    pub fn new(schema: &'static str, name: impl Into<String>, columns: Vec<SinkColumn>) -> Self {
+        Self {
+            schema,
+            name: name.into(),
+            columns,
+        }
+    }
+
+    pub fn raw(name: impl Into<String>, columns: Vec<SinkColumn>) -> Self {
+        Self::new("raw", name, columns)
+    }
+}
+
+/// Rows of a dimension table, one value per column in column order.
+pub type SinkRows<'a> = dyn Iterator<Item = Result<Vec<Value>>> + 'a;
+
+/// Destination for the tables written by a conversion. Dimension tables come
+/// first, then data batches table by table, then `finalize`.
+pub trait OutputSink {
+    /// Create an empty dimension table.
+    fn create_dimension_table(&mut self, table: &SinkTable) -> Result<()>;
+
+    /// Append rows to a table created by [`OutputSink::create_dimension_table`].
+    fn append_rows(&mut self, table: &SinkTable, rows: &mut SinkRows<'_>) -> Result<()>;
+
+    /// Append values to the data table `table_name`, e.g.
+    /// `ST__Interval__Generators__Generation`. Batches have the columns of the
+    /// `data` tables; the first batch for a table creates it.
+    fn write_data_batch(&mut self, table_name: &str, batch: &RecordBatch) -> Result<()>;
+
+    /// Flush and close the output after the last table.
+    fn finalize(&mut self) -> Result<()>;
+}
+
+/// Writes tables into a DuckDB connection with appenders.
+pub struct DuckdbSink<'a> {
+    con: &'a duckdb::Connection,
+    enum_types: std::collections::HashSet<&'static str>,
+    data_tables: std::collections::HashSet<String>,
+    rows_written: std::collections::HashMap<&'static str, u64>,
+}
+
+impl<'a> DuckdbSink<'a> {
+    pub fn new(con: &'a duckdb::Connection) -> Self {
+        Self {
+            con,
+            enum_types: Default::default(),
+            data_tables: Default::default(),
+            rows_written: Default::default(),
+        }
+    }
+
+    /// Rows appended so far to the tables in `schema`.
+    pub fn rows_written(&self, schema: &str) -> u64 {
+        self.rows_written.get(schema).copied().unwrap_or_default()
+    }
+
+    fn create_table_sql(schema: &str, name: &str, columns: &[SinkColumn]) -> String {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{} {}{}",
+                    quote_ident(column.name),
+                    column.column_type.duckdb_type(),
+                    if column.primary_key {
+                        " PRIMARY KEY"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "CREATE SCHEMA IF NOT EXISTS {schema_ident};
+             CREATE TABLE {schema_ident}.{} ({columns});",
+            quote_ident(name),
+            schema_ident = quote_ident(schema),
+        )
+    }
+}
+
+impl OutputSink for DuckdbSink<'_> {
+    fn create_dimension_table(&mut self, table: &SinkTable) -> Result<()> {
+        for column in &table.columns {
+            if let SinkColumnType::Enum { name, values } = column.column_type
+                && self.enum_types.insert(name)
+            {
+                self.con
+                    .execute_batch(&create_enum_type_sql(name, values))?;
+            }
+        }
+        self.con.execute_batch(&Self::create_table_sql(
+            table.schema,
+            &table.name,
+            &table.columns,
+        ))?;
+        Ok(())
+    }
+
+    fn append_rows(&mut self, table: &SinkTable, rows: &mut SinkRows<'_>) -> Result<()> {
+        let mut appender = self.con.appender_to_db(&table.name, table.schema)?;
+        for row in rows {
+            appender.append_row(duckdb::appender_params_from_iter(row?))?;
+            *self.rows_written.entry(table.schema).or_default() += 1;
+        }
+        appender.flush()?;
+        Ok(())
+    }
+
+    fn write_data_batch(&mut self, table_name: &str, batch: &RecordBatch) -> Result<()> {
+        if !self.data_tables.contains(table_name) {
+            let mut columns = Vec::with_capacity(batch.num_columns());
+            for field in batch.schema().fields() {
+                let column_type =
+                    SinkColumnType::for_data_type(field.data_type()).ok_or_else(|| {
+                        eyre!(
+                            "Unsupported data column type {} for '{}'",
+                            field.data_type(),
+                            field.name()
+                        )
+                    })?;
+                columns.push(format!(
+                    "{} {}",
+                    quote_ident(field.name()),
+                    column_type.duckdb_type()
+                ));
+            }
+            self.con.execute_batch(&format!(
+                "CREATE SCHEMA IF NOT EXISTS data;
+                 CREATE TABLE data.{} ({});",
+                quote_ident(table_name),
+                columns.join(", ")
+            ))?;
+            self.data_tables.insert(table_name.to_string());
+        }
+        let mut appender = self.con.appender_to_db(table_name, "data")?;
+        appender.append_record_batch(batch.clone())?;
+        appender.flush()?;
+        *self.rows_written.entry("data").or_default() += batch.num_rows() as u64;
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Double-quoted SQL identifier, shared by every sink and the CLI.
+pub(crate) fn quote_ident(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Comma-separated string literals for the values of an enum column.
+fn enum_values_sql(values: &[&str]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{}'", value.replace('\'', "''")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// DuckDB `CREATE TYPE` statement for a [`SinkColumnType::Enum`].
+pub(crate) fn create_enum_type_sql(name: &str, values: &[&str]) -> String {
+    format!("CREATE TYPE {name} AS ENUM ({});", enum_values_sql(values))
+}
+
+/// Timestamp block of a `data` table, i.e. its `phase__period` prefix.
+pub(crate) fn timestamp_block_name(table_name: &str) -> String {
+    table_name
+        .split("__")
+        .take(2)
+        .collect::<Vec<_>>()
+        .join("__")
+}
+
+/// SQLite table for `schema.name`. SQLite has no schemas, so `raw.objects`
+/// becomes `raw_objects`; tables in `main` keep their name.
+pub(crate) fn sqlite_table_name(schema: &str, name: &str) -> String {
+    if schema == "main" {
+        name.to_string()
+    } else {
+        format!("{schema}_{name}")
+    }
+}
+
+/// Writes tables into a SQLite file, `raw.objects` as `raw_objects`. Enums become
+/// `TEXT` with a `CHECK` constraint and timestamps `YYYY-MM-DD HH:MM:SS`.
+pub struct SqliteSink {
+    con: Option<rusqlite::Connection>,
+    path: std::path::PathBuf,
+    staging_path: Option<tempfile::TempPath>,
+    views_sql: String,
+    data_tables: std::collections::HashSet<String>,
+    pending_rows: usize,
+}
+
+impl SqliteSink {
+    pub fn create<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| std::path::Path::new("."));
+        let staging_path = tempfile::Builder::new()
+            .prefix(".plexos2duckdb-")
+            .suffix(".sqlite")
+            .tempfile_in(parent)?
+            .into_temp_path();
+        let con = rusqlite::Connection::open(&staging_path)?;
+        con.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+        Ok(Self {
+            con: Some(con),
+            path,
+            staging_path: Some(staging_path),
+            views_sql: String::new(),
+            data_tables: Default::default(),
+            pending_rows: 0,
+        })
+    }
+
+    /// SQL run by [`OutputSink::finalize`] once every table is written.
+    pub fn with_views_sql(mut self, sql: String) -> Self {
+        self.views_sql = sql;
+        self
+    }
+
+    fn con(&self) -> Result<&rusqlite::Connection> {
+        self.con
+            .as_ref()
+            .ok_or_else(|| eyre!("SQLite output is already finalized"))
+    }
+
+    fn begin(&self) -> Result<()> {
+        let con = self.con()?;
+        if con.is_autocommit() {
+            con.execute_batch("BEGIN;")?;
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        let con = self.con()?;
+        if !con.is_autocommit() {
+            con.execute_batch("COMMIT;")?;
+        }
+        self.pending_rows = 0;
+        Ok(())
+    }
+
+    fn insert_sql(table_name: &str, columns: usize) -> String {
+        format!(
+            "INSERT INTO {} VALUES ({});",
+            quote_ident(table_name),
+            vec!["?"; columns].join(", ")
+        )
+    }
+
+    fn value(value: Value) -> Result<rusqlite::types::Value> {
+        use rusqlite::types::Value as Sqlite;
+
+        Ok(match value {
+            Value::Null => Sqlite::Null,
+            Value::Boolean(value) => Sqlite::Integer(value.into()),
+            Value::TinyInt(value) => Sqlite::Integer(value.into()),
+            Value::SmallInt(value) => Sqlite::Integer(value.into()),
+            Value::Int(value) => Sqlite::Integer(value.into()),
+            Value::BigInt(value) => Sqlite::Integer(value),
+            Value::UTinyInt(value) => Sqlite::Integer(value.into()),
+            Value::USmallInt(value) => Sqlite::Integer(value.into()),
+            Value::UInt(value) => Sqlite::Integer(value.into()),
+            Value::UBigInt(value) => Sqlite::Integer(i64::try_from(value)?),
+            Value::Float(value) => Sqlite::Real(value.into()),
+            Value::Double(value) => Sqlite::Real(value),
+            Value::Text(value) => Sqlite::Text(value),
+            Value::Timestamp(unit, value) => {
+                let datetime = chrono::DateTime::from_timestamp_micros(unit.to_micros(value))
+                    .ok_or_else(|| eyre!("Timestamp out of range: {value}"))?;
+                Sqlite::Text(datetime.format("%Y-%m-%d %H:%M:%S").to_string())
+            },
+            other => return Err(eyre!("Unsupported SQLite value: {other:?}")),
+        })
+    }
+}
+
+impl OutputSink for SqliteSink {
+    fn create_dimension_table(&mut self, table: &SinkTable) -> Result<()> {
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| {
+                let name = quote_ident(column.name);
+                let mut sql = format!("{name} {}", column.column_type.sqlite_type());
+                if column.primary_key {
+                    sql.push_str(" PRIMARY KEY");
+                }
+                if let SinkColumnType::Enum { values, .. } = column.column_type {
+                    sql.push_str(&format!(" CHECK ({name} IN ({}))", enum_values_sql(values)));
+                }
+                sql
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.con()?.execute_batch(&format!(
+            "CREATE TABLE {} ({columns});",
+            quote_ident(&sqlite_table_name(table.schema, &table.name))
+        ))?;
+        Ok(())
+    }
+
+    fn append_rows(&mut self, table: &SinkTable, rows: &mut SinkRows<'_>) -> Result<()> {
+        self.begin()?;
+        let con = self.con()?;
+        let mut stmt = con.prepare(&Self::insert_sql(
+            &sqlite_table_name(table.schema, &table.name),
+            table.columns.len(),
+        ))?;
+        for row in rows {
+            let row = row?
+                .into_iter()
+                .map(Self::value)
+                .collect::<Result<Vec<_>>>()?;
+            stmt.execute(rusqlite::params_from_iter(row))?;
+        }
+        drop(stmt);
+        self.commit()
+    }
+
+    fn write_data_batch(&mut self, table_name: &str, batch: &RecordBatch) -> Result<()> {
+        let sqlite_table = sqlite_table_name("data", table_name);
+        if !self.data_tables.contains(table_name) {
+            let mut columns = Vec::with_capacity(batch.num_columns());
+            for field in batch.schema().fields() {
+                let column_type =
+                    SinkColumnType::for_data_type(field.data_type()).ok_or_else(|| {
+                        eyre!(
+                            "Unsupported data column type {} for '{}'",
+                            field.data_type(),
+                            field.name()
+                        )
+                    })?;
+                columns.push(format!(
+                    "{} {}",
+                    quote_ident(field.name()),
+                    column_type.sqlite_type()
+                ));
+            }
+            self.con()?.execute_batch(&format!(
+                "CREATE TABLE {} ({});",
+                quote_ident(&sqlite_table),
+                columns.join(", ")
+            ))?;
+            self.data_tables.insert(table_name.to_string());
+        }
+
+        self.begin()?;
+        let con = self.con()?;
+        let mut stmt = con.prepare_cached(&Self::insert_sql(&sqlite_table, batch.num_columns()))?;
+        let columns = batch.columns();
+        for row_idx in 0..batch.num_rows() {
+            let mut row = Vec::with_capacity(columns.len());
+            for column in columns {
+                row.push(if column.is_null(row_idx) {
+                    rusqlite::types::Value::Null
+                } else if let Some(array) = column.as_any().downcast_ref::<Int64Array>() {
+                    rusqlite::types::Value::Integer(array.value(row_idx))
+                } else if let Some(array) = column.as_any().downcast_ref::<Float64Array>() {
+                    rusqlite::types::Value::Real(array.value(row_idx))
+                } else {
+                    return Err(eyre!(
+                        "Unsupported data column type {} in '{table_name}'",
+                        column.data_type()
+                    ));
+                });
+            }
+            stmt.execute(rusqlite::params_from_iter(row))?;
+        }
+        drop(stmt);
+
+        self.pending_rows += batch.num_rows();
+        if self.pending_rows >= SQLITE_TRANSACTION_ROWS {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<()> {
+        self.commit()?;
+        let con = self
+            .con
+            .take()
+            .ok_or_else(|| eyre!("SQLite output is already finalized"))?;
+        con.execute_batch(&self.views_sql)?;
+        con.close().map_err(|(_, err)| err)?;
+        if let Some(staging_path) = self.staging_path.take() {
+            staging_path.persist(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Buffer compression of Arrow IPC files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArrowIpcCompression {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl ArrowIpcCompression {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn write_options(self) -> Result<arrow_ipc::writer::IpcWriteOptions> {
+        let compression = match self {
+            Self::None => None,
+            Self::Lz4 => Some(arrow_ipc::CompressionType::LZ4_FRAME),
+            Self::Zstd => Some(arrow_ipc::CompressionType::ZSTD),
+        };
+        Ok(arrow_ipc::writer::IpcWriteOptions::default().try_with_compression(compression)?)
+    }
+}
+
+/// One Arrow IPC file written by [`ArrowIpcSink`].
+#[derive(Debug, Clone)]
+pub(crate) struct ArrowIpcTable {
+    pub(crate) schema: String,
+    pub(crate) name: String,
+    pub(crate) path: std::path::PathBuf,
+    pub(crate) rows: usize,
+}
+
+/// Writes every table as an Arrow IPC (Feather v2) file, e.g.
+/// `<output_dir>/raw/objects.arrow`, with enums dictionary encoded.
+pub struct ArrowIpcSink {
+    output_dir: std::path::PathBuf,
+    compression: ArrowIpcCompression,
+    data_writer: Option<(
+        String,
+        arrow_ipc::writer::FileWriter<std::io::BufWriter<std::fs::File>>,
+    )>,
+    tables: Vec<ArrowIpcTable>,
+}
+
+impl ArrowIpcSink {
+    pub fn create<P: AsRef<std::path::Path>>(
+        output_dir: P,
+        compression: ArrowIpcCompression,
+    ) -> Result<Self> {
+        let output_dir = output_dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&output_dir)?;
+        Ok(Self {
+            output_dir,
+            compression,
+            data_writer: None,
+            tables: Vec::new(),
+        })
+    }
+
+    /// Files written so far, in write order.
+    pub(crate) fn tables(&self) -> &[ArrowIpcTable] {
+        &self.tables
+    }
+
+    fn table_path(&self, schema: &str, name: &str) -> std::path::PathBuf {
+        let file_name = format!("{name}.arrow");
+        if schema == "main" {
+            self.output_dir.join(file_name)
+        } else {
+            self.output_dir.join(schema).join(file_name)
+        }
+    }
+
+    fn file_writer(
+        &self,
+        path: &std::path::Path,
+        schema: &Schema,
+    ) -> Result<arrow_ipc::writer::FileWriter<std::io::BufWriter<std::fs::File>>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        Ok(arrow_ipc::writer::FileWriter::try_new_with_options(
+            file,
+            schema,
+            self.compression.write_options()?,
+        )?)
+    }
+
+    fn finish_data_table(&mut self) -> Result<()> {
+        if let Some((_, mut writer)) = self.data_writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
+    fn array(column: &SinkColumn, values: Vec<Value>) -> Result<ArrayRef> {
+        let invalid = |value: &Value| {
+            eyre!(
+                "Unexpected value {value:?} for {:?} column '{}'",
+                column.column_type,
+                column.name
+            )
+        };
+        Ok(match column.column_type {
+            SinkColumnType::BigInt => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::BigInt(value) => Ok(Some(*value)),
+                        Value::Int(value) => Ok(Some(i64::from(*value))),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<Int64Array>>()?,
+            ),
+            SinkColumnType::UBigInt => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::UBigInt(value) => Ok(Some(*value)),
+                        Value::UInt(value) => Ok(Some(u64::from(*value))),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<UInt64Array>>()?,
+            ),
+            SinkColumnType::Double => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::Double(value) => Ok(Some(*value)),
+                        Value::Float(value) => Ok(Some(f64::from(*value))),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<Float64Array>>()?,
+            ),
+            SinkColumnType::Boolean => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::Boolean(value) => Ok(Some(*value)),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<BooleanArray>>()?,
+            ),
+            SinkColumnType::Varchar => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::Text(value) => Ok(Some(value.as_str())),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<StringArray>>()?,
+            ),
+            SinkColumnType::Timestamp => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::Timestamp(unit, value) => Ok(Some(unit.to_micros(*value))),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<TimestampMicrosecondArray>>()?,
+            ),
+            SinkColumnType::Enum { .. } => std::sync::Arc::new(
+                values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => Ok(None),
+                        Value::Text(value) => Ok(Some(value.as_str())),
+                        Value::Enum(value) => Ok(Some(value.as_str())),
+                        other => Err(invalid(other)),
+                    })
+                    .collect::<Result<DictionaryArray<Int8Type>>>()?,
+            ),
+        })
+    }
+
+    fn field(column: &SinkColumn) -> Field {
+        let data_type = match column.column_type {
+            SinkColumnType::BigInt => DataType::Int64,
+            SinkColumnType::UBigInt => DataType::UInt64,
+            SinkColumnType::Double => DataType::Float64,
+            SinkColumnType::Boolean => DataType::Boolean,
+            SinkColumnType::Varchar => DataType::Utf8,
+            SinkColumnType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            SinkColumnType::Enum { .. } => {
+                DataType::Dictionary(Box::new(DataType::Int8), Box::new(DataType::Utf8))
+            },
+        };
+        Field::new(column.name, data_type, !column.primary_key)
+    }
+}
+
+impl OutputSink for ArrowIpcSink {
+    fn create_dimension_table(&mut self, _table: &SinkTable) -> Result<()> {
+        // Dimension tables are written in one batch by `append_rows`.
+        Ok(())
+    }
+
+    fn append_rows(&mut self, table: &SinkTable, rows: &mut SinkRows<'_>) -> Result<()> {
+        let mut columns = vec![Vec::new(); table.columns.len()];
+        let mut num_rows = 0;
+        for row in rows {
+            for (column, value) in columns.iter_mut().zip(row?) {
+                column.push(value);
+            }
+            num_rows += 1;
+        }
+        let schema = std::sync::Arc::new(Schema::new(
+            table.columns.iter().map(Self::field).collect::<Vec<_>>(),
+        ));
+        let arrays = table
+            .columns
+            .iter()
+            .zip(columns)
+            .map(|(column, values)| Self::array(column, values))
+            .collect::<Result<Vec<_>>>()?;
+        let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+
+        let path = self.table_path(table.schema, &table.name);
+        let mut writer = self.file_writer(&path, &schema)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+        self.tables.push(ArrowIpcTable {
+            schema: table.schema.to_string(),
+            name: table.name.clone(),
+            path,
+            rows: num_rows,
+        });
+        Ok(())
+    }
+
+    fn write_data_batch(&mut self, table_name: &str, batch: &RecordBatch) -> Result<()> {
+        if self
+            .data_writer
+            .as_ref()
+            .is_none_or(|(current, _)| current != table_name)
+        {
+            self.finish_data_table()?;
+            let path = self.table_path("data", table_name);
+            let writer = self.file_writer(&path, &batch.schema())?;
+            self.data_writer = Some((table_name.to_string(), writer));
+            self.tables.push(ArrowIpcTable {
+                schema: "data".to_string(),
+                name: table_name.to_string(),
+                path,
+                rows: 0,
+            });
+        }
+        let (_, writer) = self
+            .data_writer
+            .as_mut()
+            .expect("data writer was just opened");
+        writer.write(batch)?;
+        if let Some(table) = self.tables.last_mut() {
+            table.rows += batch.num_rows();
+        }
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<()> {
+        self.finish_data_table()
+    }
+}
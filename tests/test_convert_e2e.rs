@@ -97,6 +97,7 @@ const EXPECTED_RAW_TABLES: &[(&str, &[&str])] = &[
             "category_id",
             "index",
             "is_show",
+            "guid",
         ],
     ),
     (
@@ -161,6 +162,10 @@ const EXPECTED_RAW_TABLES: &[(&str, &[&str])] = &[
     ),
 ];
 
+const LT_FIXTURE: &str = "Model_Base_LT_Solution.zip";
+const BATTERY_GENERATION: &str = "^LT__Interval__Batteries__Generation$";
+const BATTERY_GENERATION_AND_LOAD: &str = "^LT__Interval__Batteries__(Generation|Load)$";
+
 #[fixture]
 fn fixture_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/plexos-solution-files")
@@ -217,6 +222,36 @@ fn run_convert_with_args(source_path: &Path, output_path: &Path, extra_args: &[&
     );
 }
 
+/// Convert the LT fixture to `file_name` in `temp_dir`, keeping the data
+/// tables that match `pattern`.
+fn convert_lt_fixture(
+    temp_dir: &tempfile::TempDir,
+    file_name: &str,
+    pattern: &str,
+    extra_args: &[&str],
+) -> PathBuf {
+    let output_path = temp_dir.path().join(file_name);
+    let mut args = vec!["--table-name-pattern", pattern];
+    args.extend_from_slice(extra_args);
+    run_convert_with_args(&fixture_dir().join(LT_FIXTURE), &output_path, &args);
+    output_path
+}
+
+fn run_plexos2duckdb(args: &[&str]) -> std::process::Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_plexos2duckdb"))
+        .args(args)
+        .output()
+        .expect("run plexos2duckdb");
+    assert!(
+        output.status.success(),
+        "plexos2duckdb {} failed\nstdout:\n{}\nstderr:\n{}",
+        args.first().copied().unwrap_or_default(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
 fn open_connection(db_path: &Path) -> Connection {
     Connection::open(db_path).expect("open generated duckdb file")
 }
@@ -562,6 +597,495 @@ fn convert_external_data_parquet_dir_creates_parquet_backed_data_views() {
     assert_eq!(count, 144, "unexpected external data view row count");
 }
 
+#[test]
+fn convert_downsample_writes_aggregated_external_parquet_data() {
+    let temp_dir = temp_dir();
+    let external_dir = temp_dir.path().join("external-data");
+    let interval_path = convert_lt_fixture(&temp_dir, "interval.duckdb", BATTERY_GENERATION, &[]);
+    let output_path = convert_lt_fixture(
+        &temp_dir,
+        "day.duckdb",
+        BATTERY_GENERATION,
+        &[
+            "--downsample",
+            "day",
+            "--external-data-parquet-dir",
+            external_dir.to_str().expect("external path utf8"),
+        ],
+    );
+
+    let table = "LT__Interval_to_Day__Batteries__Generation";
+    let parquet_path = external_dir
+        .join("data")
+        .join(table)
+        .join(format!("{table}.part-00001.parquet"));
+    assert!(
+        parquet_path.exists(),
+        "expected downsampled parquet file at {}",
+        parquet_path.display()
+    );
+
+    let con = open_connection(&output_path);
+    assert_eq!(fetch_table_names(&con, "data", "VIEW"), vec![table]);
+    let aggregation: String = con
+        .query_row(
+            "SELECT aggregation FROM main.plexos2duckdb_downsampled_tables WHERE table_name = ?;",
+            [table],
+            |row| row.get(0),
+        )
+        .expect("downsampled table aggregation");
+    let aggregate = match aggregation.as_str() {
+        "mean" => "AVG",
+        "sum" => "SUM",
+        "min" => "MIN",
+        "max" => "MAX",
+        other => panic!("unexpected aggregation {other}"),
+    };
+
+    con.execute_batch(&format!(
+        "ATTACH '{}' AS interval_db (READ_ONLY);",
+        interval_path.display()
+    ))
+    .expect("attach interval database");
+    let mismatches: i64 = con
+        .query_row(
+            &format!(
+                "WITH expected AS (
+                   SELECT t.key_id, t.sample_id, t.band_id, day.interval_id AS block_id,
+                          {aggregate}(t.value) AS value
+                   FROM interval_db.data.LT__Interval__Batteries__Generation t
+                   JOIN interval_db.raw.timestamp_block_LT__Interval i
+                     ON i.interval_id = t.block_id
+                   JOIN raw.timestamp_block_LT__Interval_to_Day day
+                     ON day.datetime = i.datetime
+                   GROUP BY ALL
+                 ),
+                 actual AS (
+                   SELECT key_id, sample_id, band_id, block_id, value
+                   FROM read_parquet('{}')
+                 )
+                 SELECT COUNT(*)
+                 FROM expected e
+                 FULL OUTER JOIN actual a
+                   USING (key_id, sample_id, band_id, block_id)
+                 WHERE e.value IS NULL OR a.value IS NULL
+                    OR abs(e.value - a.value) > 1e-9 * greatest(1, abs(e.value));",
+                parquet_path.display()
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .expect("compare downsampled values");
+    assert_eq!(mismatches, 0, "downsampled parquet values differ");
+
+    let rows: i64 = con
+        .query_row(&format!("SELECT COUNT(*) FROM data.{table};"), [], |row| {
+            row.get(0)
+        })
+        .expect("count downsampled rows");
+    assert!(rows > 0, "expected downsampled rows in {table}");
+}
+
+#[test]
+fn convert_external_data_parquet_hive_layout_partitions_by_table_name() {
+    let temp_dir = temp_dir();
+    let external_dir = temp_dir.path().join("external-data");
+    let output_path = convert_lt_fixture(
+        &temp_dir,
+        "hive.duckdb",
+        BATTERY_GENERATION_AND_LOAD,
+        &[
+            "--external-data-parquet-dir",
+            external_dir.to_str().expect("external path utf8"),
+            "--external-data-parquet-layout",
+            "hive",
+        ],
+    );
+
+    for property in ["Generation", "Load"] {
+        let expected_parquet_path = external_dir
+            .join("data")
+            .join("phase=LT")
+            .join("period=Interval")
+            .join("collection=Batteries")
+            .join(format!("property={property}"))
+            .join("part-00001.parquet");
+        assert!(
+            expected_parquet_path.exists(),
+            "expected hive partitioned parquet file at {}",
+            expected_parquet_path.display()
+        );
+    }
+
+    let con = open_connection(&output_path);
+    assert_eq!(
+        fetch_table_names(&con, "data", "VIEW"),
+        vec![
+            "LT__Interval__Batteries__Generation".to_string(),
+            "LT__Interval__Batteries__Load".to_string(),
+        ],
+        "unexpected external data view set",
+    );
+    assert_columns_exact(
+        &fetch_column_names(&con, "data", "LT__Interval__Batteries__Generation"),
+        EXPECTED_DATA_COLUMNS,
+        "data.LT__Interval__Batteries__Generation",
+    );
+
+    let count: i64 = con
+        .query_row(
+            "SELECT COUNT(*) FROM main.plexos2duckdb_external_data
+             WHERE phase = 'LT' AND collection = 'Batteries' AND property = 'Generation'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count hive dataset rows");
+    assert_eq!(count, 144, "unexpected hive dataset row count");
+}
+
+#[test]
+fn convert_parquet_format_writes_folder_without_duckdb_file() {
+    let temp_dir = temp_dir();
+    let output_dir = convert_lt_fixture(
+        &temp_dir,
+        "parquet-output",
+        BATTERY_GENERATION_AND_LOAD,
+        &["--format", "parquet"],
+    );
+
+    assert!(
+        output_dir.join("manifest.json").exists(),
+        "expected manifest.json"
+    );
+    assert!(output_dir.join("views.sql").exists(), "expected views.sql");
+    assert!(output_dir.join("raw").join("objects.parquet").exists());
+    assert!(output_dir.join("raw").join("memberships.parquet").exists());
+    assert!(
+        std::fs::read_dir(temp_dir.path())
+            .expect("read temp dir")
+            .all(|entry| {
+                entry
+                    .expect("read temp dir entry")
+                    .path()
+                    .extension()
+                    .is_none_or(|ext| ext != "duckdb")
+            }),
+        "parquet output must not create a DuckDB file"
+    );
+
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(output_dir.join("manifest.json")).expect("read manifest"),
+    )
+    .expect("parse manifest");
+    let data_tables = manifest["tables"]
+        .as_array()
+        .expect("manifest tables")
+        .iter()
+        .filter(|table| table["schema"] == "data")
+        .map(|table| table["name"].as_str().expect("table name").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        data_tables,
+        vec![
+            "LT__Interval__Batteries__Generation".to_string(),
+            "LT__Interval__Batteries__Load".to_string(),
+        ],
+    );
+
+    let con = Connection::open_in_memory().expect("open in-memory duckdb");
+    con.execute_batch(&format!(
+        "SET file_search_path = '{}';",
+        output_dir.to_str().expect("output path utf8")
+    ))
+    .expect("set file search path");
+    con.execute_batch(
+        &std::fs::read_to_string(output_dir.join("views.sql")).expect("read views.sql"),
+    )
+    .expect("run views.sql");
+    assert_report_view_shape(&con, "LT__Interval__Batteries__Generation", "Generation");
+
+    let count: i64 = con
+        .query_row(
+            "SELECT COUNT(*) FROM report.\"LT__Interval__Batteries__Generation\"",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count report view rows");
+    assert_eq!(count, 144, "unexpected report view row count");
+}
+
+#[test]
+fn export_writes_selected_report_views() {
+    let temp_dir = temp_dir();
+    let output_path = convert_lt_fixture(&temp_dir, "lt.duckdb", BATTERY_GENERATION_AND_LOAD, &[]);
+
+    let export_dir = temp_dir.path().join("export");
+    run_plexos2duckdb(&[
+        "export",
+        "--input",
+        output_path.to_str().expect("output path utf8"),
+        "--view",
+        "LT__Interval__Batteries__*",
+        "--format",
+        "jsonl",
+        "--out",
+        export_dir.to_str().expect("export path utf8"),
+    ]);
+
+    for property in ["Generation", "Load"] {
+        let path = export_dir.join(format!("report.LT__Interval__Batteries__{property}.jsonl"));
+        let lines = std::fs::read_to_string(&path).expect("read exported jsonl");
+        assert_eq!(
+            lines.lines().count(),
+            144,
+            "unexpected row count in {}",
+            path.display()
+        );
+        let first: serde_json::Value =
+            serde_json::from_str(lines.lines().next().expect("first line")).expect("parse jsonl");
+        assert!(first.get(property).is_some(), "missing {property} column");
+    }
+
+    let csv_path = temp_dir.path().join("generation.csv");
+    run_plexos2duckdb(&[
+        "export",
+        "--input",
+        output_path.to_str().expect("output path utf8"),
+        "--view",
+        "report.LT__Interval__Batteries__Generation",
+        "--out",
+        csv_path.to_str().expect("csv path utf8"),
+    ]);
+    let csv = std::fs::read_to_string(&csv_path).expect("read exported csv");
+    assert_eq!(csv.lines().count(), 145, "expected header plus 144 rows");
+}
+
+#[test]
+fn query_runs_read_only_sql_with_row_limit() {
+    let temp_dir = temp_dir();
+    let output_path = convert_lt_fixture(&temp_dir, "lt.duckdb", BATTERY_GENERATION, &[]);
+
+    let output = run_plexos2duckdb(&[
+        "query",
+        "--input",
+        output_path.to_str().expect("output path utf8"),
+        "--format",
+        "csv",
+        "--limit",
+        "3",
+        "SELECT band, name, \"Generation\" FROM report.\"LT__Interval__Batteries__Generation\"",
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines.len(),
+        4,
+        "expected header plus 3 rows, got:\n{stdout}"
+    );
+    assert_eq!(lines[0], "band,name,Generation");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_plexos2duckdb"))
+        .args([
+            "query",
+            "--input",
+            output_path.to_str().expect("output path utf8"),
+            "CREATE TABLE main.scratch AS SELECT 1",
+        ])
+        .output()
+        .expect("run plexos2duckdb query");
+    assert!(
+        !output.status.success(),
+        "writes must fail on the default read-only connection"
+    );
+}
+
+#[test]
+fn read_plexos_table_function_matches_converted_data() {
+    let temp_dir = temp_dir();
+    let source_path = fixture_dir().join(LT_FIXTURE);
+    let output_path = convert_lt_fixture(&temp_dir, "lt.duckdb", BATTERY_GENERATION, &[]);
+    let table = "LT__Interval__Batteries__Generation";
+
+    let converted = open_connection(&output_path);
+    let expected: (i64, f64) = converted
+        .query_row(
+            &format!("SELECT COUNT(*), SUM(value) FROM \"data\".\"{table}\""),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("summarize converted data table");
+
+    let con = Connection::open_in_memory().expect("open in-memory duckdb");
+    plexos2duckdb::register_table_functions(&con).expect("register table functions");
+    let source = source_path
+        .to_str()
+        .expect("source path utf8")
+        .replace('\'', "''");
+
+    let (keys, values): (i64, i64) = con
+        .query_row(
+            &format!(
+                "SELECT keys, \"values\" FROM plexos_tables('{source}') WHERE table_name = '{table}'"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("list data tables");
+    assert!(keys > 0, "expected keys for {table}");
+    assert_eq!(values, expected.0);
+
+    let actual: (i64, f64) = con
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), SUM(value) FROM read_plexos('{source}', table := '{table}')"
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("scan data table");
+    assert_eq!(actual.0, expected.0);
+    assert!((actual.1 - expected.1).abs() <= 1e-6 * expected.1.abs().max(1.0));
+
+    let names: i64 = con
+        .query_row(
+            &format!(
+                "SELECT COUNT(DISTINCT name) FROM read_plexos('{source}', table := '{table}', dimensions := true)"
+            ),
+            [],
+            |row| row.get(0),
+        )
+        .expect("scan data table with dimensions");
+    assert!(names > 0, "expected object names with dimensions := true");
+
+    let err = con
+        .query_row(
+            &format!("SELECT COUNT(*) FROM read_plexos('{source}', table := 'missing')"),
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .expect_err("unknown tables fail at bind time");
+    assert!(err.to_string().contains("missing"), "{err}");
+}
+
+#[test]
+fn diff_reports_tables_and_value_differences_between_databases() {
+    let temp_dir = temp_dir();
+    let base_path = convert_lt_fixture(&temp_dir, "base.duckdb", BATTERY_GENERATION, &[]);
+    let other_path =
+        convert_lt_fixture(&temp_dir, "other.duckdb", BATTERY_GENERATION_AND_LOAD, &[]);
+
+    let diff_path = temp_dir.path().join("diff.duckdb");
+    let output = run_plexos2duckdb(&[
+        "diff",
+        "--base",
+        base_path.to_str().expect("base path utf8"),
+        "--other",
+        other_path.to_str().expect("other path utf8"),
+        "--out",
+        diff_path.to_str().expect("diff path utf8"),
+        "--format-diagnostics",
+        "json",
+    ]);
+
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("parse diff json");
+    let only_other = report["tables"]
+        .as_array()
+        .expect("diff tables")
+        .iter()
+        .filter(|row| row["status"] == "only_other")
+        .map(|row| {
+            format!(
+                "{}.{}",
+                row["schema"].as_str().unwrap(),
+                row["table"].as_str().unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        only_other,
+        vec![
+            "data.LT__Interval__Batteries__Load".to_string(),
+            "report.LT__Interval__Batteries__Load".to_string(),
+        ]
+    );
+    assert_eq!(report["objects"].as_array().expect("diff objects").len(), 0);
+
+    let data = report["data"].as_array().expect("diff data");
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0]["table"], "LT__Interval__Batteries__Generation");
+    assert_eq!(data[0]["matched_rows"], 144);
+    assert_eq!(data[0]["max_abs_delta"], 0.0);
+
+    let con = open_connection(&diff_path);
+    assert_eq!(
+        fetch_table_names(&con, "diff", "BASE TABLE"),
+        vec![
+            "data_summary".to_string(),
+            "data_top_objects".to_string(),
+            "memberships".to_string(),
+            "objects".to_string(),
+            "tables".to_string(),
+            "units".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn merge_combines_scenarios_with_shared_dimensions() {
+    let temp_dir = temp_dir();
+    let base_path = convert_lt_fixture(&temp_dir, "base.duckdb", BATTERY_GENERATION, &[]);
+    let change_path = convert_lt_fixture(&temp_dir, "change.duckdb", BATTERY_GENERATION, &[]);
+
+    let merged_path = temp_dir.path().join("merged.duckdb");
+    run_plexos2duckdb(&[
+        "merge",
+        "--input",
+        base_path.to_str().expect("base path utf8"),
+        change_path.to_str().expect("change path utf8"),
+        "--output",
+        merged_path.to_str().expect("merged path utf8"),
+    ]);
+
+    let con = open_connection(&merged_path);
+    let metadata = fetch_metadata(&con);
+    assert_eq!(
+        metadata.get("scenarios").map(String::as_str),
+        Some("base, change")
+    );
+
+    let scenarios: Vec<String> = con
+        .prepare("SELECT scenario FROM main.scenarios ORDER BY scenario")
+        .expect("prepare scenarios")
+        .query_map([], |row| row.get(0))
+        .expect("query scenarios")
+        .collect::<Result<_, _>>()
+        .expect("collect scenarios");
+    assert_eq!(scenarios, vec!["base".to_string(), "change".to_string()]);
+
+    let count = |sql: &str| -> i64 { con.query_row(sql, [], |row| row.get(0)).expect(sql) };
+    let base = open_connection(&base_path);
+    let base_count = |sql: &str| -> i64 { base.query_row(sql, [], |row| row.get(0)).expect(sql) };
+    assert_eq!(
+        count("SELECT COUNT(*) FROM raw.classes"),
+        base_count("SELECT COUNT(*) FROM raw.classes")
+    );
+    assert_eq!(
+        count("SELECT COUNT(*) FROM raw.objects"),
+        2 * base_count("SELECT COUNT(*) FROM raw.objects")
+    );
+    assert_eq!(
+        count(
+            "SELECT COUNT(*) FROM report.LT__Interval__Batteries__Generation WHERE scenario = 'change'"
+        ),
+        base_count("SELECT COUNT(*) FROM report.LT__Interval__Batteries__Generation")
+    );
+    assert_eq!(
+        fetch_column_names(&con, "data", "LT__Interval__Batteries__Generation")[0],
+        "scenario"
+    );
+}
+
 #[test]
 fn convert_filters_generated_data_tables_by_pattern() {
     let fixture_name = "Model_Base_LT_Solution.zip";
@@ -602,6 +1126,132 @@ fn convert_filters_generated_data_tables_by_pattern() {
     assert_eq!(count, 144, "unexpected filtered data row count");
 }
 
+#[test]
+fn convert_filters_keys_by_class_and_property_and_trims_dimensions() {
+    let fixture_name = "Model_Base_LT_Solution.zip";
+    let fixture_dir = fixture_dir();
+    let temp_dir = temp_dir();
+    let source_path = fixture_dir.join(fixture_name);
+    let output_path = generated_output_path(&temp_dir, fixture_name);
+
+    run_convert_with_args(
+        &source_path,
+        &output_path,
+        &[
+            "--class",
+            "Battery",
+            "--property",
+            "Generation",
+            "--property",
+            "re:^Load$",
+            "--trim-dimensions",
+        ],
+    );
+    let con = open_connection(&output_path);
+
+    let tables = fetch_table_names(&con, "data", "BASE TABLE");
+    assert!(!tables.is_empty(), "expected filtered data tables");
+    for table in &tables {
+        assert!(
+            table.ends_with("__Generation") || table.ends_with("__Load"),
+            "unexpected data table {table}"
+        );
+    }
+    assert_eq!(fetch_table_names(&con, "report", "VIEW"), tables);
+
+    let unexpected_keys: i64 = con
+        .query_row(
+            "SELECT COUNT(*) FROM raw.keys k
+             JOIN raw.memberships m ON k.membership_id = m.membership_id
+             WHERE m.child_class_name <> 'Battery'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count keys outside the class filter");
+    assert_eq!(
+        unexpected_keys, 0,
+        "trimmed keys should only reference batteries"
+    );
+
+    let orphan_objects: i64 = con
+        .query_row(
+            "SELECT COUNT(*) FROM raw.objects
+             WHERE object_id NOT IN (SELECT parent_id FROM raw.memberships)
+               AND object_id NOT IN (SELECT child_id FROM raw.memberships)",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count objects without memberships");
+    assert_eq!(
+        orphan_objects, 0,
+        "trimmed objects should all be referenced"
+    );
+}
+
+#[test]
+fn convert_limits_values_to_time_window_samples_and_bands() {
+    let temp_dir = temp_dir();
+    let full_path = convert_lt_fixture(&temp_dir, "full.duckdb", BATTERY_GENERATION, &[]);
+    let full = open_connection(&full_path);
+    let (from, to): (String, String) = full
+        .query_row(
+            "SELECT strftime(MIN(timestamp), '%Y-%m-%d %H:%M:%S'),
+                    strftime(MIN(timestamp) + INTERVAL 1 DAY, '%Y-%m-%d %H:%M:%S')
+             FROM report.LT__Interval__Batteries__Generation",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read first timestamp");
+    let (sample_id, band_id): (i64, i64) = full
+        .query_row(
+            "SELECT MIN(sample_id), MIN(band_id) FROM data.LT__Interval__Batteries__Generation",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read first sample and band");
+    let expected: i64 = full
+        .query_row(
+            "SELECT COUNT(*) FROM data.LT__Interval__Batteries__Generation d
+             JOIN processed.timestamp_block_LT__Interval p ON d.block_id = p.block_id
+             WHERE p.datetime >= CAST(? AS TIMESTAMP) AND p.datetime < CAST(? AS TIMESTAMP)
+               AND d.sample_id = ? AND d.band_id = ?",
+            duckdb::params![from, to, sample_id, band_id],
+            |row| row.get(0),
+        )
+        .expect("count rows inside the window");
+
+    let (sample_id, band_id) = (sample_id.to_string(), band_id.to_string());
+    let window_path = convert_lt_fixture(
+        &temp_dir,
+        "window.duckdb",
+        BATTERY_GENERATION,
+        &[
+            "--from",
+            &from,
+            "--to",
+            &to,
+            "--samples",
+            &sample_id,
+            "--bands",
+            &band_id,
+        ],
+    );
+    let con = open_connection(&window_path);
+    let (rows, min_timestamp, max_timestamp): (i64, String, String) = con
+        .query_row(
+            "SELECT COUNT(*), strftime(MIN(timestamp), '%Y-%m-%d %H:%M:%S'),
+                    strftime(MAX(timestamp), '%Y-%m-%d %H:%M:%S')
+             FROM report.LT__Interval__Batteries__Generation",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .expect("read windowed rows");
+    assert_eq!(rows, expected, "unexpected windowed row count");
+    assert!(rows > 0, "expected rows inside the window");
+    assert!(min_timestamp >= from, "{min_timestamp} is before {from}");
+    assert!(max_timestamp < to, "{max_timestamp} is not before {to}");
+}
+
 #[test]
 fn lt_solution_preserves_observed_table_shapes() {
     let fixture_name = "Model_Base_LT_Solution.zip";